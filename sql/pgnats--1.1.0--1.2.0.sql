@@ -0,0 +1,863 @@
+ALTER TABLE pgnats.subscriptions
+    ADD COLUMN queue_group TEXT,
+    ADD COLUMN max_retries INT NOT NULL DEFAULT 0,
+    ADD COLUMN dead_letter_subject TEXT,
+    ADD COLUMN dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN batch_size INT NOT NULL DEFAULT 1,
+    ADD COLUMN batch_linger_ms BIGINT NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS pgnats.kv_watches (
+    bucket TEXT NOT NULL,
+    key_pattern TEXT NOT NULL,
+    callback TEXT NOT NULL,
+    last_revision BIGINT,
+    UNIQUE(bucket, key_pattern, callback)
+);
+
+CREATE TABLE IF NOT EXISTS pgnats.object_watches (
+    store TEXT NOT NULL,
+    callback TEXT NOT NULL,
+    last_modified_ns BIGINT,
+    UNIQUE(store, callback)
+);
+
+CREATE TABLE IF NOT EXISTS pgnats.stream_subscriptions (
+    stream TEXT NOT NULL,
+    consumer TEXT NOT NULL,
+    callback TEXT NOT NULL,
+    max_retries INT NOT NULL DEFAULT 5,
+    dead_letter_subject TEXT,
+    dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+    UNIQUE(stream, consumer, callback)
+);
+
+CREATE TABLE IF NOT EXISTS pgnats.responders (
+    subject TEXT NOT NULL,
+    callback TEXT NOT NULL,
+    queue_group TEXT,
+    UNIQUE(subject)
+);
+
+CREATE TABLE IF NOT EXISTS pgnats.services (
+    name TEXT NOT NULL,
+    version TEXT NOT NULL,
+    description TEXT,
+    UNIQUE(name)
+);
+
+CREATE TABLE IF NOT EXISTS pgnats.service_endpoints (
+    service TEXT NOT NULL REFERENCES pgnats.services(name) ON DELETE CASCADE,
+    endpoint TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    callback TEXT NOT NULL,
+    UNIQUE(service, endpoint)
+);
+
+CREATE TABLE IF NOT EXISTS pgnats.dead_letters (
+    id BIGSERIAL PRIMARY KEY,
+    subject TEXT NOT NULL,
+    callback TEXT NOT NULL,
+    payload BYTEA NOT NULL,
+    headers JSONB,
+    error TEXT NOT NULL,
+    attempts INT NOT NULL,
+    failed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE OR REPLACE FUNCTION pgnats.cleanup_subscriptions_on_drop()
+RETURNS event_trigger AS $$
+DECLARE
+    obj record;
+    clean_name TEXT;
+BEGIN
+    FOR obj IN
+        SELECT * FROM pg_event_trigger_dropped_objects()
+    LOOP
+        IF obj.object_type = 'function' THEN
+            clean_name := split_part(obj.object_identity, '(', 1);
+            DELETE FROM pgnats.subscriptions
+            WHERE callback = clean_name;
+            DELETE FROM pgnats.kv_watches
+            WHERE callback = clean_name;
+            DELETE FROM pgnats.object_watches
+            WHERE callback = clean_name;
+            DELETE FROM pgnats.stream_subscriptions
+            WHERE callback = clean_name;
+            DELETE FROM pgnats.responders
+            WHERE callback = clean_name;
+            DELETE FROM pgnats.service_endpoints
+            WHERE callback = clean_name;
+        END IF;
+    END LOOP;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP FUNCTION "nats_subscribe"(TEXT, oid);
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1852
+-- pgnats::api::nats::nats_subscribe
+CREATE  FUNCTION "nats_subscribe"(
+	"subject" TEXT, /* alloc::string::String */
+	"fn_oid" oid, /* pgrx_pg_sys::submodules::oids::Oid */
+	"queue_group" TEXT DEFAULT NULL, /* core::option::Option<alloc::string::String> */
+	"max_retries" INT DEFAULT 0, /* i32 */
+	"dead_letter_subject" TEXT DEFAULT NULL, /* core::option::Option<alloc::string::String> */
+	"dead_letter_table" bool DEFAULT false, /* bool */
+	"batch_size" INT DEFAULT 1, /* i32 */
+	"batch_linger" interval DEFAULT NULL /* core::option::Option<pgrx::datum::interval::Interval> */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_subscribe_wrapper';
+/* </end connected objects> */
+
+DROP FUNCTION "nats_get_file_list"(TEXT);
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1529
+-- pgnats::api::nats::nats_get_file_list
+CREATE  FUNCTION "nats_get_file_list"(
+	"store" TEXT, /* alloc::string::String */
+	"prefix" TEXT DEFAULT NULL, /* core::option::Option<&str> */
+	"include_deleted" bool DEFAULT false /* bool */
+) RETURNS TABLE (
+	"name" TEXT,  /* alloc::string::String */
+	"description" TEXT,  /* core::option::Option<alloc::string::String> */
+	"metadata" jsonb,  /* pgrx::datum::json::JsonB */
+	"headers" jsonb,  /* core::option::Option<pgrx::datum::json::JsonB> */
+	"options" jsonb,  /* core::option::Option<pgrx::datum::json::JsonB> */
+	"bucket" TEXT,  /* alloc::string::String */
+	"nuid" TEXT,  /* alloc::string::String */
+	"size" bigint,  /* i64 */
+	"chunks" bigint,  /* i64 */
+	"modified" TEXT,  /* core::option::Option<alloc::string::String> */
+	"digest" TEXT,  /* core::option::Option<alloc::string::String> */
+	"delete" bool  /* bool */
+)
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_get_file_list_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1141
+-- pgnats::api::nats::nats_ack
+CREATE  FUNCTION "nats_ack"(
+	"reply" TEXT /* &str */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_ack_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:930
+-- pgnats::api::nats::nats_consumer_create
+CREATE  FUNCTION "nats_consumer_create"(
+	"stream" TEXT, /* &str */
+	"config" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS TABLE (
+	"stream" TEXT,  /* alloc::string::String */
+	"name" TEXT,  /* alloc::string::String */
+	"config" jsonb,  /* pgrx::datum::json::JsonB */
+	"created" timestamp with time zone,  /* pgrx::datum::time_stamp_with_timezone::TimestampWithTimeZone */
+	"delivered_stream_seq" bigint,  /* i64 */
+	"delivered_consumer_seq" bigint,  /* i64 */
+	"ack_floor_stream_seq" bigint,  /* i64 */
+	"ack_floor_consumer_seq" bigint,  /* i64 */
+	"num_pending" bigint,  /* i64 */
+	"num_ack_pending" bigint,  /* i64 */
+	"num_redelivered" bigint,  /* i64 */
+	"num_waiting" bigint  /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_consumer_create_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:975
+-- pgnats::api::nats::nats_consumer_delete
+CREATE  FUNCTION "nats_consumer_delete"(
+	"stream" TEXT, /* &str */
+	"name" TEXT /* &str */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_consumer_delete_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:998
+-- pgnats::api::nats::nats_consumer_info
+CREATE  FUNCTION "nats_consumer_info"(
+	"stream" TEXT, /* &str */
+	"name" TEXT /* &str */
+) RETURNS TABLE (
+	"stream" TEXT,  /* alloc::string::String */
+	"name" TEXT,  /* alloc::string::String */
+	"config" jsonb,  /* pgrx::datum::json::JsonB */
+	"created" timestamp with time zone,  /* pgrx::datum::time_stamp_with_timezone::TimestampWithTimeZone */
+	"delivered_stream_seq" bigint,  /* i64 */
+	"delivered_consumer_seq" bigint,  /* i64 */
+	"ack_floor_stream_seq" bigint,  /* i64 */
+	"ack_floor_consumer_seq" bigint,  /* i64 */
+	"num_pending" bigint,  /* i64 */
+	"num_ack_pending" bigint,  /* i64 */
+	"num_redelivered" bigint,  /* i64 */
+	"num_waiting" bigint  /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_consumer_info_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1041
+-- pgnats::api::nats::nats_consumer_list
+CREATE  FUNCTION "nats_consumer_list"(
+	"stream" TEXT /* &str */
+) RETURNS TABLE (
+	"stream" TEXT,  /* alloc::string::String */
+	"name" TEXT,  /* alloc::string::String */
+	"config" jsonb,  /* pgrx::datum::json::JsonB */
+	"created" timestamp with time zone,  /* pgrx::datum::time_stamp_with_timezone::TimestampWithTimeZone */
+	"delivered_stream_seq" bigint,  /* i64 */
+	"delivered_consumer_seq" bigint,  /* i64 */
+	"ack_floor_stream_seq" bigint,  /* i64 */
+	"ack_floor_consumer_seq" bigint,  /* i64 */
+	"num_pending" bigint,  /* i64 */
+	"num_ack_pending" bigint,  /* i64 */
+	"num_redelivered" bigint,  /* i64 */
+	"num_waiting" bigint  /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_consumer_list_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1088
+-- pgnats::api::nats::nats_fetch
+CREATE  FUNCTION "nats_fetch"(
+	"stream" TEXT, /* &str */
+	"consumer" TEXT, /* &str */
+	"batch" INT, /* i32 */
+	"expires" interval DEFAULT NULL /* core::option::Option<pgrx::datum::interval::Interval> */
+) RETURNS TABLE (
+	"subject" TEXT,  /* alloc::string::String */
+	"payload" bytea,  /* alloc::vec::Vec<u8> */
+	"headers" jsonb,  /* core::option::Option<pgrx::datum::json::JsonB> */
+	"stream_seq" bigint,  /* i64 */
+	"consumer_seq" bigint,  /* i64 */
+	"delivered" bigint,  /* i64 */
+	"published" timestamp with time zone,  /* pgrx::datum::time_stamp_with_timezone::TimestampWithTimeZone */
+	"reply" TEXT  /* core::option::Option<alloc::string::String> */
+)
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_fetch_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1243
+-- pgnats::api::nats::nats_get_file_range
+CREATE  FUNCTION "nats_get_file_range"(
+	"store" TEXT, /* alloc::string::String */
+	"name" TEXT, /* &str */
+	"offset" bigint, /* i64 */
+	"length" bigint /* i64 */
+) RETURNS bytea /* core::result::Result<alloc::vec::Vec<u8>, anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_get_file_range_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1415
+-- pgnats::api::nats::nats_get_file_to_lo
+CREATE  FUNCTION "nats_get_file_to_lo"(
+	"store" TEXT, /* alloc::string::String */
+	"name" TEXT /* &str */
+) RETURNS oid /* core::result::Result<pgrx_pg_sys::submodules::oids::Oid, anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_get_file_to_lo_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1201
+-- pgnats::api::nats::nats_in_progress
+CREATE  FUNCTION "nats_in_progress"(
+	"reply" TEXT /* &str */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_in_progress_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:592
+-- pgnats::api::nats::nats_jetstream_account_info
+CREATE  FUNCTION "nats_jetstream_account_info"() RETURNS TABLE (
+	"memory" bigint,  /* i64 */
+	"storage" bigint,  /* i64 */
+	"reserved_memory" bigint,  /* i64 */
+	"reserved_storage" bigint,  /* i64 */
+	"streams" bigint,  /* i64 */
+	"consumers" bigint,  /* i64 */
+	"max_memory" bigint,  /* core::option::Option<i64> */
+	"max_storage" bigint,  /* core::option::Option<i64> */
+	"max_streams" bigint,  /* core::option::Option<i64> */
+	"max_consumers" bigint,  /* core::option::Option<i64> */
+	"domain" TEXT,  /* core::option::Option<alloc::string::String> */
+	"api_total" bigint,  /* i64 */
+	"api_errors" bigint  /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_jetstream_account_info_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:360
+-- pgnats::api::nats::nats_kv_entry
+CREATE  FUNCTION "nats_kv_entry"(
+	"bucket" TEXT, /* alloc::string::String */
+	"key" TEXT /* &str */
+) RETURNS TABLE (
+	"value" bytea,  /* alloc::vec::Vec<u8> */
+	"revision" bigint,  /* i64 */
+	"created" timestamp with time zone,  /* pgrx::datum::time_stamp_with_timezone::TimestampWithTimeZone */
+	"delta" bigint,  /* i64 */
+	"operation" TEXT  /* alloc::string::String */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_kv_entry_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:501
+-- pgnats::api::nats::nats_kv_get_many
+CREATE  FUNCTION "nats_kv_get_many"(
+	"bucket" TEXT, /* alloc::string::String */
+	"keys" TEXT[] /* alloc::vec::Vec<alloc::string::String> */
+) RETURNS TABLE (
+	"key" TEXT,  /* alloc::string::String */
+	"value" bytea,  /* core::option::Option<alloc::vec::Vec<u8>> */
+	"revision" bigint  /* core::option::Option<i64> */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_kv_get_many_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:422
+-- pgnats::api::nats::nats_kv_purge
+CREATE  FUNCTION "nats_kv_purge"(
+	"bucket" TEXT, /* alloc::string::String */
+	"key" TEXT /* &str */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_kv_purge_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:448
+-- pgnats::api::nats::nats_kv_put_many
+CREATE  FUNCTION "nats_kv_put_many"(
+	"bucket" TEXT, /* alloc::string::String */
+	"values" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS TABLE (
+	"key" TEXT,  /* alloc::string::String */
+	"revision" bigint  /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_kv_put_many_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:2470
+-- pgnats::api::nats::nats_kv_unwatch
+CREATE  FUNCTION "nats_kv_unwatch"(
+	"bucket" TEXT, /* alloc::string::String */
+	"key_pattern" TEXT, /* alloc::string::String */
+	"fn_oid" oid /* pgrx_pg_sys::submodules::oids::Oid */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_kv_unwatch_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:2412
+-- pgnats::api::nats::nats_kv_watch
+CREATE  FUNCTION "nats_kv_watch"(
+	"bucket" TEXT, /* alloc::string::String */
+	"key_pattern" TEXT, /* alloc::string::String */
+	"fn_oid" oid /* pgrx_pg_sys::submodules::oids::Oid */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_kv_watch_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1159
+-- pgnats::api::nats::nats_nak
+CREATE  FUNCTION "nats_nak"(
+	"reply" TEXT, /* &str */
+	"delay" interval DEFAULT NULL /* core::option::Option<pgrx::datum::interval::Interval> */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_nak_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1582
+-- pgnats::api::nats::nats_object_store_create
+CREATE  FUNCTION "nats_object_store_create"(
+	"store" TEXT, /* alloc::string::String */
+	"description" TEXT DEFAULT NULL, /* core::option::Option<alloc::string::String> */
+	"ttl" interval DEFAULT NULL, /* core::option::Option<pgrx::datum::interval::Interval> */
+	"max_bytes" bigint DEFAULT NULL, /* core::option::Option<i64> */
+	"replicas" INT DEFAULT NULL, /* core::option::Option<i32> */
+	"compression" bool DEFAULT false /* bool */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_object_store_create_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1650
+-- pgnats::api::nats::nats_object_store_delete
+CREATE  FUNCTION "nats_object_store_delete"(
+	"store" TEXT /* &str */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_object_store_delete_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1629
+-- pgnats::api::nats::nats_object_store_seal
+CREATE  FUNCTION "nats_object_store_seal"(
+	"store" TEXT /* &str */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_object_store_seal_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1672
+-- pgnats::api::nats::nats_object_store_status
+CREATE  FUNCTION "nats_object_store_status"(
+	"store" TEXT /* &str */
+) RETURNS TABLE (
+	"store" TEXT,  /* alloc::string::String */
+	"description" TEXT,  /* core::option::Option<alloc::string::String> */
+	"ttl" interval,  /* core::option::Option<pgrx::datum::interval::Interval> */
+	"max_bytes" bigint,  /* core::option::Option<i64> */
+	"replicas" INT,  /* i32 */
+	"storage" TEXT,  /* alloc::string::String */
+	"compressed" bool,  /* bool */
+	"sealed" bool,  /* bool */
+	"size" bigint  /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_object_store_status_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:2586
+-- pgnats::api::nats::nats_object_unwatch
+CREATE  FUNCTION "nats_object_unwatch"(
+	"store" TEXT, /* alloc::string::String */
+	"fn_oid" oid /* pgrx_pg_sys::submodules::oids::Oid */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_object_unwatch_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:2534
+-- pgnats::api::nats::nats_object_watch
+CREATE  FUNCTION "nats_object_watch"(
+	"store" TEXT, /* alloc::string::String */
+	"fn_oid" oid /* pgrx_pg_sys::submodules::oids::Oid */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_object_watch_wrapper';
+/* </end connected objects> */
+
+DROP FUNCTION "nats_put_file"(TEXT, TEXT, bytea);
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1289
+-- pgnats::api::nats::nats_put_file
+CREATE  FUNCTION "nats_put_file"(
+	"store" TEXT, /* alloc::string::String */
+	"name" TEXT, /* &str */
+	"content" bytea, /* alloc::vec::Vec<u8> */
+	"description" TEXT DEFAULT NULL, /* core::option::Option<alloc::string::String> */
+	"metadata" jsonb DEFAULT NULL, /* core::option::Option<pgrx::datum::json::JsonB> */
+	"headers" jsonb DEFAULT NULL /* core::option::Option<pgrx::datum::json::JsonB> */
+) RETURNS TABLE (
+	"nuid" TEXT,  /* alloc::string::String */
+	"size" bigint,  /* i64 */
+	"chunks" bigint,  /* i64 */
+	"digest" TEXT  /* core::option::Option<alloc::string::String> */
+)
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_put_file_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1346
+-- pgnats::api::nats::nats_put_file_from_lo
+CREATE  FUNCTION "nats_put_file_from_lo"(
+	"store" TEXT, /* alloc::string::String */
+	"name" TEXT, /* &str */
+	"lo_oid" oid /* pgrx_pg_sys::submodules::oids::Oid */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_put_file_from_lo_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1715
+-- pgnats::api::nats::nats_put_link
+CREATE  FUNCTION "nats_put_link"(
+	"store" TEXT, /* alloc::string::String */
+	"name" TEXT, /* alloc::string::String */
+	"target_store" TEXT, /* alloc::string::String */
+	"target_name" TEXT /* &str */
+) RETURNS TABLE (
+	"name" TEXT,  /* alloc::string::String */
+	"description" TEXT,  /* core::option::Option<alloc::string::String> */
+	"metadata" jsonb,  /* pgrx::datum::json::JsonB */
+	"headers" jsonb,  /* core::option::Option<pgrx::datum::json::JsonB> */
+	"options" jsonb,  /* core::option::Option<pgrx::datum::json::JsonB> */
+	"bucket" TEXT,  /* alloc::string::String */
+	"nuid" TEXT,  /* alloc::string::String */
+	"size" bigint,  /* i64 */
+	"chunks" bigint,  /* i64 */
+	"modified" TEXT,  /* core::option::Option<alloc::string::String> */
+	"digest" TEXT,  /* core::option::Option<alloc::string::String> */
+	"delete" bool  /* bool */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_put_link_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1766
+-- pgnats::api::nats::nats_put_store_link
+CREATE  FUNCTION "nats_put_store_link"(
+	"store" TEXT, /* alloc::string::String */
+	"name" TEXT, /* alloc::string::String */
+	"target_store" TEXT /* alloc::string::String */
+) RETURNS TABLE (
+	"name" TEXT,  /* alloc::string::String */
+	"description" TEXT,  /* core::option::Option<alloc::string::String> */
+	"metadata" jsonb,  /* pgrx::datum::json::JsonB */
+	"headers" jsonb,  /* core::option::Option<pgrx::datum::json::JsonB> */
+	"options" jsonb,  /* core::option::Option<pgrx::datum::json::JsonB> */
+	"bucket" TEXT,  /* alloc::string::String */
+	"nuid" TEXT,  /* alloc::string::String */
+	"size" bigint,  /* i64 */
+	"chunks" bigint,  /* i64 */
+	"modified" TEXT,  /* core::option::Option<alloc::string::String> */
+	"digest" TEXT,  /* core::option::Option<alloc::string::String> */
+	"delete" bool  /* bool */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_put_store_link_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:2120
+-- pgnats::api::nats::nats_respond
+CREATE  FUNCTION "nats_respond"(
+	"subject" TEXT, /* alloc::string::String */
+	"fn_oid" oid, /* pgrx_pg_sys::submodules::oids::Oid */
+	"queue_group" TEXT DEFAULT NULL /* core::option::Option<alloc::string::String> */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_respond_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:2209
+-- pgnats::api::nats::nats_service_add
+CREATE  FUNCTION "nats_service_add"(
+	"name" TEXT, /* alloc::string::String */
+	"version" TEXT, /* alloc::string::String */
+	"description" TEXT DEFAULT NULL /* core::option::Option<alloc::string::String> */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_service_add_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:2301
+-- pgnats::api::nats::nats_service_add_endpoint
+CREATE  FUNCTION "nats_service_add_endpoint"(
+	"service" TEXT, /* alloc::string::String */
+	"endpoint" TEXT, /* alloc::string::String */
+	"fn_oid" oid, /* pgrx_pg_sys::submodules::oids::Oid */
+	"subject" TEXT DEFAULT NULL /* core::option::Option<alloc::string::String> */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_service_add_endpoint_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:2253
+-- pgnats::api::nats::nats_service_remove
+CREATE  FUNCTION "nats_service_remove"(
+	"name" TEXT /* alloc::string::String */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_service_remove_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:2351
+-- pgnats::api::nats::nats_service_remove_endpoint
+CREATE  FUNCTION "nats_service_remove_endpoint"(
+	"service" TEXT, /* alloc::string::String */
+	"endpoint" TEXT /* alloc::string::String */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_service_remove_endpoint_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:635
+-- pgnats::api::nats::nats_stream_create
+CREATE  FUNCTION "nats_stream_create"(
+	"config" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS TABLE (
+	"name" TEXT,  /* alloc::string::String */
+	"config" jsonb,  /* pgrx::datum::json::JsonB */
+	"created" timestamp with time zone,  /* pgrx::datum::time_stamp_with_timezone::TimestampWithTimeZone */
+	"messages" bigint,  /* i64 */
+	"bytes" bigint,  /* i64 */
+	"first_seq" bigint,  /* i64 */
+	"last_seq" bigint,  /* i64 */
+	"consumers" bigint  /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_stream_create_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:715
+-- pgnats::api::nats::nats_stream_delete
+CREATE  FUNCTION "nats_stream_delete"(
+	"name" TEXT /* &str */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_stream_delete_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:883
+-- pgnats::api::nats::nats_stream_get_last
+CREATE  FUNCTION "nats_stream_get_last"(
+	"stream" TEXT, /* &str */
+	"subject" TEXT /* &str */
+) RETURNS TABLE (
+	"subject" TEXT,  /* alloc::string::String */
+	"seq" bigint,  /* i64 */
+	"payload" bytea,  /* alloc::vec::Vec<u8> */
+	"headers" jsonb,  /* core::option::Option<pgrx::datum::json::JsonB> */
+	"published" timestamp with time zone  /* pgrx::datum::time_stamp_with_timezone::TimestampWithTimeZone */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_stream_get_last_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:842
+-- pgnats::api::nats::nats_stream_get_message
+CREATE  FUNCTION "nats_stream_get_message"(
+	"stream" TEXT, /* &str */
+	"seq" bigint /* i64 */
+) RETURNS TABLE (
+	"subject" TEXT,  /* alloc::string::String */
+	"seq" bigint,  /* i64 */
+	"payload" bytea,  /* alloc::vec::Vec<u8> */
+	"headers" jsonb,  /* core::option::Option<pgrx::datum::json::JsonB> */
+	"published" timestamp with time zone  /* pgrx::datum::time_stamp_with_timezone::TimestampWithTimeZone */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_stream_get_message_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:733
+-- pgnats::api::nats::nats_stream_info
+CREATE  FUNCTION "nats_stream_info"(
+	"name" TEXT /* &str */
+) RETURNS TABLE (
+	"name" TEXT,  /* alloc::string::String */
+	"config" jsonb,  /* pgrx::datum::json::JsonB */
+	"created" timestamp with time zone,  /* pgrx::datum::time_stamp_with_timezone::TimestampWithTimeZone */
+	"messages" bigint,  /* i64 */
+	"bytes" bigint,  /* i64 */
+	"first_seq" bigint,  /* i64 */
+	"last_seq" bigint,  /* i64 */
+	"consumers" bigint  /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_stream_info_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:768
+-- pgnats::api::nats::nats_stream_list
+CREATE  FUNCTION "nats_stream_list"() RETURNS TABLE (
+	"name" TEXT,  /* alloc::string::String */
+	"config" jsonb,  /* pgrx::datum::json::JsonB */
+	"created" timestamp with time zone,  /* pgrx::datum::time_stamp_with_timezone::TimestampWithTimeZone */
+	"messages" bigint,  /* i64 */
+	"bytes" bigint,  /* i64 */
+	"first_seq" bigint,  /* i64 */
+	"last_seq" bigint,  /* i64 */
+	"consumers" bigint  /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_stream_list_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:806
+-- pgnats::api::nats::nats_stream_purge
+CREATE  FUNCTION "nats_stream_purge"(
+	"name" TEXT, /* &str */
+	"subject" TEXT DEFAULT NULL, /* core::option::Option<alloc::string::String> */
+	"keep" bigint DEFAULT NULL /* core::option::Option<i64> */
+) RETURNS bigint /* core::result::Result<i64, anyhow::Error> */
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_stream_purge_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:676
+-- pgnats::api::nats::nats_stream_update
+CREATE  FUNCTION "nats_stream_update"(
+	"config" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS TABLE (
+	"name" TEXT,  /* alloc::string::String */
+	"config" jsonb,  /* pgrx::datum::json::JsonB */
+	"created" timestamp with time zone,  /* pgrx::datum::time_stamp_with_timezone::TimestampWithTimeZone */
+	"messages" bigint,  /* i64 */
+	"bytes" bigint,  /* i64 */
+	"first_seq" bigint,  /* i64 */
+	"last_seq" bigint,  /* i64 */
+	"consumers" bigint  /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_stream_update_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1998
+-- pgnats::api::nats::nats_subscribe_stream
+CREATE  FUNCTION "nats_subscribe_stream"(
+	"stream" TEXT, /* alloc::string::String */
+	"consumer" TEXT, /* alloc::string::String */
+	"fn_oid" oid, /* pgrx_pg_sys::submodules::oids::Oid */
+	"max_retries" INT DEFAULT 5, /* i32 */
+	"dead_letter_subject" TEXT DEFAULT NULL, /* core::option::Option<alloc::string::String> */
+	"dead_letter_table" bool DEFAULT false /* bool */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_subscribe_stream_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:1184
+-- pgnats::api::nats::nats_term
+CREATE  FUNCTION "nats_term"(
+	"reply" TEXT /* &str */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_term_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:2165
+-- pgnats::api::nats::nats_unrespond
+CREATE  FUNCTION "nats_unrespond"(
+	"subject" TEXT /* alloc::string::String */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_unrespond_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/nats.rs:2059
+-- pgnats::api::nats::nats_unsubscribe_stream
+CREATE  FUNCTION "nats_unsubscribe_stream"(
+	"stream" TEXT, /* alloc::string::String */
+	"consumer" TEXT, /* alloc::string::String */
+	"fn_oid" oid /* pgrx_pg_sys::submodules::oids::Oid */
+) RETURNS VOID /* core::result::Result<(), anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'nats_unsubscribe_stream_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/api/mod.rs:99
+-- pgnats::api::pgnats_subscriptions_status
+CREATE  FUNCTION "pgnats_subscriptions_status"() RETURNS TABLE (
+	"subject" TEXT,  /* alloc::string::String */
+	"callback" TEXT,  /* alloc::string::String */
+	"active" bool,  /* bool */
+	"received" bigint,  /* i64 */
+	"processed" bigint,  /* i64 */
+	"errors" bigint,  /* i64 */
+	"last_error" TEXT,  /* core::option::Option<alloc::string::String> */
+	"last_message_at" timestamp with time zone,  /* core::option::Option<pgrx::datum::time_stamp_with_timezone::TimestampWithTimeZone> */
+	"queue_depth" bigint  /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'pgnats_subscriptions_status_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- src/fdw/mod.rs:65
+-- pgnats::fdw::pgnats_fdw_handler
+CREATE  FUNCTION "pgnats_fdw_handler"() RETURNS fdw_handler /* pgnats::fdw::FdwRoutine */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'pgnats_fdw_handler_wrapper';
+/* </end connected objects> */
+
+ALTER FOREIGN DATA WRAPPER pgnats_fdw HANDLER pgnats_fdw_handler;
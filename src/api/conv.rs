@@ -1,5 +1,7 @@
 use pgrx::name;
 
+use crate::utils::timestamptz_from_unix_nanos;
+
 #[allow(clippy::type_complexity)]
 pub fn map_server_info(
     v: impl IntoIterator<Item = async_nats::ServerInfo> + 'static,
@@ -86,3 +88,382 @@ pub fn map_object_info(
         ))
     }))
 }
+
+#[allow(clippy::type_complexity)]
+#[cfg(feature = "object_store")]
+pub fn map_put_object_info(
+    v: async_nats::jetstream::object_store::ObjectInfo,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        name!(nuid, String),
+        name!(size, i64),
+        name!(chunks, i64),
+        name!(digest, Option<String>),
+    ),
+> {
+    pgrx::iter::TableIterator::once((v.nuid, v.size as i64, v.chunks as i64, v.digest))
+}
+
+#[allow(clippy::type_complexity)]
+#[cfg(feature = "object_store")]
+pub fn map_object_store_status(
+    v: async_nats::jetstream::stream::Info,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        name!(store, String),
+        name!(description, Option<String>),
+        name!(ttl, Option<pgrx::datum::Interval>),
+        name!(max_bytes, Option<i64>),
+        name!(replicas, i32),
+        name!(storage, String),
+        name!(compressed, bool),
+        name!(sealed, bool),
+        name!(size, i64),
+    ),
+> {
+    use async_nats::jetstream::stream::{Compression, StorageType};
+
+    let config = v.config;
+    let store = config
+        .name
+        .strip_prefix("OBJ_")
+        .unwrap_or(&config.name)
+        .to_string();
+    let ttl = if config.max_age.is_zero() {
+        None
+    } else {
+        pgrx::datum::Interval::try_from(config.max_age).ok()
+    };
+    let storage = match config.storage {
+        StorageType::File => "file",
+        StorageType::Memory => "memory",
+    };
+
+    pgrx::iter::TableIterator::once((
+        store,
+        config.description,
+        ttl,
+        (config.max_bytes >= 0).then_some(config.max_bytes),
+        config.num_replicas.try_into().unwrap_or(i32::MAX),
+        storage.to_string(),
+        matches!(config.compression, Some(Compression::S2)),
+        config.sealed,
+        v.state.bytes.try_into().unwrap_or(i64::MAX),
+    ))
+}
+
+/// Converts a `jsonb` object into object store metadata, non-string values
+/// are stored as their JSON text.
+#[cfg(feature = "object_store")]
+pub fn object_metadata_from_json(
+    v: serde_json::Value,
+) -> anyhow::Result<std::collections::HashMap<String, String>> {
+    let serde_json::Value::Object(map) = v else {
+        anyhow::bail!("Metadata must be a JSON object");
+    };
+
+    Ok(map
+        .into_iter()
+        .map(|(k, v)| match v {
+            serde_json::Value::String(v) => (k, v),
+            v => (k, v.to_string()),
+        })
+        .collect())
+}
+
+/// Converts KV entries into rows, failing instead of altering or skipping an entry
+/// whose revision, delta or creation time cannot be represented.
+#[allow(clippy::type_complexity)]
+#[cfg(feature = "kv")]
+pub fn map_kv_entry(
+    v: impl IntoIterator<Item = async_nats::jetstream::kv::Entry>,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(value, Vec<u8>),
+            name!(revision, i64),
+            name!(created, pgrx::datum::TimestampWithTimeZone),
+            name!(delta, i64),
+            name!(operation, String),
+        ),
+    >,
+> {
+    let rows = v
+        .into_iter()
+        .map(|v| {
+            let revision = i64::try_from(v.revision)
+                .map_err(|_| anyhow::anyhow!("Revision {} is out of range", v.revision))?;
+            let created = timestamptz_from_unix_nanos(v.created.unix_timestamp_nanos())
+                .ok_or_else(|| anyhow::anyhow!("Creation time {} is out of range", v.created))?;
+            let delta = i64::try_from(v.delta)
+                .map_err(|_| anyhow::anyhow!("Delta {} is out of range", v.delta))?;
+
+            Ok((
+                v.value.to_vec(),
+                revision,
+                created,
+                delta,
+                crate::utils::kv_operation_name(v.operation).to_string(),
+            ))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(pgrx::iter::TableIterator::new(rows))
+}
+
+/// Converts stream infos into rows, failing instead of skipping a stream or clamping a
+/// counter that cannot be represented.
+#[allow(clippy::type_complexity)]
+pub fn map_stream_info(
+    v: impl IntoIterator<Item = async_nats::jetstream::stream::Info>,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(name, String),
+            name!(config, pgrx::JsonB),
+            name!(created, pgrx::datum::TimestampWithTimeZone),
+            name!(messages, i64),
+            name!(bytes, i64),
+            name!(first_seq, i64),
+            name!(last_seq, i64),
+            name!(consumers, i64),
+        ),
+    >,
+> {
+    let rows = v
+        .into_iter()
+        .map(|v| {
+            let created = timestamptz_from_unix_nanos(v.created.unix_timestamp_nanos())
+                .ok_or_else(|| anyhow::anyhow!("Creation time {} is out of range", v.created))?;
+
+            Ok((
+                v.config.name.clone(),
+                pgrx::JsonB(serde_json::to_value(&v.config)?),
+                created,
+                i64_from(v.state.messages, "Message count")?,
+                i64_from(v.state.bytes, "Byte count")?,
+                i64_from(v.state.first_sequence, "First sequence")?,
+                i64_from(v.state.last_sequence, "Last sequence")?,
+                i64_from(v.state.consumer_count, "Consumer count")?,
+            ))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(pgrx::iter::TableIterator::new(rows))
+}
+
+#[allow(clippy::type_complexity)]
+pub fn map_account_info(
+    v: impl IntoIterator<Item = async_nats::jetstream::account::Account> + 'static,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        name!(memory, i64),
+        name!(storage, i64),
+        name!(reserved_memory, i64),
+        name!(reserved_storage, i64),
+        name!(streams, i64),
+        name!(consumers, i64),
+        name!(max_memory, Option<i64>),
+        name!(max_storage, Option<i64>),
+        name!(max_streams, Option<i64>),
+        name!(max_consumers, Option<i64>),
+        name!(domain, Option<String>),
+        name!(api_total, i64),
+        name!(api_errors, i64),
+    ),
+> {
+    pgrx::iter::TableIterator::new(v.into_iter().map(|v| {
+        (
+            v.memory.try_into().unwrap_or(i64::MAX),
+            v.storage.try_into().unwrap_or(i64::MAX),
+            v.reserved_memory.try_into().unwrap_or(i64::MAX),
+            v.reserved_storage.try_into().unwrap_or(i64::MAX),
+            v.streams.try_into().unwrap_or(i64::MAX),
+            v.consumers.try_into().unwrap_or(i64::MAX),
+            v.limits.max_memory,
+            v.limits.max_storage,
+            v.limits.max_streams,
+            v.limits.max_consumers,
+            v.domain,
+            v.requests.total.try_into().unwrap_or(i64::MAX),
+            v.requests.errors.try_into().unwrap_or(i64::MAX),
+        )
+    }))
+}
+
+/// Parses a stream configuration written as in the JetStream API,
+/// durations such as `max_age` are given in nanoseconds.
+pub fn stream_config_from_json(
+    v: serde_json::Value,
+) -> anyhow::Result<async_nats::jetstream::stream::Config> {
+    serde_json::from_value(v).map_err(|e| anyhow::anyhow!("Invalid stream config: {e}"))
+}
+
+/// Converts consumer infos into rows, failing instead of skipping a consumer or clamping a
+/// sequence or counter that cannot be represented.
+#[allow(clippy::type_complexity)]
+pub fn map_consumer_info(
+    v: impl IntoIterator<Item = async_nats::jetstream::consumer::Info>,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(stream, String),
+            name!(name, String),
+            name!(config, pgrx::JsonB),
+            name!(created, pgrx::datum::TimestampWithTimeZone),
+            name!(delivered_stream_seq, i64),
+            name!(delivered_consumer_seq, i64),
+            name!(ack_floor_stream_seq, i64),
+            name!(ack_floor_consumer_seq, i64),
+            name!(num_pending, i64),
+            name!(num_ack_pending, i64),
+            name!(num_redelivered, i64),
+            name!(num_waiting, i64),
+        ),
+    >,
+> {
+    let rows = v
+        .into_iter()
+        .map(|v| {
+            let created = timestamptz_from_unix_nanos(v.created.unix_timestamp_nanos())
+                .ok_or_else(|| anyhow::anyhow!("Creation time {} is out of range", v.created))?;
+
+            Ok((
+                v.stream_name,
+                v.name,
+                pgrx::JsonB(serde_json::to_value(&v.config)?),
+                created,
+                i64_from(v.delivered.stream_sequence, "Delivered stream sequence")?,
+                i64_from(v.delivered.consumer_sequence, "Delivered consumer sequence")?,
+                i64_from(v.ack_floor.stream_sequence, "Ack floor stream sequence")?,
+                i64_from(v.ack_floor.consumer_sequence, "Ack floor consumer sequence")?,
+                i64_from(v.num_pending, "Pending count")?,
+                i64_from(v.num_ack_pending, "Ack pending count")?,
+                i64_from(v.num_redelivered, "Redelivered count")?,
+                i64_from(v.num_waiting, "Waiting count")?,
+            ))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(pgrx::iter::TableIterator::new(rows))
+}
+
+/// Parses a consumer configuration written as in the JetStream API,
+/// durations such as `ack_wait` and `backoff` are given in nanoseconds.
+pub fn consumer_config_from_json(
+    v: serde_json::Value,
+) -> anyhow::Result<async_nats::jetstream::consumer::Config> {
+    serde_json::from_value(v).map_err(|e| anyhow::anyhow!("Invalid consumer config: {e}"))
+}
+
+/// Converts fetched messages into rows, failing instead of skipping a message whose
+/// metadata cannot be read or represented, so that no fetched message goes unseen.
+#[allow(clippy::type_complexity)]
+pub fn map_jetstream_message(
+    v: impl IntoIterator<Item = async_nats::jetstream::Message>,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(subject, String),
+            name!(payload, Vec<u8>),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(stream_seq, i64),
+            name!(consumer_seq, i64),
+            name!(delivered, i64),
+            name!(published, pgrx::datum::TimestampWithTimeZone),
+            name!(reply, Option<String>),
+        ),
+    >,
+> {
+    let rows = v
+        .into_iter()
+        .map(|v| {
+            let info = v.info().map_err(|e| {
+                anyhow::anyhow!("Invalid metadata of message on '{}': {e}", v.subject)
+            })?;
+            let stream_seq = i64::try_from(info.stream_sequence).map_err(|_| {
+                anyhow::anyhow!("Stream sequence {} is out of range", info.stream_sequence)
+            })?;
+            let consumer_seq = i64::try_from(info.consumer_sequence).map_err(|_| {
+                anyhow::anyhow!(
+                    "Consumer sequence {} is out of range",
+                    info.consumer_sequence
+                )
+            })?;
+            let published = timestamptz_from_unix_nanos(info.published.unix_timestamp_nanos())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Publish time {} is out of range", info.published)
+                })?;
+
+            Ok((
+                v.subject.to_string(),
+                v.payload.to_vec(),
+                v.headers
+                    .as_ref()
+                    .map(|headers| pgrx::JsonB(crate::utils::headers_to_json(headers))),
+                stream_seq,
+                consumer_seq,
+                info.delivered,
+                published,
+                v.reply.as_ref().map(|reply| reply.to_string()),
+            ))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(pgrx::iter::TableIterator::new(rows))
+}
+
+/// Converts stored stream messages into rows, failing instead of skipping a message whose
+/// sequence or publish time cannot be represented.
+#[allow(clippy::type_complexity)]
+pub fn map_stream_message(
+    v: impl IntoIterator<Item = async_nats::jetstream::message::StreamMessage>,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(subject, String),
+            name!(seq, i64),
+            name!(payload, Vec<u8>),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(published, pgrx::datum::TimestampWithTimeZone),
+        ),
+    >,
+> {
+    let rows = v
+        .into_iter()
+        .map(|v| {
+            let seq = i64::try_from(v.sequence)
+                .map_err(|_| anyhow::anyhow!("Sequence {} is out of range", v.sequence))?;
+            let published = timestamptz_from_unix_nanos(v.time.unix_timestamp_nanos())
+                .ok_or_else(|| anyhow::anyhow!("Publish time {} is out of range", v.time))?;
+
+            Ok((
+                v.subject.to_string(),
+                seq,
+                v.payload.to_vec(),
+                (!v.headers.is_empty())
+                    .then(|| pgrx::JsonB(crate::utils::headers_to_json(&v.headers))),
+                published,
+            ))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(pgrx::iter::TableIterator::new(rows))
+}
+
+fn i64_from<T>(value: T, what: &str) -> anyhow::Result<i64>
+where
+    T: TryInto<i64> + Copy + std::fmt::Display,
+{
+    value
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("{what} {value} is out of range"))
+}
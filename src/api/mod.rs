@@ -81,3 +81,102 @@ pub fn pgnats_version() -> pgrx::iter::TableIterator<
         build::LAST_TAG.to_string(),
     )])
 }
+
+/// Returns the status of the subscriptions of the current database as reported by the
+/// subscriber workers: whether the subscription is active, the numbers of received and
+/// processed messages and of failed calls, the last error, the time of the last message
+/// and the number of messages waiting for the callback.
+///
+/// Subscriptions stored in `pgnats.subscriptions` which no worker runs are reported as
+/// inactive. Only `nats_subscribe` subscriptions are reported; stream subscriptions,
+/// watches, responders and services are not.
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM pgnats_subscriptions_status();
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+#[cfg(feature = "sub")]
+pub fn pgnats_subscriptions_status() -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(subject, String),
+            name!(callback, String),
+            name!(active, bool),
+            name!(received, i64),
+            name!(processed, i64),
+            name!(errors, i64),
+            name!(last_error, Option<String>),
+            name!(last_message_at, Option<pgrx::datum::TimestampWithTimeZone>),
+            name!(queue_depth, i64),
+        ),
+    >,
+> {
+    use std::collections::{btree_map::Entry, BTreeMap};
+
+    use crate::bgw::{
+        status::SubscriptionStatus, subscriber::pg_api::fetch_subscriptions, SUBSCRIPTIONS_STATUS,
+        SUBSCRIPTIONS_TABLE_NAME,
+    };
+
+    let mut statuses = BTreeMap::new();
+
+    for (subject, callback, ..) in fetch_subscriptions(SUBSCRIPTIONS_TABLE_NAME)? {
+        let _ = statuses.insert(
+            (subject.clone(), callback.clone()),
+            SubscriptionStatus {
+                subject,
+                callback,
+                ..Default::default()
+            },
+        );
+    }
+
+    // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
+    // before extension code is executed. Postgres backends are single-threaded,
+    // and this variable is immutable after initialization.
+    let db_oid = unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32();
+
+    let (published, truncated) = SUBSCRIPTIONS_STATUS.share().statuses(db_oid);
+
+    if truncated {
+        crate::warn!(
+            "Some subscriptions are not reported: their status does not fit into shared memory"
+        );
+    }
+
+    for status in published {
+        match statuses.entry((status.subject.clone(), status.callback.clone())) {
+            Entry::Vacant(entry) => {
+                let _ = entry.insert(status);
+            }
+            Entry::Occupied(mut entry) => {
+                if !entry.get().active {
+                    let _ = entry.insert(status);
+                }
+            }
+        }
+    }
+
+    let to_i64 = |v: u64| i64::try_from(v).unwrap_or(i64::MAX);
+
+    Ok(pgrx::iter::TableIterator::new(statuses.into_values().map(
+        move |status| {
+            (
+                status.subject,
+                status.callback,
+                status.active,
+                to_i64(status.received),
+                to_i64(status.processed),
+                to_i64(status.errors),
+                status.last_error,
+                status
+                    .last_message_at
+                    .and_then(crate::utils::timestamptz_from_unix_nanos),
+                to_i64(status.queue_depth),
+            )
+        },
+    )))
+}
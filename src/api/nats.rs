@@ -1,13 +1,26 @@
-#[cfg(feature = "sub")]
+#[cfg(any(feature = "object_store", feature = "sub"))]
 use pgrx::pg_sys;
 use pgrx::{name, pg_extern};
 
 use super::conv::map_server_info;
-use crate::{ctx::CTX, impl_nats_publish, impl_nats_request, utils::resolve_bytea_name};
+use crate::{
+    ctx::CTX,
+    impl_nats_publish, impl_nats_request,
+    utils::{resolve_callback, resolve_callback_name, resolve_responder_name},
+};
 
 #[cfg(feature = "kv")]
 use crate::{impl_nats_get, impl_nats_put};
 
+#[cfg(all(feature = "sub", any(feature = "kv", feature = "object_store")))]
+use crate::utils::resolve_function_name;
+
+#[cfg(feature = "object_store")]
+use crate::{
+    large_object::{self, Chunk, ChunkReader},
+    utils::extract_headers,
+};
+
 impl_nats_publish! {
     /// Publishes a raw binary message to the specified NATS subject.
     ///
@@ -328,6 +341,45 @@ impl_nats_get! {
     jsonb, pgrx::JsonB
 }
 
+/// Retrieves a KV entry together with its metadata by the specified key.
+///
+/// # Arguments
+/// * `bucket` - Name of the KV bucket
+/// * `key` - Key to retrieve the entry from
+///
+/// # Returns
+/// * `Ok(_)` - A row with the value, revision, creation time, delta and operation
+///   (`PUT`, `DEL` or `PURGE`), or no rows if the key doesn't exist
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_kv_entry('user_profiles', 'user123');
+/// ```
+#[allow(clippy::type_complexity)]
+#[cfg(feature = "kv")]
+#[pg_extern]
+pub fn nats_kv_entry(
+    bucket: String,
+    key: &str,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(value, Vec<u8>),
+            name!(revision, i64),
+            name!(created, pgrx::datum::TimestampWithTimeZone),
+            name!(delta, i64),
+            name!(operation, String),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_entry(bucket, key))
+            .and_then(super::conv::map_kv_entry)
+    })
+}
+
 /// Deletes a value from the NATS KV bucket by the specified key.
 ///
 /// # Arguments
@@ -350,6 +402,141 @@ pub fn nats_delete_value(bucket: String, key: &str) -> anyhow::Result<()> {
     })
 }
 
+/// Purges a key from the NATS KV bucket, removing all of its history.
+///
+/// Unlike [`nats_delete_value`], which only adds a delete marker, purge
+/// drops all previous revisions of the key from the server.
+///
+/// # Arguments
+/// * `bucket` - The name of the KV bucket
+/// * `key` - The key to purge
+///
+/// # Returns
+/// * `Ok(())` - If the purge was successful
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_kv_purge('user_profiles', 'inactive_user_123');
+/// ```
+#[cfg(feature = "kv")]
+#[pg_extern]
+pub fn nats_kv_purge(bucket: String, key: &str) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.purge_value(bucket, key))
+    })
+}
+
+/// Stores many values in the NATS KV bucket at once.
+///
+/// The puts are pipelined, so the call waits for the acknowledgements of all
+/// keys together instead of one round trip per key.
+///
+/// # Arguments
+/// * `bucket` - Name of the KV bucket
+/// * `values` - A JSON object mapping keys to values; every value is stored as JSON,
+///   the same way as [`nats_put_jsonb`] does
+///
+/// # Returns
+/// * `Ok(_)` - One row per key with the revision of the stored value
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_kv_put_many('config', '{"service.a": {"enabled": true}, "service.b": 42}');
+/// ```
+#[cfg(feature = "kv")]
+#[pg_extern]
+pub fn nats_kv_put_many(
+    bucket: String,
+    values: pgrx::JsonB,
+) -> anyhow::Result<pgrx::iter::TableIterator<'static, (name!(key, String), name!(revision, i64))>>
+{
+    let serde_json::Value::Object(values) = values.0 else {
+        anyhow::bail!("Values must be a JSON object mapping keys to values");
+    };
+
+    let values = values
+        .into_iter()
+        .map(|(key, value)| Ok((key, serde_json::to_vec(&value)?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.put_values(bucket, values))
+            .and_then(|revisions| {
+                let rows = revisions
+                    .into_iter()
+                    .map(|(key, revision)| {
+                        let revision = i64::try_from(revision)
+                            .map_err(|_| anyhow::anyhow!("Revision {revision} is out of range"))?;
+                        Ok((key, revision))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+
+                Ok(pgrx::iter::TableIterator::new(rows))
+            })
+    })
+}
+
+/// Retrieves the values of many keys from the NATS KV bucket at once.
+///
+/// The lookups are pipelined, so the call waits for all keys together
+/// instead of one round trip per key.
+///
+/// # Arguments
+/// * `bucket` - Name of the KV bucket
+/// * `keys` - Keys to retrieve
+///
+/// # Returns
+/// * `Ok(_)` - One row per requested key, in the order of `keys`. `value` and
+///   `revision` are `NULL` if the key doesn't exist or was deleted
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT key, convert_from(value, 'UTF8')::jsonb
+/// FROM nats_kv_get_many('config', ARRAY['service.a', 'service.b']);
+/// ```
+#[allow(clippy::type_complexity)]
+#[cfg(feature = "kv")]
+#[pg_extern]
+pub fn nats_kv_get_many(
+    bucket: String,
+    keys: Vec<String>,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(key, String),
+            name!(value, Option<Vec<u8>>),
+            name!(revision, Option<i64>),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_entries_by_keys(bucket, keys))
+            .and_then(|entries| {
+                let rows = entries
+                    .into_iter()
+                    .map(|(key, entry)| {
+                        let revision = entry
+                            .as_ref()
+                            .map(|entry| {
+                                i64::try_from(entry.revision).map_err(|_| {
+                                    anyhow::anyhow!("Revision {} is out of range", entry.revision)
+                                })
+                            })
+                            .transpose()?;
+
+                        Ok((key, entry.map(|entry| entry.value.to_vec()), revision))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+
+                Ok(pgrx::iter::TableIterator::new(rows))
+            })
+    })
+}
+
 /// Retrieves information about the NATS server connection.
 ///
 /// # Returns
@@ -391,243 +578,2040 @@ pub fn nats_get_server_info() -> anyhow::Result<
     })
 }
 
-/// Retrieves a file's content from the NATS object store by its name.
-///
-/// # Arguments
-/// * `store` - The name of the object store
-/// * `name` - The name of the file to retrieve
+/// Retrieves the JetStream usage and limits of the account used by the connection.
 ///
 /// # Returns
-/// * `Ok(Vec<u8>)` - The file content as a byte array if successful
+/// * `Ok(_)` - A row with the memory and storage usage in bytes, the number of streams
+///   and consumers, the account limits (`NULL` when unlimited) and the API request counters
 ///
 /// # SQL Usage
 /// ```sql
-/// SELECT nats_get_file('documents', 'report.pdf');
+/// SELECT storage, max_storage, streams, max_streams FROM nats_jetstream_account_info();
 /// ```
+#[allow(clippy::type_complexity)]
 #[pg_extern]
-#[cfg(feature = "object_store")]
-pub fn nats_get_file(store: String, name: &str) -> anyhow::Result<Vec<u8>> {
-    CTX.with_borrow_mut(|ctx| ctx.rt.block_on(ctx.nats_connection.get_file(store, name)))
+pub fn nats_jetstream_account_info() -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(memory, i64),
+            name!(storage, i64),
+            name!(reserved_memory, i64),
+            name!(reserved_storage, i64),
+            name!(streams, i64),
+            name!(consumers, i64),
+            name!(max_memory, Option<i64>),
+            name!(max_storage, Option<i64>),
+            name!(max_streams, Option<i64>),
+            name!(max_consumers, Option<i64>),
+            name!(domain, Option<String>),
+            name!(api_total, i64),
+            name!(api_errors, i64),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_account_info())
+            .map(|v| super::conv::map_account_info(std::iter::once(v)))
+    })
 }
 
-/// Uploads a file to the NATS object store.
+/// Creates a JetStream stream.
 ///
 /// # Arguments
-/// * `store` - The name of the object store
-/// * `name` - The name under which to store the file
-/// * `content` - The file content as a byte array
+/// * `config` - Stream configuration as a `jsonb` object, in the JetStream API format
+///   (`name`, `subjects`, `retention`, `max_msgs`, `max_bytes`, `max_age` in nanoseconds,
+///   `storage`, `num_replicas`, `mirror`, `sources`, ...)
 ///
 /// # Returns
-/// * `Ok(())` - If the upload was successful
+/// * `Ok(_)` - A row with the configuration and state of the created stream
 ///
 /// # SQL Usage
 /// ```sql
-/// SELECT nats_put_file('documents', 'report.pdf', 'binary data'::bytea);
+/// SELECT * FROM nats_stream_create('{"name": "ORDERS", "subjects": ["orders.>"], "max_age": 86400000000000}');
 /// ```
+#[allow(clippy::type_complexity)]
 #[pg_extern]
-#[cfg(feature = "object_store")]
-pub fn nats_put_file(store: String, name: &str, content: Vec<u8>) -> anyhow::Result<()> {
+pub fn nats_stream_create(
+    config: pgrx::JsonB,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(name, String),
+            name!(config, pgrx::JsonB),
+            name!(created, pgrx::datum::TimestampWithTimeZone),
+            name!(messages, i64),
+            name!(bytes, i64),
+            name!(first_seq, i64),
+            name!(last_seq, i64),
+            name!(consumers, i64),
+        ),
+    >,
+> {
+    let config = super::conv::stream_config_from_json(config.0)?;
+
     CTX.with_borrow_mut(|ctx| {
         ctx.rt
-            .block_on(ctx.nats_connection.put_file(store, name, content))
+            .block_on(ctx.nats_connection.create_stream(config))
+            .and_then(|v| super::conv::map_stream_info(std::iter::once(v)))
     })
 }
 
-/// Deletes a file from the NATS object store.
+/// Updates the configuration of a JetStream stream.
 ///
 /// # Arguments
-/// * `store` - The name of the object store
-/// * `name` - The name of the file to delete
+/// * `config` - The complete new stream configuration as a `jsonb` object, in the same
+///   format as for `nats_stream_create`
 ///
 /// # Returns
-/// * `Ok(())` - If the deletion was successful
+/// * `Ok(_)` - A row with the configuration and state of the updated stream
 ///
 /// # SQL Usage
 /// ```sql
-/// SELECT nats_delete_file('documents', 'old_report.pdf');
+/// SELECT * FROM nats_stream_update('{"name": "ORDERS", "subjects": ["orders.>", "returns.>"]}');
 /// ```
+#[allow(clippy::type_complexity)]
 #[pg_extern]
-#[cfg(feature = "object_store")]
-pub fn nats_delete_file(store: String, name: &str) -> anyhow::Result<()> {
+pub fn nats_stream_update(
+    config: pgrx::JsonB,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(name, String),
+            name!(config, pgrx::JsonB),
+            name!(created, pgrx::datum::TimestampWithTimeZone),
+            name!(messages, i64),
+            name!(bytes, i64),
+            name!(first_seq, i64),
+            name!(last_seq, i64),
+            name!(consumers, i64),
+        ),
+    >,
+> {
+    let config = super::conv::stream_config_from_json(config.0)?;
+
     CTX.with_borrow_mut(|ctx| {
         ctx.rt
-            .block_on(ctx.nats_connection.delete_file(store, name))
+            .block_on(ctx.nats_connection.update_stream(config))
+            .and_then(|v| super::conv::map_stream_info(std::iter::once(v)))
     })
 }
 
-/// Retrieves metadata information for a specific file in the NATS object store.
+/// Deletes a JetStream stream together with all of its messages and consumers.
 ///
 /// # Arguments
-/// * `store` - The name of the object store
-/// * `name` - The name of the file
+/// * `name` - The name of the stream
 ///
 /// # Returns
-/// * `Ok(_)` - A row with file metadata if successful
+/// * `Ok(())` - If the stream was deleted
 ///
 /// # SQL Usage
 /// ```sql
-/// SELECT * FROM nats_get_file_info('documents', 'report.pdf');
+/// SELECT nats_stream_delete('ORDERS');
+/// ```
+#[pg_extern]
+pub fn nats_stream_delete(name: &str) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| ctx.rt.block_on(ctx.nats_connection.delete_stream(name)))
+}
+
+/// Retrieves the configuration and state of a JetStream stream.
+///
+/// # Arguments
+/// * `name` - The name of the stream
+///
+/// # Returns
+/// * `Ok(_)` - A row with the configuration and state of the stream
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_stream_info('ORDERS');
 /// ```
 #[allow(clippy::type_complexity)]
 #[pg_extern]
-#[cfg(feature = "object_store")]
-pub fn nats_get_file_info(
-    store: String,
+pub fn nats_stream_info(
     name: &str,
 ) -> anyhow::Result<
     pgrx::iter::TableIterator<
         'static,
         (
             name!(name, String),
-            name!(description, Option<String>),
-            name!(metadata, pgrx::JsonB),
-            name!(headers, Option<pgrx::JsonB>),
-            name!(options, Option<pgrx::JsonB>),
-            name!(bucket, String),
-            name!(nuid, String),
-            name!(size, i64),
-            name!(chunks, i64),
-            name!(modified, Option<String>),
-            name!(digest, Option<String>),
-            name!(delete, bool),
+            name!(config, pgrx::JsonB),
+            name!(created, pgrx::datum::TimestampWithTimeZone),
+            name!(messages, i64),
+            name!(bytes, i64),
+            name!(first_seq, i64),
+            name!(last_seq, i64),
+            name!(consumers, i64),
         ),
     >,
 > {
     CTX.with_borrow_mut(|ctx| {
         ctx.rt
-            .block_on(ctx.nats_connection.get_file_info(store, name))
-            .map(|v| super::conv::map_object_info(std::iter::once(v)))
+            .block_on(ctx.nats_connection.get_stream_info(name))
+            .and_then(|v| super::conv::map_stream_info(std::iter::once(v)))
     })
 }
 
-/// Retrieves a list of all files in the specified NATS object store.
-///
-/// # Arguments
-/// * `store` - The name of the object store
+/// Retrieves the configuration and state of all JetStream streams.
 ///
 /// # Returns
-/// * `Ok(_)` - Iterator with metadata for all files
+/// * `Ok(_)` - A row for every stream
 ///
 /// # SQL Usage
 /// ```sql
-/// SELECT * FROM nats_get_file_list('documents');
+/// SELECT name, messages, bytes FROM nats_stream_list();
+/// ```
 #[allow(clippy::type_complexity)]
 #[pg_extern]
-#[cfg(feature = "object_store")]
-pub fn nats_get_file_list(
-    store: String,
-) -> anyhow::Result<
+pub fn nats_stream_list() -> anyhow::Result<
     pgrx::iter::TableIterator<
         'static,
         (
             name!(name, String),
-            name!(description, Option<String>),
-            name!(metadata, pgrx::JsonB),
-            name!(headers, Option<pgrx::JsonB>),
-            name!(options, Option<pgrx::JsonB>),
-            name!(bucket, String),
-            name!(nuid, String),
-            name!(size, i64),
-            name!(chunks, i64),
-            name!(modified, Option<String>),
-            name!(digest, Option<String>),
-            name!(delete, bool),
+            name!(config, pgrx::JsonB),
+            name!(created, pgrx::datum::TimestampWithTimeZone),
+            name!(messages, i64),
+            name!(bytes, i64),
+            name!(first_seq, i64),
+            name!(last_seq, i64),
+            name!(consumers, i64),
         ),
     >,
 > {
     CTX.with_borrow_mut(|ctx| {
         ctx.rt
-            .block_on(ctx.nats_connection.get_file_list(store))
-            .map(|v| super::conv::map_object_info(v))
+            .block_on(ctx.nats_connection.get_stream_list())
+            .and_then(super::conv::map_stream_info)
     })
 }
 
-/// Subscribes to a NATS subject and associates it with a PostgreSQL callback function.
-///
-/// Multiple callback functions can be subscribed to the same subject — each will be invoked
-/// independently when a matching message is received.
+/// Removes messages from a JetStream stream.
 ///
 /// # Arguments
-/// * `subject` - The NATS subject to subscribe to (e.g., "events.user.created")
-/// * `fn_oid` - The OID of the PostgreSQL function to invoke when a message is received
+/// * `name` - The name of the stream
+/// * `subject` *(optional)* - Only remove messages on this subject, wildcards are allowed
+/// * `keep` *(optional)* - Number of the newest messages to keep
 ///
 /// # Returns
-/// * `Ok(())` - If the subscription request was successfully sent
+/// * `Ok(i64)` - The number of removed messages
 ///
 /// # SQL Usage
 /// ```sql
-/// SELECT nats_subscribe('events.user.created', 'schema.handle_user_created'::regproc);
-/// SELECT nats_subscribe('events.user.created', 'schema.log_user_created'::regproc);
+/// SELECT nats_stream_purge('ORDERS');
+/// SELECT nats_stream_purge('ORDERS', subject => 'orders.cancelled', keep => 10);
 /// ```
-///
-/// # Warning
-/// The specified PostgreSQL function **must accept a single argument of type `bytea`**,
-/// which will contain the message payload received from NATS.
 #[pg_extern]
-#[cfg(feature = "sub")]
-pub fn nats_subscribe(subject: String, fn_oid: pg_sys::Oid) -> anyhow::Result<()> {
-    // SAFETY: Calling Postgres backend function which takes no arguments,
-    // has no side effects, and does not rely on any Rust-managed memory.
-    // Safe as long as we are running inside a valid Postgres backend process.
-    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
-        anyhow::bail!("Subscriptions are not allowed in replica mode");
-    }
-
-    let fn_name = resolve_bytea_name(fn_oid)?
-        .ok_or_else(|| anyhow::anyhow!("Failed to get function name"))?;
+pub fn nats_stream_purge(
+    name: &str,
+    subject: pgrx::default!(Option<String>, "NULL"),
+    keep: pgrx::default!(Option<i64>, "NULL"),
+) -> anyhow::Result<i64> {
+    let keep = keep
+        .map(u64::try_from)
+        .transpose()
+        .map_err(|_| anyhow::anyhow!("Keep must not be negative"))?;
 
-    crate::bgw::launcher::send_message_to_launcher_with_retry(
-        &crate::bgw::LAUNCHER_MESSAGE_BUS,
-        crate::bgw::launcher::message::LauncherMessage::Subscribe {
-            // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
-            // before extension code is executed. Postgres backends are single-threaded,
-            // and this variable is immutable after initialization.
-            db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
-            subject,
-            fn_name,
-        },
-        5,
-        std::time::Duration::from_secs(1),
-    )
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.purge_stream(name, subject, keep))
+            .and_then(|purged| {
+                i64::try_from(purged)
+                    .map_err(|_| anyhow::anyhow!("Purged message count {purged} is out of range"))
+            })
+    })
 }
 
-/// Unsubscribes from a NATS subject and removes the associated PostgreSQL callback function.
-///
-/// Only the specified callback function will be removed from the subject. Other callbacks
-/// subscribed to the same subject will remain active.
+/// Retrieves a message stored in a JetStream stream by its sequence number.
 ///
 /// # Arguments
-/// * `subject` - The NATS subject to unsubscribe from
-/// * `fn_oid` - The OID of the previously registered PostgreSQL function
+/// * `stream` - The name of the stream
+/// * `seq` - The sequence number of the message
 ///
 /// # Returns
-/// * `Ok(())` - If the unsubscription request was successfully sent
+/// * `Ok(_)` - The message with its headers and publish time,
+///   or no rows if there is no message with this sequence number
 ///
 /// # SQL Usage
 /// ```sql
-/// SELECT nats_unsubscribe('events.user.created', 'schema.handle_user_created'::regproc);
+/// SELECT subject, payload, headers, published FROM nats_stream_get_message('ORDERS', 42);
 /// ```
+#[allow(clippy::type_complexity)]
 #[pg_extern]
-#[cfg(feature = "sub")]
-pub fn nats_unsubscribe(subject: String, fn_oid: pg_sys::Oid) -> anyhow::Result<()> {
-    // SAFETY: Calling Postgres backend function which takes no arguments,
-    // has no side effects, and does not rely on any Rust-managed memory.
-    // Safe as long as we are running inside a valid Postgres backend process.
-    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
-        anyhow::bail!("Subscriptions are not allowed in replica mode");
-    }
-
-    let fn_name = resolve_bytea_name(fn_oid)?
-        .ok_or_else(|| anyhow::anyhow!("Failed to get function name"))?;
-
-    crate::bgw::launcher::send_message_to_launcher_with_retry(
+pub fn nats_stream_get_message(
+    stream: &str,
+    seq: i64,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(subject, String),
+            name!(seq, i64),
+            name!(payload, Vec<u8>),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(published, pgrx::datum::TimestampWithTimeZone),
+        ),
+    >,
+> {
+    let seq =
+        u64::try_from(seq).map_err(|_| anyhow::anyhow!("Sequence number must not be negative"))?;
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_stream_message(stream, seq))
+            .and_then(super::conv::map_stream_message)
+    })
+}
+
+/// Retrieves the last message stored in a JetStream stream on a subject.
+///
+/// # Arguments
+/// * `stream` - The name of the stream
+/// * `subject` - The subject of the message
+///
+/// # Returns
+/// * `Ok(_)` - The message with its headers and publish time,
+///   or no rows if there is no message on this subject
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT seq, payload, published FROM nats_stream_get_last('ORDERS', 'orders.42');
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+pub fn nats_stream_get_last(
+    stream: &str,
+    subject: &str,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(subject, String),
+            name!(seq, i64),
+            name!(payload, Vec<u8>),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(published, pgrx::datum::TimestampWithTimeZone),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_last_stream_message(stream, subject))
+            .and_then(super::conv::map_stream_message)
+    })
+}
+
+/// Creates a JetStream consumer on a stream, or updates it if it already exists.
+///
+/// A consumer with a `deliver_subject` is a push consumer, otherwise it is a pull consumer.
+///
+/// # Arguments
+/// * `stream` - The name of the stream
+/// * `config` - Consumer configuration as a `jsonb` object, in the JetStream API format
+///   (`durable_name`, `deliver_subject`, `filter_subject`, `filter_subjects`, `ack_policy`,
+///   `ack_wait`, `max_deliver`, `backoff` in nanoseconds, ...)
+///
+/// # Returns
+/// * `Ok(_)` - A row with the configuration and state of the consumer
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_consumer_create('ORDERS', '{
+///     "durable_name": "billing",
+///     "filter_subject": "orders.created",
+///     "ack_policy": "explicit",
+///     "max_deliver": 5,
+///     "backoff": [1000000000, 10000000000]
+/// }');
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+pub fn nats_consumer_create(
+    stream: &str,
+    config: pgrx::JsonB,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(stream, String),
+            name!(name, String),
+            name!(config, pgrx::JsonB),
+            name!(created, pgrx::datum::TimestampWithTimeZone),
+            name!(delivered_stream_seq, i64),
+            name!(delivered_consumer_seq, i64),
+            name!(ack_floor_stream_seq, i64),
+            name!(ack_floor_consumer_seq, i64),
+            name!(num_pending, i64),
+            name!(num_ack_pending, i64),
+            name!(num_redelivered, i64),
+            name!(num_waiting, i64),
+        ),
+    >,
+> {
+    let config = super::conv::consumer_config_from_json(config.0)?;
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.create_consumer(stream, config))
+            .and_then(|v| super::conv::map_consumer_info(std::iter::once(v)))
+    })
+}
+
+/// Deletes a JetStream consumer.
+///
+/// # Arguments
+/// * `stream` - The name of the stream
+/// * `name` - The name of the consumer
+///
+/// # Returns
+/// * `Ok(())` - If the consumer was deleted
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_consumer_delete('ORDERS', 'billing');
+/// ```
+#[pg_extern]
+pub fn nats_consumer_delete(stream: &str, name: &str) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.delete_consumer(stream, name))
+    })
+}
+
+/// Retrieves the configuration and state of a JetStream consumer.
+///
+/// # Arguments
+/// * `stream` - The name of the stream
+/// * `name` - The name of the consumer
+///
+/// # Returns
+/// * `Ok(_)` - A row with the configuration and state of the consumer, including
+///   the number of pending messages and the ack floor
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT num_pending, ack_floor_stream_seq FROM nats_consumer_info('ORDERS', 'billing');
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+pub fn nats_consumer_info(
+    stream: &str,
+    name: &str,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(stream, String),
+            name!(name, String),
+            name!(config, pgrx::JsonB),
+            name!(created, pgrx::datum::TimestampWithTimeZone),
+            name!(delivered_stream_seq, i64),
+            name!(delivered_consumer_seq, i64),
+            name!(ack_floor_stream_seq, i64),
+            name!(ack_floor_consumer_seq, i64),
+            name!(num_pending, i64),
+            name!(num_ack_pending, i64),
+            name!(num_redelivered, i64),
+            name!(num_waiting, i64),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_consumer_info(stream, name))
+            .and_then(|v| super::conv::map_consumer_info(std::iter::once(v)))
+    })
+}
+
+/// Retrieves the configuration and state of all consumers of a JetStream stream.
+///
+/// # Arguments
+/// * `stream` - The name of the stream
+///
+/// # Returns
+/// * `Ok(_)` - A row for every consumer of the stream
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT name, num_pending, num_ack_pending FROM nats_consumer_list('ORDERS');
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+pub fn nats_consumer_list(
+    stream: &str,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(stream, String),
+            name!(name, String),
+            name!(config, pgrx::JsonB),
+            name!(created, pgrx::datum::TimestampWithTimeZone),
+            name!(delivered_stream_seq, i64),
+            name!(delivered_consumer_seq, i64),
+            name!(ack_floor_stream_seq, i64),
+            name!(ack_floor_consumer_seq, i64),
+            name!(num_pending, i64),
+            name!(num_ack_pending, i64),
+            name!(num_redelivered, i64),
+            name!(num_waiting, i64),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_consumer_list(stream))
+            .and_then(super::conv::map_consumer_info)
+    })
+}
+
+/// Fetches a batch of messages from a JetStream pull consumer.
+///
+/// # Arguments
+/// * `stream` - The name of the stream
+/// * `consumer` - The name of the pull consumer
+/// * `batch` - The maximum number of messages to fetch
+/// * `expires` *(optional)* - How long to wait for the batch to fill up,
+///   if not set only the messages already available are returned
+///
+/// # Returns
+/// * `Ok(_)` - A row for every fetched message, the `reply` column is the subject
+///   used to acknowledge it with `nats_ack`, `nats_nak`, `nats_term` or `nats_in_progress`
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT subject, payload, reply FROM nats_fetch('ORDERS', 'billing', 100, expires => '5 seconds');
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+pub fn nats_fetch(
+    stream: &str,
+    consumer: &str,
+    batch: i32,
+    expires: pgrx::default!(Option<pgrx::datum::Interval>, "NULL"),
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(subject, String),
+            name!(payload, Vec<u8>),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(stream_seq, i64),
+            name!(consumer_seq, i64),
+            name!(delivered, i64),
+            name!(published, pgrx::datum::TimestampWithTimeZone),
+            name!(reply, Option<String>),
+        ),
+    >,
+> {
+    let batch = usize::try_from(batch)
+        .ok()
+        .filter(|batch| *batch > 0)
+        .ok_or_else(|| anyhow::anyhow!("Batch must be a positive number"))?;
+    let expires = expires
+        .map(std::time::Duration::try_from)
+        .transpose()
+        .map_err(|_| anyhow::anyhow!("Expires must be a positive interval"))?;
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(
+                ctx.nats_connection
+                    .fetch_messages(stream, consumer, batch, expires),
+            )
+            .and_then(super::conv::map_jetstream_message)
+    })
+}
+
+/// Acknowledges a message fetched from a JetStream consumer.
+///
+/// Like the other acknowledgement functions, the acknowledgement is sent only when
+/// the current transaction commits and is discarded if it aborts. The transaction
+/// cannot be prepared.
+///
+/// # Arguments
+/// * `reply` - The reply subject of the fetched message
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_ack('$JS.ACK.ORDERS.billing.1.5.5.1700000000000000000.0');
+/// ```
+#[pg_extern]
+pub fn nats_ack(reply: &str) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| ctx.queue_ack(reply, async_nats::jetstream::AckKind::Ack));
+
+    Ok(())
+}
+
+/// Negatively acknowledges a message fetched from a JetStream consumer,
+/// so that it is redelivered.
+///
+/// # Arguments
+/// * `reply` - The reply subject of the fetched message
+/// * `delay` *(optional)* - How long the server waits before redelivering the message
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_nak('$JS.ACK.ORDERS.billing.1.5.5.1700000000000000000.0', delay => '30 seconds');
+/// ```
+#[pg_extern]
+pub fn nats_nak(
+    reply: &str,
+    delay: pgrx::default!(Option<pgrx::datum::Interval>, "NULL"),
+) -> anyhow::Result<()> {
+    let delay = delay
+        .map(std::time::Duration::try_from)
+        .transpose()
+        .map_err(|_| anyhow::anyhow!("Delay must be a positive interval"))?;
+
+    CTX.with_borrow_mut(|ctx| ctx.queue_ack(reply, async_nats::jetstream::AckKind::Nak(delay)));
+
+    Ok(())
+}
+
+/// Terminates a message fetched from a JetStream consumer,
+/// so that it is never redelivered.
+///
+/// # Arguments
+/// * `reply` - The reply subject of the fetched message
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_term('$JS.ACK.ORDERS.billing.1.5.5.1700000000000000000.0');
+/// ```
+#[pg_extern]
+pub fn nats_term(reply: &str) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| ctx.queue_ack(reply, async_nats::jetstream::AckKind::Term));
+
+    Ok(())
+}
+
+/// Tells the server that a message fetched from a JetStream consumer is still
+/// being processed, resetting its acknowledgement timer.
+///
+/// # Arguments
+/// * `reply` - The reply subject of the fetched message
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_in_progress('$JS.ACK.ORDERS.billing.1.5.5.1700000000000000000.0');
+/// ```
+#[pg_extern]
+pub fn nats_in_progress(reply: &str) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| ctx.queue_ack(reply, async_nats::jetstream::AckKind::Progress));
+
+    Ok(())
+}
+
+/// Retrieves a file's content from the NATS object store by its name.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The name of the file to retrieve
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - The file content as a byte array if successful
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_get_file('documents', 'report.pdf');
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_get_file(store: String, name: &str) -> anyhow::Result<Vec<u8>> {
+    CTX.with_borrow_mut(|ctx| ctx.rt.block_on(ctx.nats_connection.get_file(store, name)))
+}
+
+/// Retrieves a byte range of a file from the NATS object store,
+/// fetching only the chunks that cover the range.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The name of the file to read
+/// * `offset` - Position of the first byte to read
+/// * `length` - Maximum number of bytes to read
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - The bytes of the range; shorter than `length` if the file ends before
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_get_file_range('documents', 'report.pdf', 0, 1024);
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_get_file_range(
+    store: String,
+    name: &str,
+    offset: i64,
+    length: i64,
+) -> anyhow::Result<Vec<u8>> {
+    let offset =
+        u64::try_from(offset).map_err(|_| anyhow::anyhow!("Offset must not be negative"))?;
+    let length =
+        u64::try_from(length).map_err(|_| anyhow::anyhow!("Length must not be negative"))?;
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt.block_on(
+            ctx.nats_connection
+                .get_file_range(store, name, offset, length),
+        )
+    })
+}
+
+/// Uploads a file to the NATS object store.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The name under which to store the file
+/// * `content` - The file content as a byte array
+/// * `description` *(optional)* - Description of the file
+/// * `metadata` *(optional)* - Metadata of the file, as a `jsonb` object
+/// * `headers` *(optional)* - Headers stored with the file, as a `jsonb` object
+///
+/// # Returns
+/// * `Ok(_)` - A row with the NUID, size, number of chunks and digest of the stored file
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_put_file('documents', 'report.pdf', 'binary data'::bytea);
+///
+/// SELECT * FROM nats_put_file(
+///     'documents', 'report.pdf', 'binary data'::bytea,
+///     description => 'Monthly report',
+///     metadata => '{"owner": "finance"}',
+///     headers => '{"Content-Type": "application/pdf"}'
+/// );
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_put_file(
+    store: String,
+    name: &str,
+    content: Vec<u8>,
+    description: pgrx::default!(Option<String>, "NULL"),
+    metadata: pgrx::default!(Option<pgrx::JsonB>, "NULL"),
+    headers: pgrx::default!(Option<pgrx::JsonB>, "NULL"),
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(nuid, String),
+            name!(size, i64),
+            name!(chunks, i64),
+            name!(digest, Option<String>),
+        ),
+    >,
+> {
+    let metadata = metadata
+        .map(|metadata| super::conv::object_metadata_from_json(metadata.0))
+        .transpose()?
+        .unwrap_or_default();
+
+    let meta = async_nats::jetstream::object_store::ObjectMetadata {
+        name: name.to_string(),
+        description,
+        metadata,
+        headers: headers.map(|headers| extract_headers(headers.0)),
+        ..Default::default()
+    };
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.put_file(store, meta, content))
+            .map(super::conv::map_put_object_info)
+    })
+}
+
+/// Uploads the content of a Postgres large object to the NATS object store.
+///
+/// The large object is streamed in chunks, so its size is not limited by the
+/// `bytea` limit and it is never held in memory as a whole.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The name under which to store the file
+/// * `lo_oid` - The OID of the large object to upload
+///
+/// # Returns
+/// * `Ok(())` - If the upload was successful
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_put_file_from_lo('documents', 'backup.tar', lo_import('/tmp/backup.tar'));
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_put_file_from_lo(store: String, name: &str, lo_oid: pg_sys::Oid) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| {
+        let store = ctx
+            .rt
+            .block_on(ctx.nats_connection.get_object_store(store))?;
+
+        // A missing or unreadable large object fails before anything is uploaded
+        let mut chunk = large_object::read(lo_oid, 0, large_object::CHUNK_SIZE)?;
+
+        let (sender, mut reader) = ChunkReader::channel();
+        let name = name.to_string();
+        let upload = ctx
+            .rt
+            .spawn(async move { store.put(name.as_str(), &mut reader).await });
+
+        let mut offset = 0;
+        let read = loop {
+            if chunk.is_empty() {
+                break Ok(());
+            }
+
+            offset += chunk.len() as i64;
+
+            // The upload stopped early, its error is returned below
+            if ctx.rt.block_on(sender.send(Chunk::Data(chunk))).is_err() {
+                break Ok(());
+            }
+
+            chunk = match large_object::read(lo_oid, offset, large_object::CHUNK_SIZE) {
+                Ok(chunk) => chunk,
+                Err(err) => break Err(err),
+            };
+        };
+
+        if let Err(err) = read {
+            // Ending the stream without `Chunk::End` fails the upload before the object
+            // metadata is written, so neither a truncated object nor a new version of
+            // `name` is stored. The upload is awaited so it does not outlive the call.
+            drop(sender);
+            let _ = ctx.rt.block_on(upload);
+
+            return Err(err);
+        }
+
+        let _ = ctx.rt.block_on(sender.send(Chunk::End));
+        let _ = ctx.rt.block_on(upload)??;
+
+        Ok(())
+    })
+}
+
+/// Downloads a file from the NATS object store into a new Postgres large object.
+///
+/// The file is streamed in chunks, so its size is not limited by the
+/// `bytea` limit and it is never held in memory as a whole.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The name of the file to retrieve
+///
+/// # Returns
+/// * `Ok(oid)` - The OID of the created large object
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT lo_export(nats_get_file_to_lo('documents', 'backup.tar'), '/tmp/backup.tar');
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_get_file_to_lo(store: String, name: &str) -> anyhow::Result<pg_sys::Oid> {
+    use tokio::io::AsyncReadExt;
+
+    CTX.with_borrow_mut(|ctx| {
+        let mut file = ctx
+            .rt
+            .block_on(ctx.nats_connection.open_file(store, name))?;
+        let lo_oid = large_object::create()?;
+
+        let mut offset = 0;
+        let mut chunk = Vec::with_capacity(large_object::CHUNK_SIZE as usize);
+        loop {
+            chunk.clear();
+            let len = ctx.rt.block_on(
+                (&mut file)
+                    .take(large_object::CHUNK_SIZE as u64)
+                    .read_to_end(&mut chunk),
+            )?;
+            if len == 0 {
+                break;
+            }
+
+            large_object::write(lo_oid, offset, &chunk)?;
+            offset += len as i64;
+        }
+
+        Ok(lo_oid)
+    })
+}
+
+/// Deletes a file from the NATS object store.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The name of the file to delete
+///
+/// # Returns
+/// * `Ok(())` - If the deletion was successful
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_delete_file('documents', 'old_report.pdf');
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_delete_file(store: String, name: &str) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.delete_file(store, name))
+    })
+}
+
+/// Retrieves metadata information for a specific file in the NATS object store.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The name of the file
+///
+/// # Returns
+/// * `Ok(_)` - A row with file metadata if successful
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_get_file_info('documents', 'report.pdf');
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_get_file_info(
+    store: String,
+    name: &str,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(name, String),
+            name!(description, Option<String>),
+            name!(metadata, pgrx::JsonB),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(options, Option<pgrx::JsonB>),
+            name!(bucket, String),
+            name!(nuid, String),
+            name!(size, i64),
+            name!(chunks, i64),
+            name!(modified, Option<String>),
+            name!(digest, Option<String>),
+            name!(delete, bool),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_file_info(store, name))
+            .map(|v| super::conv::map_object_info(std::iter::once(v)))
+    })
+}
+
+/// Retrieves a list of all files in the specified NATS object store.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `prefix` *(optional)* - Only list files whose name starts with this prefix
+/// * `include_deleted` *(optional)* - Also list deleted files, with `delete` set to `true`
+///
+/// # Returns
+/// * `Ok(_)` - Iterator with metadata for all files
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_get_file_list('documents');
+/// SELECT * FROM nats_get_file_list('documents', prefix => 'reports/', include_deleted => true);
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_get_file_list(
+    store: String,
+    prefix: pgrx::default!(Option<&str>, "NULL"),
+    include_deleted: pgrx::default!(bool, false),
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(name, String),
+            name!(description, Option<String>),
+            name!(metadata, pgrx::JsonB),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(options, Option<pgrx::JsonB>),
+            name!(bucket, String),
+            name!(nuid, String),
+            name!(size, i64),
+            name!(chunks, i64),
+            name!(modified, Option<String>),
+            name!(digest, Option<String>),
+            name!(delete, bool),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(
+                ctx.nats_connection
+                    .get_file_list(store, prefix, include_deleted),
+            )
+            .map(|v| super::conv::map_object_info(v))
+    })
+}
+
+/// Creates a NATS object store with the given configuration.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `description` *(optional)* - Description of the object store
+/// * `ttl` *(optional)* - How long objects are kept; kept forever if not set
+/// * `max_bytes` *(optional)* - Maximum size of the object store in bytes; unlimited if not set
+/// * `replicas` *(optional)* - Number of replicas of the object store
+/// * `compression` *(optional)* - Whether the object store data is compressed
+///
+/// # Returns
+/// * `Ok(())` - If the object store was created, or already exists with the same configuration
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_object_store_create('documents');
+/// SELECT nats_object_store_create('reports', ttl => '30 days', max_bytes => 1073741824, replicas => 3, compression => true);
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_object_store_create(
+    store: String,
+    description: pgrx::default!(Option<String>, "NULL"),
+    ttl: pgrx::default!(Option<pgrx::datum::Interval>, "NULL"),
+    max_bytes: pgrx::default!(Option<i64>, "NULL"),
+    replicas: pgrx::default!(Option<i32>, "NULL"),
+    compression: pgrx::default!(bool, false),
+) -> anyhow::Result<()> {
+    let mut config = async_nats::jetstream::object_store::Config {
+        bucket: store,
+        description,
+        compression,
+        ..Default::default()
+    };
+
+    if let Some(ttl) = ttl {
+        config.max_age = std::time::Duration::try_from(ttl)
+            .map_err(|_| anyhow::anyhow!("TTL must be a positive interval"))?;
+    }
+    if let Some(max_bytes) = max_bytes {
+        config.max_bytes = max_bytes;
+    }
+    if let Some(replicas) = replicas {
+        config.num_replicas = usize::try_from(replicas)
+            .map_err(|_| anyhow::anyhow!("Replicas must not be negative"))?;
+    }
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.create_object_store(config))
+    })
+}
+
+/// Seals a NATS object store, so that no objects can be added, changed or deleted.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+///
+/// # Returns
+/// * `Ok(())` - If the object store was sealed
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_object_store_seal('documents');
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_object_store_seal(store: &str) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.seal_object_store(store))
+    })
+}
+
+/// Deletes a NATS object store together with all of its objects.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+///
+/// # Returns
+/// * `Ok(())` - If the object store was deleted
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_object_store_delete('documents');
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_object_store_delete(store: &str) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.delete_object_store(store))
+    })
+}
+
+/// Retrieves the configuration and the current size of a NATS object store.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+///
+/// # Returns
+/// * `Ok(_)` - A row describing the object store
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_object_store_status('documents');
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_object_store_status(
+    store: &str,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(store, String),
+            name!(description, Option<String>),
+            name!(ttl, Option<pgrx::datum::Interval>),
+            name!(max_bytes, Option<i64>),
+            name!(replicas, i32),
+            name!(storage, String),
+            name!(compressed, bool),
+            name!(sealed, bool),
+            name!(size, i64),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_object_store_status(store))
+            .map(super::conv::map_object_store_status)
+    })
+}
+
+/// Creates a link to a file, which is read as the file it points to.
+///
+/// # Arguments
+/// * `store` - The name of the object store to create the link in
+/// * `name` - The name of the link
+/// * `target_store` - The name of the object store holding the file
+/// * `target_name` - The name of the file to link to
+///
+/// # Returns
+/// * `Ok(_)` - Metadata of the created link
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_put_link('documents', 'latest.pdf', 'reports', 'report-2025-10.pdf');
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_put_link(
+    store: String,
+    name: String,
+    target_store: String,
+    target_name: &str,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(name, String),
+            name!(description, Option<String>),
+            name!(metadata, pgrx::JsonB),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(options, Option<pgrx::JsonB>),
+            name!(bucket, String),
+            name!(nuid, String),
+            name!(size, i64),
+            name!(chunks, i64),
+            name!(modified, Option<String>),
+            name!(digest, Option<String>),
+            name!(delete, bool),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(
+                ctx.nats_connection
+                    .put_link(store, name, target_store, target_name),
+            )
+            .map(|v| super::conv::map_object_info(std::iter::once(v)))
+    })
+}
+
+/// Creates a link to a whole object store.
+///
+/// # Arguments
+/// * `store` - The name of the object store to create the link in
+/// * `name` - The name of the link
+/// * `target_store` - The name of the object store to link to
+///
+/// # Returns
+/// * `Ok(_)` - Metadata of the created link
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_put_store_link('documents', 'archive', 'documents_archive');
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_put_store_link(
+    store: String,
+    name: String,
+    target_store: String,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(name, String),
+            name!(description, Option<String>),
+            name!(metadata, pgrx::JsonB),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(options, Option<pgrx::JsonB>),
+            name!(bucket, String),
+            name!(nuid, String),
+            name!(size, i64),
+            name!(chunks, i64),
+            name!(modified, Option<String>),
+            name!(digest, Option<String>),
+            name!(delete, bool),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(
+                ctx.nats_connection
+                    .put_store_link(store, name, target_store),
+            )
+            .map(|v| super::conv::map_object_info(std::iter::once(v)))
+    })
+}
+
+/// Subscribes to a NATS subject and associates it with a PostgreSQL callback function.
+///
+/// Multiple callback functions can be subscribed to the same subject — each will be invoked
+/// independently when a matching message is received.
+///
+/// When a queue group is given, the subject is subscribed as a member of that group, and
+/// each message is delivered to only one of the subscribers sharing the group, e.g. the
+/// workers of several databases or clusters. Subscribing an already subscribed function
+/// again moves it to the new queue group.
+///
+/// When the function fails, the message is passed to it again up to `max_retries` times,
+/// waiting 1s, 2s, 4s, ... between attempts. A message that still fails is a dead letter:
+/// it is published to `dead_letter_subject` and recorded in `pgnats.dead_letters` when
+/// requested, and dropped otherwise. Pending retries are kept in the memory of the
+/// background worker and are lost when it restarts or its configuration changes. A retried
+/// message is passed to the function after the messages received in the meantime.
+///
+/// A function taking arrays (`bytea[]`, `text[]`, `json[]` or `jsonb[]`, optionally followed
+/// by `(subjects text[], headers jsonb[], replies text[])`) is a batch callback: it receives
+/// up to `batch_size` messages in one call and one transaction. A batch is delivered once it
+/// is full or its oldest message has waited `batch_linger`, and fails or is retried as a whole.
+///
+/// # Arguments
+/// * `subject` - The NATS subject to subscribe to (e.g., "events.user.created")
+/// * `fn_oid` - The OID of the PostgreSQL function to invoke when a message is received
+/// * `queue_group` *(optional)* – The queue group to join
+/// * `max_retries` *(optional)* – How often a failed call is retried, `0` by default
+/// * `dead_letter_subject` *(optional)* – Subject to publish dead letters to
+/// * `dead_letter_table` *(optional)* – Whether to record dead letters in `pgnats.dead_letters`
+/// * `batch_size` *(optional)* – Maximum number of messages passed to a batch callback
+/// * `batch_linger` *(optional)* – How long a batch callback waits for a batch to fill up
+///
+/// # Returns
+/// * `Ok(())` - If the subscription request was successfully sent
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_subscribe('events.user.created', 'schema.handle_user_created'::regproc);
+/// SELECT nats_subscribe('events.user.created', 'schema.log_user_created'::regproc);
+/// SELECT nats_subscribe('orders.new', 'schema.handle_order'::regproc, 'order_processors');
+/// SELECT nats_subscribe('payments', 'schema.handle_payment'::regproc,
+///     max_retries => 3, dead_letter_subject => 'payments.dead', dead_letter_table => true);
+/// SELECT nats_subscribe('metrics', 'schema.insert_metrics'::regproc,
+///     batch_size => 500, batch_linger => '200 milliseconds');
+/// ```
+///
+/// # Warning
+/// The specified PostgreSQL function **must accept the message payload as `bytea`, `text`,
+/// `json` or `jsonb`**, optionally followed by `(subject text, headers jsonb, reply text)`,
+/// or the same types as arrays. The extended form tells wildcard subscriptions which subject
+/// the message was sent to.
+#[pg_extern]
+#[cfg(feature = "sub")]
+pub fn nats_subscribe(
+    subject: String,
+    fn_oid: pg_sys::Oid,
+    queue_group: pgrx::default!(Option<String>, "NULL"),
+    max_retries: pgrx::default!(i32, 0),
+    dead_letter_subject: pgrx::default!(Option<String>, "NULL"),
+    dead_letter_table: pgrx::default!(bool, false),
+    batch_size: pgrx::default!(i32, 1),
+    batch_linger: pgrx::default!(Option<pgrx::datum::Interval>, "NULL"),
+) -> anyhow::Result<()> {
+    // SAFETY: Calling Postgres backend function which takes no arguments,
+    // has no side effects, and does not rely on any Rust-managed memory.
+    // Safe as long as we are running inside a valid Postgres backend process.
+    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
+        anyhow::bail!("Subscriptions are not allowed in replica mode");
+    }
+
+    let max_retries = u32::try_from(max_retries)
+        .map_err(|_| anyhow::anyhow!("Max retries must be a non-negative number"))?;
+    let batch_size = u32::try_from(batch_size)
+        .ok()
+        .filter(|size| *size > 0)
+        .ok_or_else(|| anyhow::anyhow!("Batch size must be a positive number"))?;
+    let batch_linger = batch_linger
+        .map(std::time::Duration::try_from)
+        .transpose()
+        .map_err(|_| anyhow::anyhow!("Batch linger must be a positive interval"))?;
+
+    let (fn_name, batch) =
+        resolve_callback(fn_oid)?.ok_or_else(|| anyhow::anyhow!("Failed to get function name"))?;
+
+    anyhow::ensure!(
+        batch || (batch_size == 1 && batch_linger.is_none()),
+        "Batch size and linger require a function taking arrays of messages"
+    );
+
+    crate::bgw::launcher::send_message_to_launcher_with_retry(
+        &crate::bgw::LAUNCHER_MESSAGE_BUS,
+        crate::bgw::launcher::message::LauncherMessage::Subscribe {
+            // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
+            // before extension code is executed. Postgres backends are single-threaded,
+            // and this variable is immutable after initialization.
+            db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+            subject,
+            fn_name,
+            queue_group,
+            retry: crate::bgw::subscriber::pg_api::RetryPolicy {
+                max_retries,
+                dead_letter_subject,
+                dead_letter_table,
+            },
+            batch: crate::bgw::subscriber::pg_api::BatchPolicy {
+                size: batch_size,
+                linger_ms: batch_linger.map_or(0, |linger| {
+                    u64::try_from(linger.as_millis()).unwrap_or(u64::MAX)
+                }),
+            },
+        },
+        5,
+        std::time::Duration::from_secs(1),
+    )
+}
+
+/// Unsubscribes from a NATS subject and removes the associated PostgreSQL callback function.
+///
+/// Only the specified callback function will be removed from the subject. Other callbacks
+/// subscribed to the same subject will remain active.
+///
+/// # Arguments
+/// * `subject` - The NATS subject to unsubscribe from
+/// * `fn_oid` - The OID of the previously registered PostgreSQL function
+///
+/// # Returns
+/// * `Ok(())` - If the unsubscription request was successfully sent
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_unsubscribe('events.user.created', 'schema.handle_user_created'::regproc);
+/// ```
+#[pg_extern]
+#[cfg(feature = "sub")]
+pub fn nats_unsubscribe(subject: String, fn_oid: pg_sys::Oid) -> anyhow::Result<()> {
+    // SAFETY: Calling Postgres backend function which takes no arguments,
+    // has no side effects, and does not rely on any Rust-managed memory.
+    // Safe as long as we are running inside a valid Postgres backend process.
+    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
+        anyhow::bail!("Subscriptions are not allowed in replica mode");
+    }
+
+    let fn_name = resolve_callback_name(fn_oid)?
+        .ok_or_else(|| anyhow::anyhow!("Failed to get function name"))?;
+
+    crate::bgw::launcher::send_message_to_launcher_with_retry(
+        &crate::bgw::LAUNCHER_MESSAGE_BUS,
+        crate::bgw::launcher::message::LauncherMessage::Unsubscribe {
+            // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
+            // before extension code is executed. Postgres backends are single-threaded,
+            // and this variable is immutable after initialization.
+            db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+            subject,
+            fn_name,
+        },
+        5,
+        std::time::Duration::from_secs(1),
+    )
+}
+
+/// Subscribes to a JetStream stream through a durable pull consumer and associates it with
+/// a PostgreSQL callback function.
+///
+/// The consumer is created if it does not exist yet, delivering a message at most
+/// `max_retries + 1` times. Each message is acknowledged only after the transaction running
+/// the callback commits; when the callback fails the message is negatively acknowledged and
+/// redelivered with an increasing delay. Several functions can share a consumer: a
+/// redelivered message is only passed to the functions that have not processed it yet.
+/// A message that still fails on its last delivery is a dead letter: it is published to
+/// `dead_letter_subject` and recorded in `pgnats.dead_letters` when requested, and then
+/// terminated so that it is not delivered again.
+///
+/// Messages published while the background worker is down or the instance is a replica
+/// stay in the consumer and are delivered later.
+///
+/// # Arguments
+/// * `stream` - The name of an existing JetStream stream
+/// * `consumer` - The name of the durable consumer to read from
+/// * `fn_oid` - The OID of the PostgreSQL function to invoke for every message
+/// * `max_retries` *(optional)* – How often a failed message is redelivered, `5` by default
+/// * `dead_letter_subject` *(optional)* – Subject to publish dead letters to
+/// * `dead_letter_table` *(optional)* – Whether to record dead letters in `pgnats.dead_letters`
+///
+/// # Returns
+/// * `Ok(())` - If the subscription request was successfully sent
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_subscribe_stream('ORDERS', 'pg_orders', 'schema.handle_order'::regproc);
+/// SELECT nats_subscribe_stream('ORDERS', 'pg_orders', 'schema.handle_order'::regproc,
+///     max_retries => 3, dead_letter_table => true);
+/// ```
+///
+/// # Warning
+/// The specified PostgreSQL function takes the same arguments as a [`nats_subscribe`]
+/// callback; `reply` is always `NULL`. Delivery is at-least-once, so the callback should
+/// be idempotent.
+#[pg_extern]
+#[cfg(feature = "sub")]
+pub fn nats_subscribe_stream(
+    stream: String,
+    consumer: String,
+    fn_oid: pg_sys::Oid,
+    max_retries: pgrx::default!(i32, 5),
+    dead_letter_subject: pgrx::default!(Option<String>, "NULL"),
+    dead_letter_table: pgrx::default!(bool, false),
+) -> anyhow::Result<()> {
+    // SAFETY: Calling Postgres backend function which takes no arguments,
+    // has no side effects, and does not rely on any Rust-managed memory.
+    // Safe as long as we are running inside a valid Postgres backend process.
+    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
+        anyhow::bail!("Subscriptions are not allowed in replica mode");
+    }
+
+    let max_retries = u32::try_from(max_retries)
+        .map_err(|_| anyhow::anyhow!("Max retries must be a non-negative number"))?;
+
+    let fn_name = resolve_callback_name(fn_oid)?
+        .ok_or_else(|| anyhow::anyhow!("Failed to get function name"))?;
+
+    crate::bgw::launcher::send_message_to_launcher_with_retry(
+        &crate::bgw::LAUNCHER_MESSAGE_BUS,
+        crate::bgw::launcher::message::LauncherMessage::StreamSubscribe {
+            // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
+            // before extension code is executed. Postgres backends are single-threaded,
+            // and this variable is immutable after initialization.
+            db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+            stream,
+            consumer,
+            fn_name,
+            retry: crate::bgw::subscriber::pg_api::RetryPolicy {
+                max_retries,
+                dead_letter_subject,
+                dead_letter_table,
+            },
+        },
+        5,
+        std::time::Duration::from_secs(1),
+    )
+}
+
+/// Removes a PostgreSQL callback function from a JetStream stream subscription.
+///
+/// The durable consumer itself is kept, so messages published afterwards are delivered
+/// once a callback is subscribed again. Use [`nats_consumer_delete`] to remove it.
+///
+/// # Arguments
+/// * `stream` - The name of the JetStream stream
+/// * `consumer` - The name of the durable consumer
+/// * `fn_oid` - The OID of the previously registered PostgreSQL function
+///
+/// # Returns
+/// * `Ok(())` - If the unsubscription request was successfully sent
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_unsubscribe_stream('ORDERS', 'pg_orders', 'schema.handle_order'::regproc);
+/// ```
+#[pg_extern]
+#[cfg(feature = "sub")]
+pub fn nats_unsubscribe_stream(
+    stream: String,
+    consumer: String,
+    fn_oid: pg_sys::Oid,
+) -> anyhow::Result<()> {
+    // SAFETY: Calling Postgres backend function which takes no arguments,
+    // has no side effects, and does not rely on any Rust-managed memory.
+    // Safe as long as we are running inside a valid Postgres backend process.
+    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
+        anyhow::bail!("Subscriptions are not allowed in replica mode");
+    }
+
+    let fn_name = resolve_callback_name(fn_oid)?
+        .ok_or_else(|| anyhow::anyhow!("Failed to get function name"))?;
+
+    crate::bgw::launcher::send_message_to_launcher_with_retry(
+        &crate::bgw::LAUNCHER_MESSAGE_BUS,
+        crate::bgw::launcher::message::LauncherMessage::StreamUnsubscribe {
+            // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
+            // before extension code is executed. Postgres backends are single-threaded,
+            // and this variable is immutable after initialization.
+            db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+            stream,
+            consumer,
+            fn_name,
+        },
+        5,
+        std::time::Duration::from_secs(1),
+    )
+}
+
+/// Answers requests sent to a NATS subject with the result of a PostgreSQL function.
+///
+/// For every request the function is called with the request message, and its return value
+/// is published to the reply subject after the transaction commits. If the function fails,
+/// the reply carries an empty payload and the error in the `Nats-Service-Error` and
+/// `Nats-Service-Error-Code` headers. Messages published without a reply subject are ignored.
+///
+/// A subject has a single responder; registering another function replaces it. When a queue
+/// group is given, requests are balanced between all responders sharing the group.
+///
+/// # Arguments
+/// * `subject` - The NATS subject to answer requests on (e.g., "users.get")
+/// * `fn_oid` - The OID of the PostgreSQL function computing the response
+/// * `queue_group` *(optional)* – The queue group to join
+///
+/// # Returns
+/// * `Ok(())` - If the request was successfully sent
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_respond('users.get', 'schema.get_user'::regproc);
+/// SELECT nats_respond('users.get', 'schema.get_user'::regproc, 'user_service');
+/// ```
+///
+/// # Warning
+/// The specified PostgreSQL function takes the same arguments as a [`nats_subscribe`]
+/// callback and **must return `bytea`, `text`, `json` or `jsonb`**. A `NULL` result is sent
+/// as an empty payload.
+#[pg_extern]
+#[cfg(feature = "sub")]
+pub fn nats_respond(
+    subject: String,
+    fn_oid: pg_sys::Oid,
+    queue_group: pgrx::default!(Option<String>, "NULL"),
+) -> anyhow::Result<()> {
+    // SAFETY: Calling Postgres backend function which takes no arguments,
+    // has no side effects, and does not rely on any Rust-managed memory.
+    // Safe as long as we are running inside a valid Postgres backend process.
+    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
+        anyhow::bail!("Subscriptions are not allowed in replica mode");
+    }
+
+    let fn_name = resolve_responder_name(fn_oid)?
+        .ok_or_else(|| anyhow::anyhow!("Failed to get function name"))?;
+
+    crate::bgw::launcher::send_message_to_launcher_with_retry(
+        &crate::bgw::LAUNCHER_MESSAGE_BUS,
+        crate::bgw::launcher::message::LauncherMessage::Respond {
+            // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
+            // before extension code is executed. Postgres backends are single-threaded,
+            // and this variable is immutable after initialization.
+            db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+            subject,
+            fn_name,
+            queue_group,
+        },
+        5,
+        std::time::Duration::from_secs(1),
+    )
+}
+
+/// Stops answering requests sent to a NATS subject.
+///
+/// # Arguments
+/// * `subject` - The NATS subject previously registered with [`nats_respond`]
+///
+/// # Returns
+/// * `Ok(())` - If the request was successfully sent
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_unrespond('users.get');
+/// ```
+#[pg_extern]
+#[cfg(feature = "sub")]
+pub fn nats_unrespond(subject: String) -> anyhow::Result<()> {
+    // SAFETY: Calling Postgres backend function which takes no arguments,
+    // has no side effects, and does not rely on any Rust-managed memory.
+    // Safe as long as we are running inside a valid Postgres backend process.
+    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
+        anyhow::bail!("Subscriptions are not allowed in replica mode");
+    }
+
+    crate::bgw::launcher::send_message_to_launcher_with_retry(
         &crate::bgw::LAUNCHER_MESSAGE_BUS,
-        crate::bgw::launcher::message::LauncherMessage::Unsubscribe {
+        crate::bgw::launcher::message::LauncherMessage::Unrespond {
             // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
             // before extension code is executed. Postgres backends are single-threaded,
             // and this variable is immutable after initialization.
             db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
             subject,
+        },
+        5,
+        std::time::Duration::from_secs(1),
+    )
+}
+
+/// Registers a NATS micro service hosted by the background worker, or updates the version
+/// and description of an already registered one.
+///
+/// The service answers the `$SRV.PING`, `$SRV.INFO` and `$SRV.STATS` discovery requests, so
+/// that it is listed by `nats micro ls` like any other service. It is announced once its first
+/// endpoint is added with [`nats_service_add_endpoint`]. A new version or description starts a
+/// new instance of the service, which resets its statistics.
+///
+/// # Arguments
+/// * `name` - The service name (letters, digits, `-` and `_`)
+/// * `version` - The service version, a SemVer string (e.g., "1.0.0")
+/// * `description` *(optional)* – A human-readable description
+///
+/// # Returns
+/// * `Ok(())` - If the request was successfully sent
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_service_add('users', '1.0.0', 'User directory');
+/// ```
+#[pg_extern]
+#[cfg(feature = "sub")]
+pub fn nats_service_add(
+    name: String,
+    version: String,
+    description: pgrx::default!(Option<String>, "NULL"),
+) -> anyhow::Result<()> {
+    // SAFETY: Calling Postgres backend function which takes no arguments,
+    // has no side effects, and does not rely on any Rust-managed memory.
+    // Safe as long as we are running inside a valid Postgres backend process.
+    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
+        anyhow::bail!("Subscriptions are not allowed in replica mode");
+    }
+
+    ensure_service_name(&name)?;
+
+    crate::bgw::launcher::send_message_to_launcher_with_retry(
+        &crate::bgw::LAUNCHER_MESSAGE_BUS,
+        crate::bgw::launcher::message::LauncherMessage::ServiceAdd {
+            // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
+            // before extension code is executed. Postgres backends are single-threaded,
+            // and this variable is immutable after initialization.
+            db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+            name,
+            version,
+            description,
+        },
+        5,
+        std::time::Duration::from_secs(1),
+    )
+}
+
+/// Stops a NATS micro service and removes it together with its endpoints.
+///
+/// # Arguments
+/// * `name` - The name of the service
+///
+/// # Returns
+/// * `Ok(())` - If the request was successfully sent
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_service_remove('users');
+/// ```
+#[pg_extern]
+#[cfg(feature = "sub")]
+pub fn nats_service_remove(name: String) -> anyhow::Result<()> {
+    // SAFETY: Calling Postgres backend function which takes no arguments,
+    // has no side effects, and does not rely on any Rust-managed memory.
+    // Safe as long as we are running inside a valid Postgres backend process.
+    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
+        anyhow::bail!("Subscriptions are not allowed in replica mode");
+    }
+
+    crate::bgw::launcher::send_message_to_launcher_with_retry(
+        &crate::bgw::LAUNCHER_MESSAGE_BUS,
+        crate::bgw::launcher::message::LauncherMessage::ServiceRemove {
+            // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
+            // before extension code is executed. Postgres backends are single-threaded,
+            // and this variable is immutable after initialization.
+            db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+            name,
+        },
+        5,
+        std::time::Duration::from_secs(1),
+    )
+}
+
+/// Adds an endpoint to a NATS micro service, answering its requests with the result of a
+/// PostgreSQL function, or replaces the function and subject of an existing endpoint.
+///
+/// Requests are handled like those of [`nats_respond`], and the endpoint statistics
+/// reported by `$SRV.STATS` count every request, failed requests and the processing time.
+/// Requests are balanced between all instances of the service.
+///
+/// # Arguments
+/// * `service` - The name of a registered service
+/// * `endpoint` - The endpoint name (letters, digits, `-` and `_`)
+/// * `fn_oid` - The OID of the PostgreSQL function computing the response
+/// * `subject` *(optional)* – The subject of the endpoint, the endpoint name by default
+///
+/// # Returns
+/// * `Ok(())` - If the request was successfully sent
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_service_add_endpoint('users', 'get', 'schema.get_user'::regproc, 'users.get');
+/// ```
+///
+/// # Warning
+/// The specified PostgreSQL function takes the same arguments as a [`nats_subscribe`]
+/// callback and **must return `bytea`, `text`, `json` or `jsonb`**.
+#[pg_extern]
+#[cfg(feature = "sub")]
+pub fn nats_service_add_endpoint(
+    service: String,
+    endpoint: String,
+    fn_oid: pg_sys::Oid,
+    subject: pgrx::default!(Option<String>, "NULL"),
+) -> anyhow::Result<()> {
+    // SAFETY: Calling Postgres backend function which takes no arguments,
+    // has no side effects, and does not rely on any Rust-managed memory.
+    // Safe as long as we are running inside a valid Postgres backend process.
+    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
+        anyhow::bail!("Subscriptions are not allowed in replica mode");
+    }
+
+    ensure_service_name(&endpoint)?;
+
+    let fn_name = resolve_responder_name(fn_oid)?
+        .ok_or_else(|| anyhow::anyhow!("Failed to get function name"))?;
+
+    crate::bgw::launcher::send_message_to_launcher_with_retry(
+        &crate::bgw::LAUNCHER_MESSAGE_BUS,
+        crate::bgw::launcher::message::LauncherMessage::ServiceEndpointAdd {
+            // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
+            // before extension code is executed. Postgres backends are single-threaded,
+            // and this variable is immutable after initialization.
+            db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+            subject: subject.unwrap_or_else(|| endpoint.clone()),
+            service,
+            endpoint,
+            fn_name,
+        },
+        5,
+        std::time::Duration::from_secs(1),
+    )
+}
+
+/// Removes an endpoint from a NATS micro service.
+///
+/// # Arguments
+/// * `service` - The name of the service
+/// * `endpoint` - The name of the endpoint
+///
+/// # Returns
+/// * `Ok(())` - If the request was successfully sent
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_service_remove_endpoint('users', 'get');
+/// ```
+#[pg_extern]
+#[cfg(feature = "sub")]
+pub fn nats_service_remove_endpoint(service: String, endpoint: String) -> anyhow::Result<()> {
+    // SAFETY: Calling Postgres backend function which takes no arguments,
+    // has no side effects, and does not rely on any Rust-managed memory.
+    // Safe as long as we are running inside a valid Postgres backend process.
+    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
+        anyhow::bail!("Subscriptions are not allowed in replica mode");
+    }
+
+    crate::bgw::launcher::send_message_to_launcher_with_retry(
+        &crate::bgw::LAUNCHER_MESSAGE_BUS,
+        crate::bgw::launcher::message::LauncherMessage::ServiceEndpointRemove {
+            // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
+            // before extension code is executed. Postgres backends are single-threaded,
+            // and this variable is immutable after initialization.
+            db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+            service,
+            endpoint,
+        },
+        5,
+        std::time::Duration::from_secs(1),
+    )
+}
+
+#[cfg(feature = "sub")]
+fn ensure_service_name(name: &str) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+        "Name '{name}' may only contain letters, digits, '-' and '_'"
+    );
+
+    Ok(())
+}
+
+/// Watches a NATS KV bucket and associates it with a PostgreSQL callback function.
+///
+/// The callback is invoked for the latest revision of every matching key when the watch
+/// starts, and then for every subsequent put, delete or purge. The bucket must already
+/// exist. The watch is persisted in `pgnats.kv_watches` together with the last revision
+/// processed by the callback, and resumes after it when the background worker restarts.
+///
+/// # Arguments
+/// * `bucket` - The name of the KV bucket to watch
+/// * `key_pattern` - Key or wildcard pattern to watch (e.g., "service.>" or ">" for all keys)
+/// * `fn_oid` - The OID of the PostgreSQL function to invoke on every change
+///
+/// # Returns
+/// * `Ok(())` - If the watch request was successfully sent
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_kv_watch('config', 'service.>', 'schema.apply_config_change'::regproc);
+/// ```
+///
+/// # Warning
+/// The specified PostgreSQL function **must accept `(key text, value bytea, revision bigint,
+/// operation text)`**, where `operation` is one of `PUT`, `DEL` or `PURGE`.
+#[pg_extern]
+#[cfg(all(feature = "kv", feature = "sub"))]
+pub fn nats_kv_watch(
+    bucket: String,
+    key_pattern: String,
+    fn_oid: pg_sys::Oid,
+) -> anyhow::Result<()> {
+    // SAFETY: Calling Postgres backend function which takes no arguments,
+    // has no side effects, and does not rely on any Rust-managed memory.
+    // Safe as long as we are running inside a valid Postgres backend process.
+    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
+        anyhow::bail!("KV watches are not allowed in replica mode");
+    }
+
+    let fn_name = resolve_function_name(
+        fn_oid,
+        &[
+            pg_sys::TEXTOID,
+            pg_sys::BYTEAOID,
+            pg_sys::INT8OID,
+            pg_sys::TEXTOID,
+        ],
+    )?
+    .ok_or_else(|| anyhow::anyhow!("Failed to get function name"))?;
+
+    crate::bgw::launcher::send_message_to_launcher_with_retry(
+        &crate::bgw::LAUNCHER_MESSAGE_BUS,
+        crate::bgw::launcher::message::LauncherMessage::KvWatch {
+            // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
+            // before extension code is executed. Postgres backends are single-threaded,
+            // and this variable is immutable after initialization.
+            db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+            bucket,
+            key_pattern,
+            fn_name,
+        },
+        5,
+        std::time::Duration::from_secs(1),
+    )
+}
+
+/// Stops watching a NATS KV bucket with the specified PostgreSQL callback function.
+///
+/// Only the specified callback function will be removed from the watch. Other callbacks
+/// watching the same bucket and key pattern will remain active.
+///
+/// # Arguments
+/// * `bucket` - The name of the watched KV bucket
+/// * `key_pattern` - The key pattern used when the watch was created
+/// * `fn_oid` - The OID of the previously registered PostgreSQL function
+///
+/// # Returns
+/// * `Ok(())` - If the unwatch request was successfully sent
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_kv_unwatch('config', 'service.>', 'schema.apply_config_change'::regproc);
+/// ```
+#[pg_extern]
+#[cfg(all(feature = "kv", feature = "sub"))]
+pub fn nats_kv_unwatch(
+    bucket: String,
+    key_pattern: String,
+    fn_oid: pg_sys::Oid,
+) -> anyhow::Result<()> {
+    // SAFETY: Calling Postgres backend function which takes no arguments,
+    // has no side effects, and does not rely on any Rust-managed memory.
+    // Safe as long as we are running inside a valid Postgres backend process.
+    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
+        anyhow::bail!("KV watches are not allowed in replica mode");
+    }
+
+    let fn_name = resolve_function_name(
+        fn_oid,
+        &[
+            pg_sys::TEXTOID,
+            pg_sys::BYTEAOID,
+            pg_sys::INT8OID,
+            pg_sys::TEXTOID,
+        ],
+    )?
+    .ok_or_else(|| anyhow::anyhow!("Failed to get function name"))?;
+
+    crate::bgw::launcher::send_message_to_launcher_with_retry(
+        &crate::bgw::LAUNCHER_MESSAGE_BUS,
+        crate::bgw::launcher::message::LauncherMessage::KvUnwatch {
+            // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
+            // before extension code is executed. Postgres backends are single-threaded,
+            // and this variable is immutable after initialization.
+            db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+            bucket,
+            key_pattern,
+            fn_name,
+        },
+        5,
+        std::time::Duration::from_secs(1),
+    )
+}
+
+/// Watches a NATS object store and associates it with a PostgreSQL callback function.
+///
+/// The callback is invoked for the latest state of every object when the watch starts,
+/// and then whenever an object is added, replaced or deleted. The store must already
+/// exist. The watch is persisted in `pgnats.object_watches` together with the publish time
+/// of the last change processed by the callback, and resumes after it when the background
+/// worker restarts.
+///
+/// # Arguments
+/// * `store` - The name of the object store to watch
+/// * `fn_oid` - The OID of the PostgreSQL function to invoke on every change
+///
+/// # Returns
+/// * `Ok(())` - If the watch request was successfully sent
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_object_watch('uploads', 'schema.ingest_upload'::regproc);
+/// ```
+///
+/// # Warning
+/// The specified PostgreSQL function **must accept `(name text, nuid text, size bigint,
+/// operation text)`**, where `operation` is either `PUT` or `DEL`.
+#[pg_extern]
+#[cfg(all(feature = "object_store", feature = "sub"))]
+pub fn nats_object_watch(store: String, fn_oid: pg_sys::Oid) -> anyhow::Result<()> {
+    // SAFETY: Calling Postgres backend function which takes no arguments,
+    // has no side effects, and does not rely on any Rust-managed memory.
+    // Safe as long as we are running inside a valid Postgres backend process.
+    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
+        anyhow::bail!("Object watches are not allowed in replica mode");
+    }
+
+    let fn_name = resolve_function_name(
+        fn_oid,
+        &[
+            pg_sys::TEXTOID,
+            pg_sys::TEXTOID,
+            pg_sys::INT8OID,
+            pg_sys::TEXTOID,
+        ],
+    )?
+    .ok_or_else(|| anyhow::anyhow!("Failed to get function name"))?;
+
+    crate::bgw::launcher::send_message_to_launcher_with_retry(
+        &crate::bgw::LAUNCHER_MESSAGE_BUS,
+        crate::bgw::launcher::message::LauncherMessage::ObjectWatch {
+            // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
+            // before extension code is executed. Postgres backends are single-threaded,
+            // and this variable is immutable after initialization.
+            db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+            store,
+            fn_name,
+        },
+        5,
+        std::time::Duration::from_secs(1),
+    )
+}
+
+/// Stops watching a NATS object store with the specified PostgreSQL callback function.
+///
+/// Only the specified callback function will be removed from the watch. Other callbacks
+/// watching the same store will remain active.
+///
+/// # Arguments
+/// * `store` - The name of the watched object store
+/// * `fn_oid` - The OID of the previously registered PostgreSQL function
+///
+/// # Returns
+/// * `Ok(())` - If the unwatch request was successfully sent
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_object_unwatch('uploads', 'schema.ingest_upload'::regproc);
+/// ```
+#[pg_extern]
+#[cfg(all(feature = "object_store", feature = "sub"))]
+pub fn nats_object_unwatch(store: String, fn_oid: pg_sys::Oid) -> anyhow::Result<()> {
+    // SAFETY: Calling Postgres backend function which takes no arguments,
+    // has no side effects, and does not rely on any Rust-managed memory.
+    // Safe as long as we are running inside a valid Postgres backend process.
+    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
+        anyhow::bail!("Object watches are not allowed in replica mode");
+    }
+
+    let fn_name = resolve_function_name(
+        fn_oid,
+        &[
+            pg_sys::TEXTOID,
+            pg_sys::TEXTOID,
+            pg_sys::INT8OID,
+            pg_sys::TEXTOID,
+        ],
+    )?
+    .ok_or_else(|| anyhow::anyhow!("Failed to get function name"))?;
+
+    crate::bgw::launcher::send_message_to_launcher_with_retry(
+        &crate::bgw::LAUNCHER_MESSAGE_BUS,
+        crate::bgw::launcher::message::LauncherMessage::ObjectUnwatch {
+            // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
+            // before extension code is executed. Postgres backends are single-threaded,
+            // and this variable is immutable after initialization.
+            db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+            store,
             fn_name,
         },
         5,
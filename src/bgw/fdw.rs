@@ -6,17 +6,18 @@ use crate::{
         ring_queue::RingQueue,
         LAUNCHER_MESSAGE_BUS,
     },
-    config::parse_config,
+    config::{parse_config, validate_server_options},
     error,
+    fdw::{pgnats_fdw_handler, validate_table_options},
 };
 
 extension_sql!(
     r#"
-    CREATE FOREIGN DATA WRAPPER pgnats_fdw VALIDATOR pgnats_fdw_validator;
+    CREATE FOREIGN DATA WRAPPER pgnats_fdw HANDLER pgnats_fdw_handler VALIDATOR pgnats_fdw_validator;
     -- CREATE SERVER nats_fdw_server FOREIGN DATA WRAPPER pgnats_fdw OPTIONS (host 'localhost', port '4222');
     "#,
     name = "create_fdw",
-    requires = [pgnats_fdw_validator]
+    requires = [pgnats_fdw_validator, pgnats_fdw_handler]
 );
 
 extension_sql!(
@@ -65,6 +66,10 @@ pub fn fdw_validator<const N: usize>(
             .map(|(k, v)| (k.into(), v.into()))
             .collect();
 
+        if let Err(err) = validate_server_options(&options) {
+            error!("{err}");
+        }
+
         let config = parse_config(&options);
 
         if let Err(err) = send_message_to_launcher_with_retry(
@@ -82,4 +87,10 @@ pub fn fdw_validator<const N: usize>(
             error!("{err}");
         }
     }
+
+    if oid == sys::ForeignTableRelationId {
+        if let Err(err) = validate_table_options(&options) {
+            error!("{err}");
+        }
+    }
 }
@@ -5,31 +5,37 @@ use pgrx::pg_sys as sys;
 use crate::{
     bgw::{
         launcher::worker_entry::{RunningState, TerminatedState, WorkerEntry},
+        partition::WorkerPartition,
         pgrx_wrappers::shm_mq::ShmMqSender,
-        subscriber::message::SubscriberMessage,
+        subscriber::{
+            message::SubscriberMessage,
+            pg_api::{BatchPolicy, RetryPolicy},
+        },
         DSM_SIZE,
     },
     config::Config,
+    constants::DEFAULT_SUBSCRIBER_WORKERS,
 };
 
 #[derive(Default)]
 pub struct LauncherContext {
-    pending_workers: HashMap<u32, WorkerEntry<RunningState>>,
-    workers: HashMap<u32, WorkerEntry<RunningState>>,
-    terminated_workers: HashMap<u32, WorkerEntry<TerminatedState>>,
+    pending_workers: HashMap<usize, WorkerEntry<RunningState>>,
+    workers: HashMap<usize, WorkerEntry<RunningState>>,
+    terminated_workers: Vec<WorkerEntry<TerminatedState>>,
+    pool_sizes: HashMap<u32, usize>,
     counter: usize,
 }
 
 impl LauncherContext {
     pub fn process_terminated_workers(&mut self) {
-        for (_, v) in self.terminated_workers.drain() {
+        for v in self.terminated_workers.drain(..) {
             let _ = v.wait_for_shutdown(); // ignore error
         }
     }
 
-    pub fn register_worker(&mut self, db_oid: u32) {
-        if let Some(worker) = self.pending_workers.remove(&db_oid) {
-            let _ = self.workers.insert(db_oid, worker);
+    pub fn register_worker(&mut self, worker_id: usize) {
+        if let Some(worker) = self.pending_workers.remove(&worker_id) {
+            let _ = self.workers.insert(worker_id, worker);
         }
     }
 
@@ -39,17 +45,39 @@ impl LauncherContext {
         config: Config,
         entry_point: &str,
     ) -> anyhow::Result<Option<String>> {
-        if let Some(entry) = self.workers.get_mut(&db_oid) {
-            send_subscriber_message(&mut entry.sender, SubscriberMessage::NewConfig { config })?;
+        let workers = config.workers;
 
-            Ok(None)
-        } else if !self.pending_workers.contains_key(&db_oid) {
-            let db_name = self.start_subscribe_worker(db_oid, entry_point)?;
-
-            Ok(Some(db_name))
+        if self.pool_size(db_oid) != workers {
+            self.shutdown_worker(db_oid);
         } else {
-            Ok(None)
+            self.broadcast_subscriber_message(db_oid, &SubscriberMessage::NewConfig { config })?;
+        }
+
+        let _ = self.pool_sizes.insert(db_oid, workers);
+
+        self.start_missing_workers(db_oid, entry_point)
+    }
+
+    /// Handles the pool size reported by the first worker of a database whose pool size
+    /// was not known to the launcher yet.
+    pub fn handle_pool_size_message(
+        &mut self,
+        db_oid: u32,
+        workers: usize,
+        entry_point: &str,
+    ) -> anyhow::Result<Option<String>> {
+        if self.pool_sizes.contains_key(&db_oid) {
+            return Ok(None);
         }
+
+        let _ = self.pool_sizes.insert(db_oid, workers);
+
+        if workers == DEFAULT_SUBSCRIBER_WORKERS {
+            return Ok(None);
+        }
+
+        self.shutdown_worker(db_oid);
+        self.start_missing_workers(db_oid, entry_point)
     }
 
     pub fn handle_subscribe_message(
@@ -57,15 +85,23 @@ impl LauncherContext {
         db_oid: u32,
         subject: String,
         fn_name: String,
+        queue_group: Option<String>,
+        retry: RetryPolicy,
+        batch: BatchPolicy,
     ) -> anyhow::Result<()> {
-        if let Some(entry) = self.workers.get_mut(&db_oid) {
-            send_subscriber_message(
-                &mut entry.sender,
-                SubscriberMessage::Subscribe { subject, fn_name },
-            )?;
-        }
+        let partition = self.partition_of(db_oid, &subject);
 
-        Ok(())
+        self.send_to_partition(
+            db_oid,
+            partition,
+            SubscriberMessage::Subscribe {
+                subject,
+                fn_name,
+                queue_group,
+                retry,
+                batch,
+            },
+        )
     }
 
     pub fn handle_unsubscribe_message(
@@ -74,18 +110,230 @@ impl LauncherContext {
         subject: String,
         fn_name: String,
     ) -> anyhow::Result<()> {
-        if let Some(entry) = self.workers.get_mut(&db_oid) {
-            send_subscriber_message(
-                &mut entry.sender,
-                SubscriberMessage::Unsubscribe { subject, fn_name },
-            )?;
-        }
+        let partition = self.partition_of(db_oid, &subject);
 
-        Ok(())
+        self.send_to_partition(
+            db_oid,
+            partition,
+            SubscriberMessage::Unsubscribe { subject, fn_name },
+        )
     }
 
-    pub fn handle_subscriber_exit_message(&mut self, db_oid: u32) {
-        self.shutdown_worker(db_oid);
+    pub fn handle_kv_watch_message(
+        &mut self,
+        db_oid: u32,
+        bucket: String,
+        key_pattern: String,
+        fn_name: String,
+    ) -> anyhow::Result<()> {
+        let partition = self.partition_of(db_oid, &bucket);
+
+        self.send_to_partition(
+            db_oid,
+            partition,
+            SubscriberMessage::KvWatch {
+                bucket,
+                key_pattern,
+                fn_name,
+            },
+        )
+    }
+
+    pub fn handle_kv_unwatch_message(
+        &mut self,
+        db_oid: u32,
+        bucket: String,
+        key_pattern: String,
+        fn_name: String,
+    ) -> anyhow::Result<()> {
+        let partition = self.partition_of(db_oid, &bucket);
+
+        self.send_to_partition(
+            db_oid,
+            partition,
+            SubscriberMessage::KvUnwatch {
+                bucket,
+                key_pattern,
+                fn_name,
+            },
+        )
+    }
+
+    pub fn handle_object_watch_message(
+        &mut self,
+        db_oid: u32,
+        store: String,
+        fn_name: String,
+    ) -> anyhow::Result<()> {
+        let partition = self.partition_of(db_oid, &store);
+
+        self.send_to_partition(
+            db_oid,
+            partition,
+            SubscriberMessage::ObjectWatch { store, fn_name },
+        )
+    }
+
+    pub fn handle_object_unwatch_message(
+        &mut self,
+        db_oid: u32,
+        store: String,
+        fn_name: String,
+    ) -> anyhow::Result<()> {
+        let partition = self.partition_of(db_oid, &store);
+
+        self.send_to_partition(
+            db_oid,
+            partition,
+            SubscriberMessage::ObjectUnwatch { store, fn_name },
+        )
+    }
+
+    pub fn handle_stream_subscribe_message(
+        &mut self,
+        db_oid: u32,
+        stream: String,
+        consumer: String,
+        fn_name: String,
+        retry: RetryPolicy,
+    ) -> anyhow::Result<()> {
+        let partition = self.partition_of(db_oid, &stream);
+
+        self.send_to_partition(
+            db_oid,
+            partition,
+            SubscriberMessage::StreamSubscribe {
+                stream,
+                consumer,
+                fn_name,
+                retry,
+            },
+        )
+    }
+
+    pub fn handle_stream_unsubscribe_message(
+        &mut self,
+        db_oid: u32,
+        stream: String,
+        consumer: String,
+        fn_name: String,
+    ) -> anyhow::Result<()> {
+        let partition = self.partition_of(db_oid, &stream);
+
+        self.send_to_partition(
+            db_oid,
+            partition,
+            SubscriberMessage::StreamUnsubscribe {
+                stream,
+                consumer,
+                fn_name,
+            },
+        )
+    }
+
+    pub fn handle_respond_message(
+        &mut self,
+        db_oid: u32,
+        subject: String,
+        fn_name: String,
+        queue_group: Option<String>,
+    ) -> anyhow::Result<()> {
+        let partition = self.partition_of(db_oid, &subject);
+
+        self.send_to_partition(
+            db_oid,
+            partition,
+            SubscriberMessage::Respond {
+                subject,
+                fn_name,
+                queue_group,
+            },
+        )
+    }
+
+    pub fn handle_unrespond_message(&mut self, db_oid: u32, subject: String) -> anyhow::Result<()> {
+        let partition = self.partition_of(db_oid, &subject);
+
+        self.send_to_partition(db_oid, partition, SubscriberMessage::Unrespond { subject })
+    }
+
+    pub fn handle_service_add_message(
+        &mut self,
+        db_oid: u32,
+        name: String,
+        version: String,
+        description: Option<String>,
+    ) -> anyhow::Result<()> {
+        let partition = self.partition_of(db_oid, &name);
+
+        self.send_to_partition(
+            db_oid,
+            partition,
+            SubscriberMessage::ServiceAdd {
+                name,
+                version,
+                description,
+            },
+        )
+    }
+
+    pub fn handle_service_remove_message(
+        &mut self,
+        db_oid: u32,
+        name: String,
+    ) -> anyhow::Result<()> {
+        let partition = self.partition_of(db_oid, &name);
+
+        self.send_to_partition(db_oid, partition, SubscriberMessage::ServiceRemove { name })
+    }
+
+    pub fn handle_service_endpoint_add_message(
+        &mut self,
+        db_oid: u32,
+        service: String,
+        endpoint: String,
+        subject: String,
+        fn_name: String,
+    ) -> anyhow::Result<()> {
+        let partition = self.partition_of(db_oid, &service);
+
+        self.send_to_partition(
+            db_oid,
+            partition,
+            SubscriberMessage::ServiceEndpointAdd {
+                service,
+                endpoint,
+                subject,
+                fn_name,
+            },
+        )
+    }
+
+    pub fn handle_service_endpoint_remove_message(
+        &mut self,
+        db_oid: u32,
+        service: String,
+        endpoint: String,
+    ) -> anyhow::Result<()> {
+        let partition = self.partition_of(db_oid, &service);
+
+        self.send_to_partition(
+            db_oid,
+            partition,
+            SubscriberMessage::ServiceEndpointRemove { service, endpoint },
+        )
+    }
+
+    pub fn handle_subscriber_exit_message(&mut self, worker_id: usize) {
+        let Some(entry) = self
+            .workers
+            .remove(&worker_id)
+            .or_else(|| self.pending_workers.remove(&worker_id))
+        else {
+            return;
+        };
+
+        self.shutdown_worker_entry(entry);
     }
 
     pub fn handle_foreign_server_dropped(&mut self, db_oid: u32) {
@@ -94,45 +342,83 @@ impl LauncherContext {
 
     #[cfg(any(test, feature = "pg_test"))]
     pub fn handle_change_status(&mut self, db_oid: u32, is_master: bool) -> anyhow::Result<()> {
-        if let Some(entry) = self.workers.get_mut(&db_oid) {
-            send_subscriber_message(
-                &mut entry.sender,
-                SubscriberMessage::ChangeStatus { is_master },
-            )?;
+        self.broadcast_subscriber_message(db_oid, &SubscriberMessage::ChangeStatus { is_master })
+    }
+
+    /// Starts the workers of the pool of `db_oid` which are neither pending nor running.
+    /// While the pool size is unknown only the first worker is started, and it reports
+    /// the configured size back to the launcher.
+    ///
+    /// Returns the database name if at least one worker was started.
+    pub fn start_missing_workers(
+        &mut self,
+        db_oid: u32,
+        entry_point: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let count = self.pool_sizes.get(&db_oid).copied().unwrap_or_default();
+        let mut db_name = None;
+
+        for index in 0..count.max(1) {
+            let started = self
+                .workers
+                .values()
+                .chain(self.pending_workers.values())
+                .any(|entry| entry.oid.to_u32() == db_oid && entry.partition.index == index);
+
+            if !started {
+                db_name = Some(self.start_subscribe_worker(
+                    db_oid,
+                    WorkerPartition { index, count },
+                    entry_point,
+                )?);
+            }
         }
 
-        Ok(())
+        Ok(db_name)
     }
 
     pub fn start_subscribe_worker(
         &mut self,
         oid: u32,
+        partition: WorkerPartition,
         entry_point: &str,
     ) -> anyhow::Result<String> {
+        let worker_id = self.counter;
         let entry = WorkerEntry::start(
             sys::Oid::from_u32(oid),
-            &format!("PGNats Background Worker Subscriber {}", self.counter),
-            &format!("pgnats_bgw_subscriber_{}", self.counter),
+            worker_id,
+            partition,
+            &format!("PGNats Background Worker Subscriber {worker_id}"),
+            &format!("pgnats_bgw_subscriber_{worker_id}"),
             entry_point,
             DSM_SIZE,
         )?;
         self.counter += 1;
         let db_name = entry.db_name.clone();
-        let _ = self.pending_workers.insert(oid, entry);
+        let _ = self.pending_workers.insert(worker_id, entry);
 
         Ok(db_name)
     }
 
+    /// Shuts down every worker of the pool of `db_oid`.
     pub fn shutdown_worker(&mut self, db_oid: u32) {
-        let Some(entry) = self
+        let ids: Vec<usize> = self
             .workers
-            .remove(&db_oid)
-            .or_else(|| self.pending_workers.remove(&db_oid))
-        else {
-            return;
-        };
+            .iter()
+            .chain(self.pending_workers.iter())
+            .filter(|(_, entry)| entry.oid.to_u32() == db_oid)
+            .map(|(id, _)| *id)
+            .collect();
 
-        self.shutdown_worker_entry(entry);
+        for id in ids {
+            if let Some(entry) = self
+                .workers
+                .remove(&id)
+                .or_else(|| self.pending_workers.remove(&id))
+            {
+                self.shutdown_worker_entry(entry);
+            }
+        }
     }
 
     pub fn shutdown_all_workers(&mut self) {
@@ -146,16 +432,64 @@ impl LauncherContext {
     }
 
     pub fn shutdown_worker_entry(&mut self, entry: WorkerEntry<RunningState>) {
-        let entry = entry.terminate();
-        let _ = self.terminated_workers.insert(entry.oid.to_u32(), entry);
+        self.terminated_workers.push(entry.terminate());
+    }
+
+    pub fn get_worker(&self, db_oid: u32, partition: usize) -> Option<&WorkerEntry<RunningState>> {
+        self.workers
+            .values()
+            .find(|entry| entry.oid.to_u32() == db_oid && entry.partition.index == partition)
+    }
+
+    fn pool_size(&self, db_oid: u32) -> usize {
+        self.pool_sizes
+            .get(&db_oid)
+            .copied()
+            .unwrap_or(DEFAULT_SUBSCRIBER_WORKERS)
     }
 
-    pub fn get_worker(&self, db_oid: u32) -> Option<&WorkerEntry<RunningState>> {
-        self.workers.get(&db_oid)
+    fn partition_of(&self, db_oid: u32, key: &str) -> usize {
+        WorkerPartition::of(key, self.pool_size(db_oid))
+    }
+
+    fn send_to_partition(
+        &mut self,
+        db_oid: u32,
+        partition: usize,
+        msg: SubscriberMessage,
+    ) -> anyhow::Result<()> {
+        if let Some(entry) = self
+            .workers
+            .values_mut()
+            .find(|entry| entry.oid.to_u32() == db_oid && entry.partition.index == partition)
+        {
+            send_subscriber_message(&mut entry.sender, &msg)?;
+        }
+
+        Ok(())
+    }
+
+    fn broadcast_subscriber_message(
+        &mut self,
+        db_oid: u32,
+        msg: &SubscriberMessage,
+    ) -> anyhow::Result<()> {
+        for entry in self
+            .workers
+            .values_mut()
+            .filter(|entry| entry.oid.to_u32() == db_oid)
+        {
+            send_subscriber_message(&mut entry.sender, msg)?;
+        }
+
+        Ok(())
     }
 }
 
-fn send_subscriber_message(sender: &mut ShmMqSender, msg: SubscriberMessage) -> anyhow::Result<()> {
-    let data = postcard::to_stdvec(&msg)?;
+fn send_subscriber_message(
+    sender: &mut ShmMqSender,
+    msg: &SubscriberMessage,
+) -> anyhow::Result<()> {
+    let data = postcard::to_stdvec(msg)?;
     sender.send(&data)
 }
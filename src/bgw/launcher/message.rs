@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 
-use crate::config::Config;
+use crate::{
+    bgw::subscriber::pg_api::{BatchPolicy, RetryPolicy},
+    config::Config,
+};
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum ExtensionStatus {
@@ -13,24 +16,100 @@ pub enum ExtensionStatus {
 pub enum LauncherMessage {
     DbExtensionStatus {
         db_oid: u32,
+        worker_id: usize,
         status: ExtensionStatus,
     },
     NewConfig {
         db_oid: u32,
         config: Config,
     },
+    PoolSize {
+        db_oid: u32,
+        workers: usize,
+    },
     Subscribe {
         db_oid: u32,
         subject: String,
         fn_name: String,
+        queue_group: Option<String>,
+        retry: RetryPolicy,
+        batch: BatchPolicy,
     },
     Unsubscribe {
         db_oid: u32,
         subject: String,
         fn_name: String,
     },
+    KvWatch {
+        db_oid: u32,
+        bucket: String,
+        key_pattern: String,
+        fn_name: String,
+    },
+    KvUnwatch {
+        db_oid: u32,
+        bucket: String,
+        key_pattern: String,
+        fn_name: String,
+    },
+    ObjectWatch {
+        db_oid: u32,
+        store: String,
+        fn_name: String,
+    },
+    ObjectUnwatch {
+        db_oid: u32,
+        store: String,
+        fn_name: String,
+    },
+    StreamSubscribe {
+        db_oid: u32,
+        stream: String,
+        consumer: String,
+        fn_name: String,
+        retry: RetryPolicy,
+    },
+    StreamUnsubscribe {
+        db_oid: u32,
+        stream: String,
+        consumer: String,
+        fn_name: String,
+    },
+    Respond {
+        db_oid: u32,
+        subject: String,
+        fn_name: String,
+        queue_group: Option<String>,
+    },
+    Unrespond {
+        db_oid: u32,
+        subject: String,
+    },
+    ServiceAdd {
+        db_oid: u32,
+        name: String,
+        version: String,
+        description: Option<String>,
+    },
+    ServiceRemove {
+        db_oid: u32,
+        name: String,
+    },
+    ServiceEndpointAdd {
+        db_oid: u32,
+        service: String,
+        endpoint: String,
+        subject: String,
+        fn_name: String,
+    },
+    ServiceEndpointRemove {
+        db_oid: u32,
+        service: String,
+        endpoint: String,
+    },
     SubscriberExit {
         db_oid: u32,
+        worker_id: usize,
         reason: Result<(), String>,
     },
     ForeignServerDropped {
@@ -95,7 +95,11 @@ pub fn process_launcher_bus<const N: usize>(
         );
 
         match msg {
-            LauncherMessage::DbExtensionStatus { db_oid, status } => match status {
+            LauncherMessage::DbExtensionStatus {
+                db_oid,
+                worker_id,
+                status,
+            } => match status {
                 ExtensionStatus::Exist => {
                     log!(
                         context = LAUNCHER_CTX,
@@ -104,7 +108,7 @@ pub fn process_launcher_bus<const N: usize>(
                         db_oid
                     );
 
-                    ctx.register_worker(db_oid);
+                    ctx.register_worker(worker_id);
                 }
                 ExtensionStatus::NoExtension => {
                     log!(
@@ -148,12 +152,48 @@ pub fn process_launcher_bus<const N: usize>(
                     }
                 }
             }
+            LauncherMessage::PoolSize { db_oid, workers } => {
+                match ctx.handle_pool_size_message(db_oid, workers, entry_point) {
+                    Ok(Some(db_name)) => {
+                        log!(
+                            context = LAUNCHER_CTX,
+                            "Restarting {} background worker subscribers for '{}'",
+                            workers,
+                            db_name
+                        );
+                    }
+                    Ok(None) => {
+                        debug!(
+                            context = LAUNCHER_CTX,
+                            "Worker pool of database (OID: {}) already has {} workers",
+                            db_oid,
+                            workers
+                        );
+                    }
+                    Err(err) => {
+                        warn!(
+                            context = LAUNCHER_CTX,
+                            "Failed to resize worker pool for db_oid {}: {}", db_oid, err
+                        );
+                    }
+                }
+            }
             LauncherMessage::Subscribe {
                 db_oid,
                 subject,
                 fn_name,
+                queue_group,
+                retry,
+                batch,
             } => {
-                if let Err(err) = ctx.handle_subscribe_message(db_oid, subject, fn_name) {
+                if let Err(err) = ctx.handle_subscribe_message(
+                    db_oid,
+                    subject,
+                    fn_name,
+                    queue_group,
+                    retry,
+                    batch,
+                ) {
                     warn!(
                         context = LAUNCHER_CTX,
                         "Failed to process subscription (db_oid: {}): {}", db_oid, err
@@ -182,7 +222,226 @@ pub fn process_launcher_bus<const N: usize>(
                     );
                 }
             }
-            LauncherMessage::SubscriberExit { db_oid, reason } => {
+            LauncherMessage::KvWatch {
+                db_oid,
+                bucket,
+                key_pattern,
+                fn_name,
+            } => {
+                if let Err(err) = ctx.handle_kv_watch_message(db_oid, bucket, key_pattern, fn_name)
+                {
+                    warn!(
+                        context = LAUNCHER_CTX,
+                        "Failed to process KV watch (db_oid: {}): {}", db_oid, err
+                    );
+                } else {
+                    debug!(
+                        context = LAUNCHER_CTX,
+                        "Registered KV watch: db_oid={}", db_oid
+                    );
+                }
+            }
+            LauncherMessage::KvUnwatch {
+                db_oid,
+                bucket,
+                key_pattern,
+                fn_name,
+            } => {
+                if let Err(err) =
+                    ctx.handle_kv_unwatch_message(db_oid, bucket, key_pattern, fn_name)
+                {
+                    warn!(
+                        context = LAUNCHER_CTX,
+                        "Failed to process KV unwatch (db_oid: {}): {}", db_oid, err
+                    );
+                } else {
+                    debug!(
+                        context = LAUNCHER_CTX,
+                        "Removed KV watch: db_oid={}", db_oid
+                    );
+                }
+            }
+            LauncherMessage::ObjectWatch {
+                db_oid,
+                store,
+                fn_name,
+            } => {
+                if let Err(err) = ctx.handle_object_watch_message(db_oid, store, fn_name) {
+                    warn!(
+                        context = LAUNCHER_CTX,
+                        "Failed to process object watch (db_oid: {}): {}", db_oid, err
+                    );
+                } else {
+                    debug!(
+                        context = LAUNCHER_CTX,
+                        "Registered object watch: db_oid={}", db_oid
+                    );
+                }
+            }
+            LauncherMessage::ObjectUnwatch {
+                db_oid,
+                store,
+                fn_name,
+            } => {
+                if let Err(err) = ctx.handle_object_unwatch_message(db_oid, store, fn_name) {
+                    warn!(
+                        context = LAUNCHER_CTX,
+                        "Failed to process object unwatch (db_oid: {}): {}", db_oid, err
+                    );
+                } else {
+                    debug!(
+                        context = LAUNCHER_CTX,
+                        "Removed object watch: db_oid={}", db_oid
+                    );
+                }
+            }
+            LauncherMessage::StreamSubscribe {
+                db_oid,
+                stream,
+                consumer,
+                fn_name,
+                retry,
+            } => {
+                if let Err(err) =
+                    ctx.handle_stream_subscribe_message(db_oid, stream, consumer, fn_name, retry)
+                {
+                    warn!(
+                        context = LAUNCHER_CTX,
+                        "Failed to process stream subscription (db_oid: {}): {}", db_oid, err
+                    );
+                } else {
+                    debug!(
+                        context = LAUNCHER_CTX,
+                        "Registered stream subscription: db_oid={}", db_oid
+                    );
+                }
+            }
+            LauncherMessage::StreamUnsubscribe {
+                db_oid,
+                stream,
+                consumer,
+                fn_name,
+            } => {
+                if let Err(err) =
+                    ctx.handle_stream_unsubscribe_message(db_oid, stream, consumer, fn_name)
+                {
+                    warn!(
+                        context = LAUNCHER_CTX,
+                        "Failed to process stream unsubscription (db_oid: {}): {}", db_oid, err
+                    );
+                } else {
+                    debug!(
+                        context = LAUNCHER_CTX,
+                        "Removed stream subscription: db_oid={}", db_oid
+                    );
+                }
+            }
+            LauncherMessage::Respond {
+                db_oid,
+                subject,
+                fn_name,
+                queue_group,
+            } => {
+                if let Err(err) = ctx.handle_respond_message(db_oid, subject, fn_name, queue_group)
+                {
+                    warn!(
+                        context = LAUNCHER_CTX,
+                        "Failed to process responder (db_oid: {}): {}", db_oid, err
+                    );
+                } else {
+                    debug!(
+                        context = LAUNCHER_CTX,
+                        "Registered responder: db_oid={}", db_oid
+                    );
+                }
+            }
+            LauncherMessage::Unrespond { db_oid, subject } => {
+                if let Err(err) = ctx.handle_unrespond_message(db_oid, subject) {
+                    warn!(
+                        context = LAUNCHER_CTX,
+                        "Failed to process responder removal (db_oid: {}): {}", db_oid, err
+                    );
+                } else {
+                    debug!(
+                        context = LAUNCHER_CTX,
+                        "Removed responder: db_oid={}", db_oid
+                    );
+                }
+            }
+            LauncherMessage::ServiceAdd {
+                db_oid,
+                name,
+                version,
+                description,
+            } => {
+                if let Err(err) = ctx.handle_service_add_message(db_oid, name, version, description)
+                {
+                    warn!(
+                        context = LAUNCHER_CTX,
+                        "Failed to process service registration (db_oid: {}): {}", db_oid, err
+                    );
+                } else {
+                    debug!(
+                        context = LAUNCHER_CTX,
+                        "Registered service: db_oid={}", db_oid
+                    );
+                }
+            }
+            LauncherMessage::ServiceRemove { db_oid, name } => {
+                if let Err(err) = ctx.handle_service_remove_message(db_oid, name) {
+                    warn!(
+                        context = LAUNCHER_CTX,
+                        "Failed to process service removal (db_oid: {}): {}", db_oid, err
+                    );
+                } else {
+                    debug!(context = LAUNCHER_CTX, "Removed service: db_oid={}", db_oid);
+                }
+            }
+            LauncherMessage::ServiceEndpointAdd {
+                db_oid,
+                service,
+                endpoint,
+                subject,
+                fn_name,
+            } => {
+                if let Err(err) = ctx.handle_service_endpoint_add_message(
+                    db_oid, service, endpoint, subject, fn_name,
+                ) {
+                    warn!(
+                        context = LAUNCHER_CTX,
+                        "Failed to process service endpoint (db_oid: {}): {}", db_oid, err
+                    );
+                } else {
+                    debug!(
+                        context = LAUNCHER_CTX,
+                        "Registered service endpoint: db_oid={}", db_oid
+                    );
+                }
+            }
+            LauncherMessage::ServiceEndpointRemove {
+                db_oid,
+                service,
+                endpoint,
+            } => {
+                if let Err(err) =
+                    ctx.handle_service_endpoint_remove_message(db_oid, service, endpoint)
+                {
+                    warn!(
+                        context = LAUNCHER_CTX,
+                        "Failed to process service endpoint removal (db_oid: {}): {}", db_oid, err
+                    );
+                } else {
+                    debug!(
+                        context = LAUNCHER_CTX,
+                        "Removed service endpoint: db_oid={}", db_oid
+                    );
+                }
+            }
+            LauncherMessage::SubscriberExit {
+                db_oid,
+                worker_id,
+                reason,
+            } => {
                 match reason {
                     Ok(()) => {
                         debug!(
@@ -198,7 +457,7 @@ pub fn process_launcher_bus<const N: usize>(
                     }
                 }
 
-                ctx.handle_subscriber_exit_message(db_oid);
+                ctx.handle_subscriber_exit_message(worker_id);
             }
             LauncherMessage::ForeignServerDropped { db_oid } => {
                 debug!(
@@ -258,14 +517,15 @@ fn add_subscribe_workers(
     entry_point: &str,
 ) {
     for oid in oids {
-        match ctx.start_subscribe_worker(oid.to_u32(), entry_point) {
-            Ok(db_name) => {
+        match ctx.start_missing_workers(oid.to_u32(), entry_point) {
+            Ok(Some(db_name)) => {
                 log!(
                     context = LAUNCHER_CTX,
                     "Trying to start background worker subscriber for '{}'",
                     db_name
                 );
             }
+            Ok(None) => {}
             Err(err) => {
                 warn!(
                     context = LAUNCHER_CTX,
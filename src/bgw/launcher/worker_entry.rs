@@ -7,7 +7,10 @@ use pgrx::{
 };
 
 use crate::{
-    bgw::pgrx_wrappers::{dsm::DynamicSharedMemory, shm_mq::ShmMqSender},
+    bgw::{
+        partition::WorkerPartition,
+        pgrx_wrappers::{dsm::DynamicSharedMemory, shm_mq::ShmMqSender},
+    },
     constants::EXTENSION_NAME,
     utils::{get_database_name, pack_oid_dsmh_to_i64},
 };
@@ -21,6 +24,7 @@ pub struct WorkerEntry<S> {
     pub db_name: String,
     pub sender: ShmMqSender,
     pub oid: sys::Oid,
+    pub partition: WorkerPartition,
     state: S,
     _dsm: DynamicSharedMemory,
 }
@@ -28,6 +32,8 @@ pub struct WorkerEntry<S> {
 impl WorkerEntry<RunningState> {
     pub fn start(
         oid: sys::Oid,
+        worker_id: usize,
+        partition: WorkerPartition,
         name: &str,
         ty: &str,
         entrypoint: &str,
@@ -65,6 +71,7 @@ impl WorkerEntry<RunningState> {
             .set_library(EXTENSION_NAME)
             .set_function(entrypoint)
             .set_argument(packed_arg.into_datum())
+            .set_extra(&partition.to_extra(worker_id))
             .set_start_time(BgWorkerStartTime::ConsistentState)
             .set_notify_pid(unsafe { sys::MyProcPid })
             .load_dynamic()
@@ -78,6 +85,7 @@ impl WorkerEntry<RunningState> {
         Ok(Self {
             db_name,
             oid,
+            partition,
             state: RunningState(worker),
             sender,
             _dsm: dsm,
@@ -91,6 +99,7 @@ impl WorkerEntry<RunningState> {
             db_name: self.db_name,
             sender: self.sender,
             oid: self.oid,
+            partition: self.partition,
             state: TerminatedState(terminate),
 
             _dsm: self._dsm,
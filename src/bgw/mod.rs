@@ -5,22 +5,37 @@ use pgrx::{
     prelude::*,
 };
 
-use crate::{bgw::ring_queue::RingQueue, constants::EXTENSION_NAME};
+use crate::{
+    bgw::{ring_queue::RingQueue, status::StatusBoard},
+    constants::EXTENSION_NAME,
+};
 
 pub mod fdw;
 pub mod launcher;
 pub mod notification;
+pub mod partition;
 pub mod pgrx_wrappers;
 pub mod ring_queue;
+pub mod status;
 pub mod subscriber;
 
 pub const SUBSCRIPTIONS_TABLE_NAME: &str = "pgnats.subscriptions";
+pub const KV_WATCHES_TABLE_NAME: &str = "pgnats.kv_watches";
+pub const OBJECT_WATCHES_TABLE_NAME: &str = "pgnats.object_watches";
+pub const STREAM_SUBSCRIPTIONS_TABLE_NAME: &str = "pgnats.stream_subscriptions";
+pub const RESPONDERS_TABLE_NAME: &str = "pgnats.responders";
+pub const SERVICES_TABLE_NAME: &str = "pgnats.services";
+pub const SERVICE_ENDPOINTS_TABLE_NAME: &str = "pgnats.service_endpoints";
+pub const DEAD_LETTERS_TABLE_NAME: &str = "pgnats.dead_letters";
 pub const LAUNCHER_ENTRY_POINT: &str = "background_worker_launcher_entry_point";
 pub const SUBSCRIBER_ENTRY_POINT: &str = "background_worker_subscriber_entry_point";
 
 pub const MESSAGE_BUS_SIZE: usize = 0x10000;
 pub const DSM_SIZE: usize = MESSAGE_BUS_SIZE >> 3;
 
+pub const STATUS_SLOTS: usize = 1024;
+pub const STATUS_BOARD_SIZE: usize = 0x200000;
+
 extension_sql!(
     r#"
     CREATE SCHEMA IF NOT EXISTS pgnats;
@@ -28,12 +43,112 @@ extension_sql!(
     CREATE TABLE IF NOT EXISTS pgnats.subscriptions (
         subject TEXT NOT NULL,
         callback TEXT NOT NULL,
+        queue_group TEXT,
+        max_retries INT NOT NULL DEFAULT 0,
+        dead_letter_subject TEXT,
+        dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+        batch_size INT NOT NULL DEFAULT 1,
+        batch_linger_ms BIGINT NOT NULL DEFAULT 0,
         UNIQUE(subject, callback)
     );
     "#,
     name = "create_subscriptions_table",
 );
 
+extension_sql!(
+    r#"
+    CREATE TABLE IF NOT EXISTS pgnats.kv_watches (
+        bucket TEXT NOT NULL,
+        key_pattern TEXT NOT NULL,
+        callback TEXT NOT NULL,
+        last_revision BIGINT,
+        UNIQUE(bucket, key_pattern, callback)
+    );
+    "#,
+    name = "create_kv_watches_table",
+    requires = ["create_subscriptions_table"]
+);
+
+extension_sql!(
+    r#"
+    CREATE TABLE IF NOT EXISTS pgnats.object_watches (
+        store TEXT NOT NULL,
+        callback TEXT NOT NULL,
+        last_modified_ns BIGINT,
+        UNIQUE(store, callback)
+    );
+    "#,
+    name = "create_object_watches_table",
+    requires = ["create_subscriptions_table"]
+);
+
+extension_sql!(
+    r#"
+    CREATE TABLE IF NOT EXISTS pgnats.stream_subscriptions (
+        stream TEXT NOT NULL,
+        consumer TEXT NOT NULL,
+        callback TEXT NOT NULL,
+        max_retries INT NOT NULL DEFAULT 5,
+        dead_letter_subject TEXT,
+        dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+        UNIQUE(stream, consumer, callback)
+    );
+    "#,
+    name = "create_stream_subscriptions_table",
+    requires = ["create_subscriptions_table"]
+);
+
+extension_sql!(
+    r#"
+    CREATE TABLE IF NOT EXISTS pgnats.responders (
+        subject TEXT NOT NULL,
+        callback TEXT NOT NULL,
+        queue_group TEXT,
+        UNIQUE(subject)
+    );
+    "#,
+    name = "create_responders_table",
+    requires = ["create_subscriptions_table"]
+);
+
+extension_sql!(
+    r#"
+    CREATE TABLE IF NOT EXISTS pgnats.services (
+        name TEXT NOT NULL,
+        version TEXT NOT NULL,
+        description TEXT,
+        UNIQUE(name)
+    );
+
+    CREATE TABLE IF NOT EXISTS pgnats.service_endpoints (
+        service TEXT NOT NULL REFERENCES pgnats.services(name) ON DELETE CASCADE,
+        endpoint TEXT NOT NULL,
+        subject TEXT NOT NULL,
+        callback TEXT NOT NULL,
+        UNIQUE(service, endpoint)
+    );
+    "#,
+    name = "create_services_tables",
+    requires = ["create_subscriptions_table"]
+);
+
+extension_sql!(
+    r#"
+    CREATE TABLE IF NOT EXISTS pgnats.dead_letters (
+        id BIGSERIAL PRIMARY KEY,
+        subject TEXT NOT NULL,
+        callback TEXT NOT NULL,
+        payload BYTEA NOT NULL,
+        headers JSONB,
+        error TEXT NOT NULL,
+        attempts INT NOT NULL,
+        failed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+    );
+    "#,
+    name = "create_dead_letters_table",
+    requires = ["create_subscriptions_table"]
+);
+
 extension_sql!(
     r#"
     CREATE OR REPLACE FUNCTION pgnats.cleanup_subscriptions_on_drop()
@@ -49,6 +164,16 @@ extension_sql!(
                 clean_name := split_part(obj.object_identity, '(', 1);
                 DELETE FROM pgnats.subscriptions
                 WHERE callback = clean_name;
+                DELETE FROM pgnats.kv_watches
+                WHERE callback = clean_name;
+                DELETE FROM pgnats.object_watches
+                WHERE callback = clean_name;
+                DELETE FROM pgnats.stream_subscriptions
+                WHERE callback = clean_name;
+                DELETE FROM pgnats.responders
+                WHERE callback = clean_name;
+                DELETE FROM pgnats.service_endpoints
+                WHERE callback = clean_name;
             END IF;
         END LOOP;
     END;
@@ -60,14 +185,27 @@ extension_sql!(
     EXECUTE FUNCTION pgnats.cleanup_subscriptions_on_drop();
     "#,
     name = "delete_function_from_subscriptions_table",
-    requires = ["create_subscriptions_table"]
+    requires = [
+        "create_subscriptions_table",
+        "create_kv_watches_table",
+        "create_object_watches_table",
+        "create_stream_subscriptions_table",
+        "create_responders_table",
+        "create_services_tables"
+    ]
 );
 
 pub static LAUNCHER_MESSAGE_BUS: PgLwLock<RingQueue<MESSAGE_BUS_SIZE>> =
     PgLwLock::new(c"pgnats_launcher_message_bus");
 
+pub type SubscriptionsStatusBoard = StatusBoard<STATUS_SLOTS, STATUS_BOARD_SIZE>;
+
+pub static SUBSCRIPTIONS_STATUS: PgLwLock<SubscriptionsStatusBoard> =
+    PgLwLock::new(c"pgnats_subscriptions_status");
+
 pub fn init_background_worker_launcher() {
     pg_shmem_init!(LAUNCHER_MESSAGE_BUS);
+    pg_shmem_init!(SUBSCRIPTIONS_STATUS);
 
     BackgroundWorkerBuilder::new("PGNats Background Worker Launcher")
         .set_function(LAUNCHER_ENTRY_POINT)
@@ -0,0 +1,69 @@
+/// Position of a subscriber worker in the pool of its database.
+///
+/// Every subscription is owned by exactly one worker of the pool, chosen by the hash of
+/// its key (subject, bucket, store, stream or service name), so messages of one subject
+/// are always processed in order by the same worker while unrelated subjects are
+/// processed concurrently. Redelivered messages of a failed callback are the exception:
+/// a retry is queued behind the messages received in the meantime.
+///
+/// A `count` of zero means that the launcher does not know the pool size yet: such a
+/// worker reads it from the foreign server options and reports it to the launcher.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorkerPartition {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl Default for WorkerPartition {
+    fn default() -> Self {
+        Self { index: 0, count: 1 }
+    }
+}
+
+impl WorkerPartition {
+    /// Returns the index of the worker owning `key` in a pool of `count` workers.
+    pub fn of(key: &str, count: usize) -> usize {
+        if count <= 1 {
+            return 0;
+        }
+
+        (fnv1a(key.as_bytes()) % count as u64) as usize
+    }
+
+    pub fn owns(&self, key: &str) -> bool {
+        Self::of(key, self.count) == self.index
+    }
+
+    /// Encodes the worker id and its partition as the background worker extra argument.
+    pub fn to_extra(self, worker_id: usize) -> String {
+        format!("{}:{}/{}", worker_id, self.index, self.count)
+    }
+
+    /// Decodes the background worker extra argument produced by [`Self::to_extra`].
+    pub fn from_extra(extra: &str) -> Option<(usize, Self)> {
+        let (worker_id, partition) = extra.split_once(':')?;
+        let (index, count) = partition.split_once('/')?;
+
+        let partition = Self {
+            index: index.parse().ok()?,
+            count: count.parse().ok()?,
+        };
+
+        if partition.index >= partition.count.max(1) {
+            return None;
+        }
+
+        Some((worker_id.parse().ok()?, partition))
+    }
+}
+
+/// 64-bit FNV-1a hash. Unlike `DefaultHasher`, its result is fixed across Rust releases,
+/// so workers built by different compilers agree on the owner of a key.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
@@ -0,0 +1,194 @@
+use pgrx::{pg_sys as sys, PGRXSharedMemory};
+use serde::{Deserialize, Serialize};
+
+/// Status of a subscription as reported by the subscriber worker running it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionStatus {
+    pub subject: String,
+    pub callback: String,
+    pub active: bool,
+    pub received: u64,
+    pub processed: u64,
+    pub errors: u64,
+    pub last_error: Option<String>,
+    /// Time of the last received message in nanoseconds since the Unix epoch.
+    pub last_message_at: Option<i128>,
+    pub queue_depth: u64,
+}
+
+/// Subscription statuses published by the subscriber workers, one slot per worker.
+///
+/// The `SIZE` bytes of the board are split between as many slots as the cluster can run
+/// background workers (`max_worker_processes`), up to `SLOTS`. Every worker serializes a
+/// snapshot of its subscriptions with [`encode_statuses`] outside the lock, replaces the
+/// contents of its slot with it whenever it changes, and frees the slot when it exits.
+/// Slots of workers which exited without freeing them are reused.
+#[repr(C)]
+pub struct StatusBoard<const SLOTS: usize, const SIZE: usize> {
+    slots: [StatusSlot; SLOTS],
+    data: [u8; SIZE],
+}
+
+#[repr(C)]
+struct StatusSlot {
+    pid: i32,
+    db_oid: u32,
+    len: usize,
+    /// Whether statuses were left out because the snapshot did not fit into the slot.
+    truncated: bool,
+}
+
+impl StatusSlot {
+    const EMPTY: Self = Self {
+        pid: 0,
+        db_oid: 0,
+        len: 0,
+        truncated: false,
+    };
+
+    fn is_owned(&self) -> bool {
+        // SAFETY: `BackendPidGetProc` only reads the process array under its own lock
+        // and accepts any pid.
+        self.pid != 0 && !unsafe { sys::BackendPidGetProc(self.pid) }.is_null()
+    }
+}
+
+/// Statuses of the subscriptions serialized for a slot of the [`StatusBoard`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EncodedStatuses {
+    data: Vec<u8>,
+    truncated: bool,
+}
+
+impl EncodedStatuses {
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+/// Serializes `statuses` one after another, leaving out those which do not fit into
+/// `limit` bytes.
+pub fn encode_statuses(
+    statuses: &[SubscriptionStatus],
+    limit: usize,
+) -> anyhow::Result<EncodedStatuses> {
+    let mut encoded = EncodedStatuses::default();
+
+    for status in statuses {
+        let data = postcard::to_stdvec(status)?;
+
+        if encoded.data.len().saturating_add(data.len()) > limit {
+            encoded.truncated = true;
+            break;
+        }
+
+        encoded.data.extend_from_slice(&data);
+    }
+
+    Ok(encoded)
+}
+
+impl<const SLOTS: usize, const SIZE: usize> StatusBoard<SLOTS, SIZE> {
+    pub const fn new() -> Self {
+        Self {
+            slots: [StatusSlot::EMPTY; SLOTS],
+            data: [0; SIZE],
+        }
+    }
+
+    /// Number of slots in use, one per background worker the cluster can run.
+    fn slot_count() -> usize {
+        // SAFETY: `max_worker_processes` is a Postgres global set from the configuration
+        // at server start, which cannot change while the server is running.
+        let workers = unsafe { sys::max_worker_processes };
+
+        usize::try_from(workers).map_or(SLOTS, |workers| workers.clamp(1, SLOTS))
+    }
+
+    /// Size of the data of a slot in bytes.
+    pub fn slot_size() -> usize {
+        SIZE / Self::slot_count()
+    }
+
+    fn slot_data(&mut self, index: usize) -> Option<&mut [u8]> {
+        let size = Self::slot_size();
+        self.data
+            .get_mut(index.checked_mul(size)?..)?
+            .get_mut(..size)
+    }
+
+    /// Replaces the statuses published by the worker with `pid`.
+    pub fn publish(
+        &mut self,
+        pid: i32,
+        db_oid: u32,
+        statuses: &EncodedStatuses,
+    ) -> anyhow::Result<()> {
+        let slots = self.slots.get(..Self::slot_count()).unwrap_or_default();
+        let index = slots
+            .iter()
+            .position(|slot| slot.pid == pid)
+            .or_else(|| slots.iter().position(|slot| !slot.is_owned()))
+            .ok_or_else(|| anyhow::anyhow!("No free slot to publish subscription statuses"))?;
+
+        self.slot_data(index)
+            .and_then(|data| data.get_mut(..statuses.data.len()))
+            .ok_or_else(|| anyhow::anyhow!("Subscription statuses do not fit into the slot"))?
+            .copy_from_slice(&statuses.data);
+
+        let slot = self
+            .slots
+            .get_mut(index)
+            .ok_or_else(|| anyhow::anyhow!("No free slot to publish subscription statuses"))?;
+        slot.len = statuses.data.len();
+        slot.truncated = statuses.truncated;
+        slot.db_oid = db_oid;
+        slot.pid = pid;
+
+        Ok(())
+    }
+
+    pub fn release(&mut self, pid: i32) {
+        for slot in self.slots.iter_mut().filter(|slot| slot.pid == pid) {
+            *slot = StatusSlot::EMPTY;
+        }
+    }
+
+    /// Statuses published by the running workers of the database `db_oid`, and whether
+    /// some of them were left out because they did not fit into shared memory.
+    pub fn statuses(&self, db_oid: u32) -> (Vec<SubscriptionStatus>, bool) {
+        let size = Self::slot_size();
+        let mut statuses = Vec::new();
+        let mut truncated = false;
+
+        for (index, slot) in self.slots.iter().enumerate().take(Self::slot_count()) {
+            if slot.db_oid != db_oid || !slot.is_owned() {
+                continue;
+            }
+
+            truncated |= slot.truncated;
+
+            let mut data = index
+                .checked_mul(size)
+                .and_then(|start| self.data.get(start..)?.get(..slot.len))
+                .unwrap_or_default();
+
+            while let Ok((status, rest)) = postcard::take_from_bytes(data) {
+                statuses.push(status);
+                data = rest;
+            }
+        }
+
+        (statuses, truncated)
+    }
+}
+
+impl<const SLOTS: usize, const SIZE: usize> Default for StatusBoard<SLOTS, SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// SAFETY: `StatusBoard` is a plain `repr(C)` structure without pointers, so it can be
+// placed in shared memory and accessed from several processes under a lock.
+unsafe impl<const SLOTS: usize, const SIZE: usize> PGRXSharedMemory for StatusBoard<SLOTS, SIZE> {}
@@ -1,16 +1,37 @@
-use std::sync::{mpsc::Sender, Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{mpsc::Sender, Arc},
+    time::{Duration, Instant, SystemTime},
+};
 
+use async_nats::jetstream::AckKind;
 use pgrx::bgworkers::BackgroundWorker;
 
 use crate::{
     bgw::{
         notification::PgInstanceNotification,
+        partition::WorkerPartition,
+        status::{encode_statuses, EncodedStatuses, SubscriptionStatus},
         subscriber::{
-            pg_api::{fetch_status, fetch_subject_with_callbacks, CallError, PgInstanceStatus},
+            nats::{
+                retry_delay, KvWatchKey, StreamDelivery, StreamSubscriptionKey, SubscriptionKey,
+            },
+            pg_api::{
+                check_payload, fetch_callback_signature, fetch_kv_watches, fetch_object_watches,
+                fetch_responder_function, fetch_service_endpoints, fetch_services, fetch_status,
+                fetch_stream_subscriptions, fetch_subject_with_callbacks, fetch_subscriptions,
+                insert_dead_letters, BatchPolicy, CallError, CallbackMessage, PgInstanceStatus,
+                ResponderFunction, RetryPolicy,
+            },
             InternalWorkerMessage, NatsConnectionState,
         },
+        SubscriptionsStatusBoard, DEAD_LETTERS_TABLE_NAME, KV_WATCHES_TABLE_NAME,
+        OBJECT_WATCHES_TABLE_NAME, RESPONDERS_TABLE_NAME, SERVICES_TABLE_NAME,
+        SERVICE_ENDPOINTS_TABLE_NAME, STREAM_SUBSCRIPTIONS_TABLE_NAME, SUBSCRIPTIONS_STATUS,
     },
     config::Config,
+    utils::CallbackSignature,
+    warn,
 };
 
 pub struct SubscriberContext {
@@ -20,6 +41,18 @@ pub struct SubscriberContext {
     config: Config,
     nats: NatsConnectionState,
     status: PgInstanceStatus,
+    partition: WorkerPartition,
+    callback_signatures: HashMap<Arc<str>, CallbackSignature>,
+    responder_functions: HashMap<Arc<str>, ResponderFunction>,
+    retry_policies: HashMap<(Arc<str>, Arc<str>), RetryPolicy>,
+    stream_retry_policies: HashMap<(StreamSubscriptionKey, Arc<str>), RetryPolicy>,
+    /// Functions that are done with a stream message awaiting redelivery, by the consumer
+    /// and stream sequence of the message.
+    stream_settled: HashMap<(StreamSubscriptionKey, u64), HashSet<Arc<str>>>,
+    batches: HashMap<(Arc<str>, Arc<str>), PendingBatch>,
+    stats: HashMap<(Arc<str>, Arc<str>), CallbackStats>,
+    /// Statuses last published to shared memory, `None` before the first publication.
+    published_statuses: Option<EncodedStatuses>,
 
     #[cfg(any(test, feature = "pg_test"))]
     pub(super) fetch_status: PgInstanceStatus,
@@ -31,6 +64,7 @@ impl SubscriberContext {
         sender: Sender<InternalWorkerMessage>,
         nats: NatsConnectionState,
         config: Config,
+        partition: WorkerPartition,
     ) -> Self {
         let status = BackgroundWorker::transaction(fetch_status);
 
@@ -40,6 +74,15 @@ impl SubscriberContext {
             nats,
             config,
             status,
+            partition,
+            callback_signatures: HashMap::new(),
+            responder_functions: HashMap::new(),
+            retry_policies: HashMap::new(),
+            stream_retry_policies: HashMap::new(),
+            stream_settled: HashMap::new(),
+            batches: HashMap::new(),
+            stats: HashMap::new(),
+            published_statuses: None,
             #[cfg(any(test, feature = "pg_test"))]
             fetch_status: status,
         }
@@ -56,6 +99,16 @@ impl SubscriberContext {
             (PgInstanceStatus::Master, PgInstanceStatus::Replica) => {
                 self.status = PgInstanceStatus::Replica;
                 let _ = self.nats.unsubscribe_all();
+                let _ = self.nats.kv_unwatch_all();
+                let _ = self.nats.object_unwatch_all();
+                let _ = self.nats.stream_unsubscribe_all();
+                let _ = self.nats.unrespond_all();
+                let _ = self.nats.service_stop_all(&self.rt);
+                self.retry_policies.clear();
+                self.stream_retry_policies.clear();
+                self.stream_settled.clear();
+                self.batches.clear();
+                self.stats.clear();
 
                 self.send_notification()?;
             }
@@ -76,20 +129,121 @@ impl SubscriberContext {
             self.nats
                 .reconnect_nats(&config.nats_opt, &self.rt, self.sender.clone())?;
         }
+        if self.config.queue != config.queue {
+            self.nats.set_callback_queue_options(config.queue);
+        }
         self.config = config;
         Ok(())
     }
 
+    /// Restores the subscriptions, watches, responders and services owned by the
+    /// partition of this worker.
     pub fn restore_state(&mut self, subscriptions_table_name: &str) -> anyhow::Result<()> {
-        let subs = BackgroundWorker::transaction(|| {
-            fetch_subject_with_callbacks(subscriptions_table_name)
-        })?;
+        let partition = self.partition;
+        let subs = BackgroundWorker::transaction(|| fetch_subscriptions(subscriptions_table_name))?;
 
-        for (subject, fn_name) in subs {
+        for (subject, fn_name, queue_group, retry, batch) in subs
+            .into_iter()
+            .filter(|(subject, ..)| partition.owns(subject))
+        {
             let _ = self.sender.send(InternalWorkerMessage::Subscribe {
                 register: false,
                 subject,
                 fn_name,
+                queue_group,
+                retry,
+                batch,
+            });
+        }
+
+        let watches = BackgroundWorker::transaction(|| fetch_kv_watches(KV_WATCHES_TABLE_NAME))?;
+
+        for (bucket, key_pattern, fn_name) in watches
+            .into_iter()
+            .filter(|(bucket, ..)| partition.owns(bucket))
+        {
+            let _ = self.sender.send(InternalWorkerMessage::KvWatch {
+                register: false,
+                bucket,
+                key_pattern,
+                fn_name,
+            });
+        }
+
+        let object_watches =
+            BackgroundWorker::transaction(|| fetch_object_watches(OBJECT_WATCHES_TABLE_NAME))?;
+
+        for (store, fn_name) in object_watches
+            .into_iter()
+            .filter(|(store, _)| partition.owns(store))
+        {
+            let _ = self.sender.send(InternalWorkerMessage::ObjectWatch {
+                register: false,
+                store,
+                fn_name,
+            });
+        }
+
+        let stream_subs = BackgroundWorker::transaction(|| {
+            fetch_stream_subscriptions(STREAM_SUBSCRIPTIONS_TABLE_NAME)
+        })?;
+
+        for (stream, consumer, fn_name, retry) in stream_subs
+            .into_iter()
+            .filter(|(stream, ..)| partition.owns(stream))
+        {
+            let _ = self.sender.send(InternalWorkerMessage::StreamSubscribe {
+                register: false,
+                stream,
+                consumer,
+                fn_name,
+                retry,
+            });
+        }
+
+        let responders =
+            BackgroundWorker::transaction(|| fetch_subject_with_callbacks(RESPONDERS_TABLE_NAME))?;
+
+        for (subject, fn_name, queue_group) in responders
+            .into_iter()
+            .filter(|(subject, ..)| partition.owns(subject))
+        {
+            let _ = self.sender.send(InternalWorkerMessage::Respond {
+                register: false,
+                subject,
+                fn_name,
+                queue_group,
+            });
+        }
+
+        let services = BackgroundWorker::transaction(|| fetch_services(SERVICES_TABLE_NAME))?;
+
+        for (name, version, description) in services
+            .into_iter()
+            .filter(|(name, ..)| partition.owns(name))
+        {
+            let _ = self.sender.send(InternalWorkerMessage::ServiceAdd {
+                register: false,
+                name,
+                version,
+                description,
+            });
+        }
+
+        let endpoints = BackgroundWorker::transaction(|| {
+            fetch_service_endpoints(SERVICE_ENDPOINTS_TABLE_NAME)
+        })?;
+
+        for (service, endpoint, subject, fn_name) in endpoints
+            .into_iter()
+            .filter(|(service, ..)| partition.owns(service))
+        {
+            let _ = self.sender.send(InternalWorkerMessage::ServiceEndpointAdd {
+                register: false,
+                service,
+                endpoint,
+                subject,
+                fn_name,
             });
         }
 
@@ -104,30 +258,777 @@ impl SubscriberContext {
         self.status == PgInstanceStatus::Replica
     }
 
-    pub fn handle_subscribe(&mut self, subject: Arc<str>, fn_name: Arc<str>) {
+    pub fn handle_subscribe(
+        &mut self,
+        key: SubscriptionKey,
+        fn_name: Arc<str>,
+        retry: RetryPolicy,
+        batch: BatchPolicy,
+    ) -> anyhow::Result<()> {
+        let signature = self.resolve_callback_signature(&fn_name)?;
+        let id = (key.subject.clone(), fn_name.clone());
+
+        if signature.batch {
+            self.batches.entry(id.clone()).or_default().policy = batch;
+        } else {
+            let _ = self.batches.remove(&id);
+        }
+
+        let _ = self.retry_policies.insert(id, retry);
         self.nats
-            .subscribe(subject, fn_name, &self.rt, self.sender.clone());
+            .subscribe(key, fn_name, &self.rt, self.sender.clone());
+
+        Ok(())
     }
 
     pub fn handle_unsubscribe(&mut self, subject: Arc<str>, fn_name: Arc<str>) {
+        let id = (subject.clone(), fn_name.clone());
+        let _ = self.retry_policies.remove(&id);
+        let _ = self.batches.remove(&id);
+        let _ = self.stats.remove(&id);
         self.nats.unsubscribe(subject, fn_name);
     }
 
-    pub fn handle_unsubscribe_subject(&mut self, subject: &str) {
-        self.nats.unsubscribe_subject(subject);
+    pub fn handle_unsubscribe_subject(&mut self, key: &SubscriptionKey) {
+        self.nats.unsubscribe_subject(key);
     }
 
+    /// Passes a message to the callbacks of a subscription. Batch callbacks only collect
+    /// it, and are called once their batch is full.
     pub fn handle_callback(
         &mut self,
-        subject: &str,
-        data: Arc<[u8]>,
+        key: &SubscriptionKey,
+        message: &CallbackMessage,
+        db_name: &str,
+        callback: impl Fn(&str, CallbackSignature, &[CallbackMessage]) -> Result<(), CallError>,
+    ) {
+        let signatures = &self.callback_signatures;
+        let batches = &mut self.batches;
+        let stats = &mut self.stats;
+        let mut failed_calls = vec![];
+
+        let failed = self
+            .nats
+            .run_callbacks(key, db_name, message, |fnname, message| {
+                let id = (key.subject.clone(), fnname.clone());
+                let stats = stats.entry(id.clone()).or_default();
+                stats.received += 1;
+                stats.last_message_at = Some(SystemTime::now());
+
+                if let Some(batch) = batches.get_mut(&id) {
+                    batch.push(message.clone());
+                    return Ok(());
+                }
+
+                let result = callback(
+                    fnname,
+                    signature_of(signatures, fnname),
+                    std::slice::from_ref(message),
+                );
+                stats.record(&result, 1);
+
+                if result.is_err() {
+                    failed_calls.push(fnname.clone());
+                }
+
+                result
+            });
+
+        for fn_name in &failed_calls {
+            self.refresh_callback_signature(fn_name);
+        }
+
+        for (fn_name, error) in failed {
+            self.handle_failed_callback(
+                &key.subject,
+                fn_name,
+                std::slice::from_ref(message),
+                error,
+                1,
+                db_name,
+            );
+        }
+
+        self.flush_batches(db_name, &callback, |(subject, _), batch| {
+            *subject == key.subject && batch.is_full()
+        });
+    }
+
+    /// Calls the batch callbacks whose batch is full or whose oldest message has waited
+    /// for the linger time of the subscription.
+    pub fn handle_batch_flush(
+        &mut self,
+        db_name: &str,
+        callback: impl Fn(&str, CallbackSignature, &[CallbackMessage]) -> Result<(), CallError>,
+    ) {
+        let now = Instant::now();
+
+        self.flush_batches(db_name, &callback, |_, batch| {
+            batch.is_full() || batch.is_lingered(now)
+        });
+    }
+
+    /// Calls a callback again for messages that failed on an earlier attempt, unless the
+    /// function has been unsubscribed in the meantime.
+    pub fn handle_callback_retry(
+        &mut self,
+        subject: &Arc<str>,
+        fn_name: Arc<str>,
+        messages: &[CallbackMessage],
+        attempt: u32,
+        db_name: &str,
+        callback: impl Fn(&str, CallbackSignature, &[CallbackMessage]) -> Result<(), CallError>,
+    ) {
+        if !self.nats.is_subscribed(subject, &fn_name) {
+            return;
+        }
+
+        self.call_callback(subject, fn_name, messages, attempt, db_name, &callback);
+    }
+
+    fn flush_batches(
+        &mut self,
+        db_name: &str,
+        callback: &impl Fn(&str, CallbackSignature, &[CallbackMessage]) -> Result<(), CallError>,
+        ready: impl Fn(&(Arc<str>, Arc<str>), &PendingBatch) -> bool,
+    ) {
+        let ready: Vec<_> = self
+            .batches
+            .iter_mut()
+            .filter(|(id, batch)| ready(id, batch))
+            .map(|(id, batch)| (id.clone(), batch.take()))
+            .collect();
+
+        for ((subject, fn_name), messages) in ready {
+            let signature = signature_of(&self.callback_signatures, &fn_name);
+            let mut valid = Vec::with_capacity(messages.len());
+
+            // A malformed message would fail every attempt of its batch, so it is
+            // dead-lettered on its own instead.
+            for message in messages {
+                match check_payload(signature.payload, &message.payload) {
+                    Ok(()) => valid.push(message),
+                    Err(err) => self.reject_message(&subject, &fn_name, &message, err, db_name),
+                }
+            }
+
+            if !valid.is_empty() {
+                self.call_callback(&subject, fn_name, &valid, 1, db_name, callback);
+            }
+        }
+    }
+
+    /// How long the worker can wait for new messages before the linger time of a pending
+    /// batch runs out, at most `max`.
+    pub fn batch_wait_timeout(&self, max: Duration) -> Duration {
+        let now = Instant::now();
+
+        self.batches
+            .values()
+            .filter_map(PendingBatch::deadline)
+            .map(|deadline| deadline.saturating_duration_since(now))
+            .fold(max, Duration::min)
+    }
+
+    /// Turns a message that cannot be passed to a callback into a dead letter without
+    /// retrying it.
+    fn reject_message(
+        &mut self,
+        subject: &Arc<str>,
+        fn_name: &Arc<str>,
+        message: &CallbackMessage,
+        error: anyhow::Error,
+        db_name: &str,
+    ) {
+        warn!(
+            context = db_name,
+            "Message on '{}' cannot be passed to function '{fn_name}': {error}", message.subject,
+        );
+
+        let id = (subject.clone(), fn_name.clone());
+        let error_text = error.to_string();
+        self.stats
+            .entry(id.clone())
+            .or_default()
+            .record(&Err(CallError::Other(error)), 1);
+
+        if let Some(retry) = self.retry_policies.get(&id) {
+            self.dead_letter(
+                fn_name,
+                retry,
+                std::slice::from_ref(message),
+                &error_text,
+                1,
+                db_name,
+            );
+        }
+    }
+
+    fn call_callback(
+        &mut self,
+        subject: &Arc<str>,
+        fn_name: Arc<str>,
+        messages: &[CallbackMessage],
+        attempt: u32,
         db_name: &str,
-        callback: impl Fn(&str, &[u8]) -> Result<(), CallError>,
+        callback: &impl Fn(&str, CallbackSignature, &[CallbackMessage]) -> Result<(), CallError>,
     ) {
-        self.nats.run_callbacks(subject, db_name, data, callback);
+        let signature = signature_of(&self.callback_signatures, &fn_name);
+        let result = callback(&fn_name, signature, messages);
+
+        self.stats
+            .entry((subject.clone(), fn_name.clone()))
+            .or_default()
+            .record(&result, messages.len());
+
+        if result.is_err() {
+            self.refresh_callback_signature(&fn_name);
+        }
+
+        match result {
+            Ok(()) => {}
+            Err(CallError::NotFound) => {
+                warn!(
+                    context = db_name,
+                    "Function '{fn_name}' was dropped, unregistering...",
+                );
+
+                self.handle_unsubscribe(subject.clone(), fn_name);
+            }
+            Err(CallError::Other(err)) => {
+                warn!(
+                    context = db_name,
+                    "Error while calling subscriber function '{fn_name}' (attempt {attempt}): {err:?}",
+                );
+
+                self.handle_failed_callback(
+                    subject,
+                    fn_name,
+                    messages,
+                    err.to_string(),
+                    attempt,
+                    db_name,
+                );
+            }
+        }
+    }
+
+    /// Schedules the next attempt of a failed callback with an increasing delay, or turns
+    /// the messages into dead letters once the retries of the subscription are exhausted.
+    /// Scheduled attempts only live in the runtime of this worker and end with it.
+    fn handle_failed_callback(
+        &mut self,
+        subject: &Arc<str>,
+        fn_name: Arc<str>,
+        messages: &[CallbackMessage],
+        error: String,
+        attempt: u32,
+        db_name: &str,
+    ) {
+        let Some(retry) = self
+            .retry_policies
+            .get(&(subject.clone(), fn_name.clone()))
+            .cloned()
+        else {
+            return;
+        };
+
+        if attempt <= retry.max_retries {
+            // Retries bypass the callback queue, so that its overflow policy never drops
+            // a message that is still owed a retry or a dead letter.
+            let sender = self.sender.clone();
+            let subject = subject.clone();
+            let messages = messages.to_vec();
+            let delay = retry_delay(i64::from(attempt));
+
+            let _ = self.rt.spawn(async move {
+                tokio::time::sleep(delay).await;
+
+                let _ = sender.send(InternalWorkerMessage::CallbackRetry {
+                    subject,
+                    fn_name,
+                    messages,
+                    attempt: attempt.saturating_add(1),
+                });
+            });
+
+            return;
+        }
+
+        self.dead_letter(&fn_name, &retry, messages, &error, attempt, db_name);
+    }
+
+    /// Records messages whose callback kept failing in `pgnats.dead_letters` and publishes
+    /// them to the dead-letter subject, as requested by the retry policy.
+    fn dead_letter(
+        &self,
+        fn_name: &str,
+        retry: &RetryPolicy,
+        messages: &[CallbackMessage],
+        error: &str,
+        attempt: u32,
+        db_name: &str,
+    ) {
+        if retry.dead_letter_table {
+            if let Err(err) = BackgroundWorker::transaction(|| {
+                insert_dead_letters(DEAD_LETTERS_TABLE_NAME, fn_name, messages, error, attempt)
+            }) {
+                warn!(
+                    context = db_name,
+                    "Failed to record dead letters of function '{fn_name}': {err}",
+                );
+            }
+        }
+
+        if let Some(dead_letter_subject) = &retry.dead_letter_subject {
+            for message in messages {
+                if let Err(err) = self.rt.block_on(self.nats.publish_dead_letter(
+                    dead_letter_subject.clone(),
+                    message,
+                    fn_name,
+                    error,
+                    attempt,
+                )) {
+                    warn!(
+                        context = db_name,
+                        "Failed to publish dead letter of function '{fn_name}': {err}",
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn handle_kv_watch(&mut self, key: KvWatchKey, fn_name: Arc<str>) {
+        self.nats
+            .kv_watch(key, fn_name, &self.rt, self.sender.clone());
+    }
+
+    pub fn handle_kv_unwatch(&mut self, key: KvWatchKey, fn_name: Arc<str>) {
+        self.nats.kv_unwatch(key, fn_name);
+    }
+
+    pub fn handle_kv_unwatch_pattern(&mut self, key: &KvWatchKey) {
+        self.nats.kv_unwatch_pattern(key);
+    }
+
+    pub fn handle_kv_watch_callback(
+        &mut self,
+        key: &KvWatchKey,
+        db_name: &str,
+        callback: impl Fn(&str) -> Result<(), CallError>,
+    ) {
+        self.nats.run_kv_watch_callbacks(key, db_name, callback);
+    }
+
+    pub fn handle_object_watch(&mut self, store: Arc<str>, fn_name: Arc<str>) {
+        self.nats
+            .object_watch(store, fn_name, &self.rt, self.sender.clone());
+    }
+
+    pub fn handle_object_unwatch(&mut self, store: Arc<str>, fn_name: Arc<str>) {
+        self.nats.object_unwatch(store, fn_name);
+    }
+
+    pub fn handle_object_unwatch_store(&mut self, store: &str) {
+        self.nats.object_unwatch_store(store);
+    }
+
+    pub fn handle_object_watch_callback(
+        &mut self,
+        store: &str,
+        db_name: &str,
+        callback: impl Fn(&str) -> Result<(), CallError>,
+    ) {
+        self.nats
+            .run_object_watch_callbacks(store, db_name, callback);
+    }
+
+    pub fn handle_stream_subscribe(
+        &mut self,
+        key: StreamSubscriptionKey,
+        fn_name: Arc<str>,
+        retry: RetryPolicy,
+    ) -> anyhow::Result<()> {
+        self.resolve_callback_signature(&fn_name)?;
+
+        let max_deliver = i64::from(retry.max_retries).saturating_add(1);
+        let _ = self
+            .stream_retry_policies
+            .insert((key.clone(), fn_name.clone()), retry);
+        self.nats
+            .stream_subscribe(key, fn_name, max_deliver, &self.rt, self.sender.clone());
+
+        Ok(())
+    }
+
+    pub fn handle_stream_unsubscribe(&mut self, key: StreamSubscriptionKey, fn_name: Arc<str>) {
+        let _ = self
+            .stream_retry_policies
+            .remove(&(key.clone(), fn_name.clone()));
+        self.nats.stream_unsubscribe(key, fn_name);
+    }
+
+    pub fn handle_stream_unsubscribe_consumer(&mut self, key: &StreamSubscriptionKey) {
+        self.stream_settled
+            .retain(|(consumer, _), _| consumer != key);
+        self.nats.stream_unsubscribe_consumer(key);
+    }
+
+    /// Runs the callbacks for a stream message and then settles it. A redelivered message
+    /// is only passed to the functions that have neither committed it nor given up on it.
+    /// The message is acknowledged once every callback transaction has committed, and
+    /// negatively acknowledged with an increasing delay while a failed function has
+    /// deliveries left. Otherwise the message is dead-lettered for the functions that
+    /// failed on their last delivery and terminated.
+    pub fn handle_stream_callback(
+        &mut self,
+        key: &StreamSubscriptionKey,
+        message: &CallbackMessage,
+        delivery: StreamDelivery,
+        db_name: &str,
+        callback: impl Fn(&str, CallbackSignature, &CallbackMessage) -> Result<(), CallError>,
+    ) -> anyhow::Result<()> {
+        let StreamDelivery {
+            ack_subject,
+            stream_sequence,
+            delivered,
+        } = delivery;
+        let id = (key.clone(), stream_sequence);
+        let signatures = &self.callback_signatures;
+        let mut settled = self.stream_settled.remove(&id).unwrap_or_default();
+        let mut failed_calls = vec![];
+
+        let failed = self
+            .nats
+            .run_stream_callbacks(key, db_name, message, |fnname, message| {
+                if settled.contains(fnname) {
+                    return Ok(());
+                }
+
+                let result = callback(fnname, signature_of(signatures, fnname), message);
+                if result.is_ok() {
+                    let _ = settled.insert(fnname.clone());
+                } else {
+                    failed_calls.push(fnname.clone());
+                }
+
+                result
+            });
+
+        for fn_name in &failed_calls {
+            self.refresh_callback_signature(fn_name);
+        }
+
+        let Some(failed) = failed else {
+            return self.rt.block_on(
+                self.nats
+                    .ack_stream_message(ack_subject, AckKind::Nak(Some(retry_delay(delivered)))),
+            );
+        };
+
+        let attempts = u32::try_from(delivered).unwrap_or(u32::MAX);
+        let mut retried = false;
+        let mut dead_lettered = false;
+
+        for (fn_name, error) in failed {
+            let retry = self
+                .stream_retry_policies
+                .get(&(key.clone(), fn_name.clone()))
+                .cloned()
+                .unwrap_or_default();
+
+            if attempts <= retry.max_retries {
+                retried = true;
+                continue;
+            }
+
+            self.dead_letter(
+                &fn_name,
+                &retry,
+                std::slice::from_ref(message),
+                &error,
+                attempts,
+                db_name,
+            );
+            let _ = settled.insert(fn_name);
+            dead_lettered = true;
+        }
+
+        let kind = if retried {
+            AckKind::Nak(Some(retry_delay(delivered)))
+        } else if dead_lettered {
+            AckKind::Term
+        } else {
+            AckKind::Ack
+        };
+
+        // Kept until the message is settled, so that a redelivery after a failed
+        // acknowledgement does not call the functions that are done with it again.
+        let _ = self.stream_settled.insert(id.clone(), settled);
+
+        self.rt
+            .block_on(self.nats.ack_stream_message(ack_subject, kind))?;
+
+        if !retried {
+            let _ = self.stream_settled.remove(&id);
+        }
+
+        Ok(())
+    }
+
+    pub fn handle_respond(
+        &mut self,
+        subject: Arc<str>,
+        fn_name: Arc<str>,
+        queue_group: Option<Arc<str>>,
+    ) -> anyhow::Result<()> {
+        self.resolve_responder_function(&fn_name)?;
+        self.nats
+            .respond(subject, fn_name, queue_group, &self.rt, self.sender.clone());
+
+        Ok(())
+    }
+
+    pub fn handle_unrespond(&mut self, subject: &str) {
+        self.nats.unrespond(subject);
+    }
+
+    /// Calls the responder of a subject and publishes its result to the reply subject of
+    /// the request once the transaction has committed.
+    pub fn handle_responder_call(
+        &mut self,
+        subject: &str,
+        message: &CallbackMessage,
+        db_name: &str,
+        callback: impl Fn(&str, ResponderFunction, &CallbackMessage) -> Result<Vec<u8>, CallError>,
+    ) -> anyhow::Result<()> {
+        let Some(reply) = message.reply.clone() else {
+            return Ok(());
+        };
+
+        let functions = &self.responder_functions;
+        let mut failed_call = None;
+
+        let response = self
+            .nats
+            .run_responder(subject, db_name, message, |fnname, message| {
+                let result = match functions.get(fnname).copied() {
+                    Some(function) => callback(fnname, function, message),
+                    None => Err(CallError::Other(anyhow::anyhow!(
+                        "Function '{fnname}' is not resolved"
+                    ))),
+                };
+
+                if result.is_err() {
+                    failed_call = Some(Arc::<str>::from(fnname));
+                }
+
+                result
+            });
+
+        if let Some(fn_name) = failed_call {
+            self.refresh_responder_function(&fn_name);
+        }
+
+        let Some(response) = response else {
+            return Ok(());
+        };
+
+        self.rt.block_on(self.nats.send_response(reply, response))
+    }
+
+    pub fn handle_service_add(
+        &mut self,
+        name: Arc<str>,
+        version: String,
+        description: Option<String>,
+    ) -> anyhow::Result<()> {
+        self.nats.service_add(name, version, description, &self.rt)
+    }
+
+    pub fn handle_service_remove(&mut self, name: &str) {
+        self.nats.service_remove(name, &self.rt);
+    }
+
+    pub fn handle_service_endpoint_add(
+        &mut self,
+        service: Arc<str>,
+        endpoint: Arc<str>,
+        subject: String,
+        fn_name: Arc<str>,
+    ) -> anyhow::Result<()> {
+        self.resolve_responder_function(&fn_name)?;
+        self.nats
+            .service_endpoint_add(service, endpoint, subject, fn_name, &self.rt)
+    }
+
+    pub fn handle_service_endpoint_remove(&mut self, service: &Arc<str>, endpoint: &str) {
+        self.nats
+            .service_endpoint_remove(service, endpoint, &self.rt);
+    }
+
+    /// Calls the function of a service endpoint and answers the request once the
+    /// transaction has committed. A dropped function removes the endpoint.
+    pub fn handle_service_request(
+        &mut self,
+        service: &Arc<str>,
+        endpoint: &str,
+        message: &CallbackMessage,
+        request: async_nats::service::Request,
+        db_name: &str,
+        callback: impl Fn(&str, ResponderFunction, &CallbackMessage) -> Result<Vec<u8>, CallError>,
+    ) -> anyhow::Result<()> {
+        let Some(fnname) = self.nats.service_endpoint_function(service, endpoint) else {
+            return Ok(());
+        };
+
+        let response = match self.responder_functions.get(&fnname).copied() {
+            Some(function) => callback(&fnname, function, message),
+            None => Err(CallError::Other(anyhow::anyhow!(
+                "Function '{fnname}' is not resolved"
+            ))),
+        };
+
+        if response.is_err() {
+            self.refresh_responder_function(&fnname);
+        }
+
+        let response = match response {
+            Ok(payload) => Ok(payload),
+            Err(CallError::NotFound) => {
+                warn!(
+                    context = db_name,
+                    "Function '{fnname}' was dropped, unregistering...",
+                );
+
+                self.nats
+                    .service_endpoint_remove(service, endpoint, &self.rt);
+
+                Err(format!("Function '{fnname}' does not exist"))
+            }
+            Err(CallError::Other(err)) => {
+                warn!(
+                    context = db_name,
+                    "Error while calling service endpoint function '{fnname}': {err:?}",
+                );
+
+                Err(err.to_string())
+            }
+        };
+
+        self.rt
+            .block_on(self.nats.send_service_response(request, response))
+    }
+
+    fn resolve_responder_function(&mut self, fn_name: &Arc<str>) -> anyhow::Result<()> {
+        let function = BackgroundWorker::transaction(|| fetch_responder_function(fn_name))?;
+        let _ = self.responder_functions.insert(fn_name.clone(), function);
+
+        Ok(())
+    }
+
+    /// Forgets the cached types of a responder function whose call failed and looks them up
+    /// again, as the function may have been dropped or replaced.
+    fn refresh_responder_function(&mut self, fn_name: &Arc<str>) {
+        let _ = self.responder_functions.remove(fn_name);
+        let _ = self.resolve_responder_function(fn_name);
+    }
+
+    /// Forgets the cached signature of a callback whose call failed and looks it up again,
+    /// as the function may have been dropped or replaced by one taking other arguments.
+    fn refresh_callback_signature(&mut self, fn_name: &Arc<str>) {
+        let _ = self.callback_signatures.remove(fn_name);
+        let _ = self.resolve_callback_signature(fn_name);
+    }
+
+    /// Looks up the argument shape of a callback in the catalog, so that messages can be
+    /// passed to it without a catalog lookup per call.
+    fn resolve_callback_signature(
+        &mut self,
+        fn_name: &Arc<str>,
+    ) -> anyhow::Result<CallbackSignature> {
+        let signature = BackgroundWorker::transaction(|| fetch_callback_signature(fn_name))?;
+        let _ = self.callback_signatures.insert(fn_name.clone(), signature);
+
+        Ok(signature)
+    }
+
+    /// Publishes the status of the subscriptions of this worker to shared memory, unless
+    /// it has not changed since the last publication.
+    pub fn publish_status(&mut self, db_oid: u32) -> anyhow::Result<()> {
+        let mut callbacks: HashMap<_, _> =
+            self.stats.keys().map(|id| (id.clone(), false)).collect();
+
+        for (subject, fn_name, active) in self.nats.subscribed_callbacks() {
+            let _ = callbacks.insert((subject.clone(), fn_name.clone()), active);
+        }
+
+        let queued = self.nats.queued_callbacks();
+
+        let mut statuses: Vec<_> = callbacks
+            .into_iter()
+            .map(|(id, active)| {
+                let stats = self.stats.get(&id);
+                let pending = self
+                    .batches
+                    .get(&id)
+                    .map_or(0, |batch| batch.messages.len());
+                let queued = queued.get(&id.0).copied().unwrap_or_default();
+
+                SubscriptionStatus {
+                    subject: id.0.to_string(),
+                    callback: id.1.to_string(),
+                    active,
+                    received: stats.map_or(0, |stats| stats.received),
+                    processed: stats.map_or(0, |stats| stats.processed),
+                    errors: stats.map_or(0, |stats| stats.errors),
+                    last_error: stats.and_then(|stats| stats.last_error.clone()),
+                    last_message_at: stats
+                        .and_then(|stats| stats.last_message_at)
+                        .and_then(|at| at.duration_since(SystemTime::UNIX_EPOCH).ok())
+                        .and_then(|since| i128::try_from(since.as_nanos()).ok()),
+                    queue_depth: u64::try_from(queued + pending).unwrap_or(u64::MAX),
+                }
+            })
+            .collect();
+
+        // A stable order, so that an unchanged status is encoded the same way.
+        statuses.sort_by(|a, b| (&a.subject, &a.callback).cmp(&(&b.subject, &b.callback)));
+
+        let encoded = encode_statuses(&statuses, SubscriptionsStatusBoard::slot_size())?;
+
+        if self.published_statuses.as_ref() == Some(&encoded) {
+            return Ok(());
+        }
+
+        if encoded.is_truncated()
+            && !self
+                .published_statuses
+                .as_ref()
+                .is_some_and(EncodedStatuses::is_truncated)
+        {
+            warn!("Subscription statuses do not fit into shared memory, some are not reported");
+        }
+
+        // SAFETY: `MyProcPid` is a Postgres backend global which is initialized
+        // before extension code is executed. Postgres backends are single-threaded,
+        // and this variable is immutable after initialization.
+        let pid = unsafe { pgrx::pg_sys::MyProcPid };
+
+        SUBSCRIPTIONS_STATUS
+            .exclusive()
+            .publish(pid, db_oid, &encoded)?;
+        self.published_statuses = Some(encoded);
+
+        Ok(())
     }
 
     pub fn send_notification(&self) -> anyhow::Result<()> {
+        // The instance status is the same for the whole pool, so it is announced once.
+        if self.partition.index != 0 {
+            return Ok(());
+        }
+
         let config = &self.config;
         let status = self.status;
 
@@ -148,8 +1049,78 @@ impl SubscriberContext {
     }
 }
 
+/// Counters of a subscription callback, published as the status of the subscription.
+#[derive(Default)]
+struct CallbackStats {
+    received: u64,
+    processed: u64,
+    errors: u64,
+    last_error: Option<String>,
+    last_message_at: Option<SystemTime>,
+}
+
+impl CallbackStats {
+    fn record(&mut self, result: &Result<(), CallError>, messages: usize) {
+        match result {
+            Ok(()) => {
+                self.processed += u64::try_from(messages).unwrap_or_default();
+            }
+            Err(err) => {
+                self.errors += 1;
+                self.last_error = Some(match err {
+                    CallError::NotFound => "Function does not exist".to_string(),
+                    CallError::Other(err) => err.to_string(),
+                });
+            }
+        }
+    }
+}
+
+/// Messages collected for a batch callback.
+#[derive(Default)]
+struct PendingBatch {
+    policy: BatchPolicy,
+    messages: Vec<CallbackMessage>,
+    since: Option<Instant>,
+}
+
+impl PendingBatch {
+    fn push(&mut self, message: CallbackMessage) {
+        let _ = self.since.get_or_insert_with(Instant::now);
+        self.messages.push(message);
+    }
+
+    fn take(&mut self) -> Vec<CallbackMessage> {
+        self.since = None;
+        std::mem::take(&mut self.messages)
+    }
+
+    fn is_full(&self) -> bool {
+        !self.messages.is_empty()
+            && usize::try_from(self.policy.size).is_ok_and(|size| self.messages.len() >= size)
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.since?
+            .checked_add(Duration::from_millis(self.policy.linger_ms))
+    }
+
+    fn is_lingered(&self, now: Instant) -> bool {
+        self.since.is_some_and(|since| {
+            now.duration_since(since) >= Duration::from_millis(self.policy.linger_ms)
+        })
+    }
+}
+
 impl Drop for SubscriberContext {
     fn drop(&mut self) {
         let _ = self.rt.block_on(self.nats.drain());
     }
 }
+
+fn signature_of(
+    signatures: &HashMap<Arc<str>, CallbackSignature>,
+    fn_name: &str,
+) -> CallbackSignature {
+    signatures.get(fn_name).copied().unwrap_or_default()
+}
@@ -1,8 +1,15 @@
 use std::sync::Arc;
 
+use async_nats::service::Request;
 use serde::{Deserialize, Serialize};
 
-use crate::config::Config;
+use crate::{
+    bgw::subscriber::{
+        nats::StreamDelivery,
+        pg_api::{BatchPolicy, CallbackMessage, KvWatchEntry, ObjectWatchEntry, RetryPolicy},
+    },
+    config::Config,
+};
 
 #[derive(Serialize, Deserialize)]
 pub enum SubscriberMessage {
@@ -12,11 +19,69 @@ pub enum SubscriberMessage {
     Subscribe {
         subject: String,
         fn_name: String,
+        queue_group: Option<String>,
+        retry: RetryPolicy,
+        batch: BatchPolicy,
     },
     Unsubscribe {
         subject: String,
         fn_name: String,
     },
+    KvWatch {
+        bucket: String,
+        key_pattern: String,
+        fn_name: String,
+    },
+    KvUnwatch {
+        bucket: String,
+        key_pattern: String,
+        fn_name: String,
+    },
+    ObjectWatch {
+        store: String,
+        fn_name: String,
+    },
+    ObjectUnwatch {
+        store: String,
+        fn_name: String,
+    },
+    StreamSubscribe {
+        stream: String,
+        consumer: String,
+        fn_name: String,
+        retry: RetryPolicy,
+    },
+    StreamUnsubscribe {
+        stream: String,
+        consumer: String,
+        fn_name: String,
+    },
+    Respond {
+        subject: String,
+        fn_name: String,
+        queue_group: Option<String>,
+    },
+    Unrespond {
+        subject: String,
+    },
+    ServiceAdd {
+        name: String,
+        version: String,
+        description: Option<String>,
+    },
+    ServiceRemove {
+        name: String,
+    },
+    ServiceEndpointAdd {
+        service: String,
+        endpoint: String,
+        subject: String,
+        fn_name: String,
+    },
+    ServiceEndpointRemove {
+        service: String,
+        endpoint: String,
+    },
     #[cfg(any(test, feature = "pg_test"))]
     ChangeStatus {
         is_master: bool,
@@ -28,6 +93,9 @@ pub(super) enum InternalWorkerMessage {
         register: bool,
         subject: String,
         fn_name: String,
+        queue_group: Option<String>,
+        retry: RetryPolicy,
+        batch: BatchPolicy,
     },
     Unsubscribe {
         subject: Arc<str>,
@@ -35,10 +103,122 @@ pub(super) enum InternalWorkerMessage {
     },
     CallbackCall {
         subject: Arc<str>,
-        data: Arc<[u8]>,
+        queue_group: Option<Arc<str>>,
+        message: CallbackMessage,
+    },
+    CallbackRetry {
+        subject: Arc<str>,
+        fn_name: Arc<str>,
+        messages: Vec<CallbackMessage>,
+        attempt: u32,
     },
     UnsubscribeSubject {
         subject: Arc<str>,
+        queue_group: Option<Arc<str>>,
         reason: String,
     },
+    KvWatch {
+        register: bool,
+        bucket: String,
+        key_pattern: String,
+        fn_name: String,
+    },
+    KvUnwatch {
+        bucket: Arc<str>,
+        key_pattern: Arc<str>,
+        fn_name: Arc<str>,
+    },
+    KvWatchCall {
+        bucket: Arc<str>,
+        key_pattern: Arc<str>,
+        entry: KvWatchEntry,
+    },
+    KvUnwatchPattern {
+        bucket: Arc<str>,
+        key_pattern: Arc<str>,
+        reason: String,
+    },
+    ObjectWatch {
+        register: bool,
+        store: String,
+        fn_name: String,
+    },
+    ObjectUnwatch {
+        store: Arc<str>,
+        fn_name: Arc<str>,
+    },
+    ObjectWatchCall {
+        store: Arc<str>,
+        entry: ObjectWatchEntry,
+    },
+    ObjectUnwatchStore {
+        store: Arc<str>,
+        reason: String,
+    },
+    StreamSubscribe {
+        register: bool,
+        stream: String,
+        consumer: String,
+        fn_name: String,
+        retry: RetryPolicy,
+    },
+    StreamUnsubscribe {
+        stream: Arc<str>,
+        consumer: Arc<str>,
+        fn_name: Arc<str>,
+    },
+    StreamCallbackCall {
+        stream: Arc<str>,
+        consumer: Arc<str>,
+        message: CallbackMessage,
+        delivery: StreamDelivery,
+    },
+    StreamUnsubscribeConsumer {
+        stream: Arc<str>,
+        consumer: Arc<str>,
+        reason: String,
+    },
+    Respond {
+        register: bool,
+        subject: String,
+        fn_name: String,
+        queue_group: Option<String>,
+    },
+    Unrespond {
+        subject: Arc<str>,
+    },
+    ResponderCall {
+        subject: Arc<str>,
+        message: CallbackMessage,
+    },
+    UnrespondSubject {
+        subject: Arc<str>,
+        reason: String,
+    },
+    ServiceAdd {
+        register: bool,
+        name: String,
+        version: String,
+        description: Option<String>,
+    },
+    ServiceRemove {
+        name: Arc<str>,
+    },
+    ServiceEndpointAdd {
+        register: bool,
+        service: String,
+        endpoint: String,
+        subject: String,
+        fn_name: String,
+    },
+    ServiceEndpointRemove {
+        service: Arc<str>,
+        endpoint: Arc<str>,
+    },
+    ServiceRequest {
+        service: Arc<str>,
+        endpoint: Arc<str>,
+        message: CallbackMessage,
+        request: Request,
+    },
 }
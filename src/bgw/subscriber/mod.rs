@@ -1,12 +1,16 @@
 mod context;
 mod nats;
+mod queue;
 
 pub mod message;
 pub mod pg_api;
 
-use std::sync::{
-    mpsc::{channel, Sender},
-    Arc,
+use std::{
+    sync::{
+        mpsc::{channel, Sender},
+        Arc,
+    },
+    time::Duration,
 };
 
 use pgrx::{
@@ -20,6 +24,7 @@ use crate::{
             message::{ExtensionStatus, LauncherMessage},
             send_message_to_launcher,
         },
+        partition::WorkerPartition,
         pgrx_wrappers::{
             dsm::{DsmHandle, DynamicSharedMemory},
             shm_mq::ShmMqReceiver,
@@ -28,18 +33,31 @@ use crate::{
         subscriber::{
             context::SubscriberContext,
             message::{InternalWorkerMessage, SubscriberMessage},
-            nats::NatsConnectionState,
-            pg_api::{call_function, delete_subject_callback, insert_subject_callback},
+            nats::{KvWatchKey, NatsConnectionState, StreamSubscriptionKey, SubscriptionKey},
+            pg_api::{
+                call_function, call_kv_watch_function, call_object_watch_function, call_responder,
+                delete_kv_watch, delete_object_watch, delete_responder, delete_service,
+                delete_service_endpoint, delete_stream_subscription, delete_subject_callback,
+                insert_kv_watch, insert_object_watch, insert_responder, insert_service,
+                insert_service_endpoint, insert_stream_subscription, insert_subject_callback,
+                CallError, CallbackMessage,
+            },
+            queue::BoundedQueue,
         },
-        LAUNCHER_MESSAGE_BUS, SUBSCRIPTIONS_TABLE_NAME,
+        KV_WATCHES_TABLE_NAME, LAUNCHER_MESSAGE_BUS, OBJECT_WATCHES_TABLE_NAME,
+        RESPONDERS_TABLE_NAME, SERVICES_TABLE_NAME, SERVICE_ENDPOINTS_TABLE_NAME,
+        STREAM_SUBSCRIPTIONS_TABLE_NAME, SUBSCRIPTIONS_STATUS, SUBSCRIPTIONS_TABLE_NAME,
     },
     config::{fetch_config, fetch_fdw_server_name},
     constants::{EXTENSION_NAME, FDW_EXTENSION_NAME},
     debug, error, log,
-    utils::{get_database_name, is_extension_installed, unpack_i64_to_oid_dsmh},
+    utils::{get_database_name, is_extension_installed, unpack_i64_to_oid_dsmh, CallbackSignature},
     warn,
 };
 
+/// How long the worker waits for a wake-up before polling its queues again.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 #[pgrx::pg_guard]
 #[unsafe(no_mangle)]
 pub extern "C-unwind" fn background_worker_subscriber_entry_point(arg: sys::Datum) {
@@ -89,6 +107,9 @@ pub fn background_worker_subscriber_main<const N: usize>(
         anyhow::anyhow!("Subscriber: failed to resolve database name for OID {db_oid}",)
     })?;
 
+    let (worker_id, partition) =
+        WorkerPartition::from_extra(BackgroundWorker::get_extra()).unwrap_or_default();
+
     let result = background_worker_subscriber_main_internal(
         launcher_bus,
         sub_table_name,
@@ -96,12 +117,21 @@ pub fn background_worker_subscriber_main<const N: usize>(
         db_oid.to_u32(),
         &db_name,
         dsmh,
+        (worker_id, partition),
     );
 
+    // SAFETY: `MyProcPid` is a Postgres backend global which is initialized
+    // before extension code is executed. Postgres backends are single-threaded,
+    // and this variable is immutable after initialization.
+    SUBSCRIPTIONS_STATUS
+        .exclusive()
+        .release(unsafe { sys::MyProcPid });
+
     send_message_to_launcher(
         launcher_bus,
         LauncherMessage::SubscriberExit {
             db_oid: db_oid.to_u32(),
+            worker_id,
             reason: result.as_ref().map(|v| *v).map_err(|err| err.to_string()),
         },
     )?;
@@ -117,12 +147,17 @@ fn background_worker_subscriber_main_internal<const N: usize>(
     db_oid: u32,
     db_name: &str,
     dsmh: DsmHandle,
+    (worker_id, mut partition): (usize, WorkerPartition),
 ) -> anyhow::Result<()> {
     let status = check_extension_status(fdw_extension_name);
 
     send_message_to_launcher(
         launcher_bus,
-        LauncherMessage::DbExtensionStatus { db_oid, status },
+        LauncherMessage::DbExtensionStatus {
+            db_oid,
+            worker_id,
+            status,
+        },
     )?;
 
     if status != ExtensionStatus::Exist {
@@ -145,9 +180,36 @@ fn background_worker_subscriber_main_internal<const N: usize>(
 
     let config = BackgroundWorker::transaction(|| fetch_config(fdw_extension_name));
 
-    let nats = rt.block_on(NatsConnectionState::new(&config.nats_opt))?;
+    if partition.count == 0 {
+        send_message_to_launcher(
+            launcher_bus,
+            LauncherMessage::PoolSize {
+                db_oid,
+                workers: config.workers,
+            },
+        )?;
 
-    let mut ctx = SubscriberContext::new(rt, msg_sender.clone(), nats, config);
+        if config.workers > 1 {
+            log!(
+                context = db_name,
+                "Exiting so that the launcher starts a pool of {} subscriber workers",
+                config.workers
+            );
+            return Ok(());
+        }
+
+        partition.count = 1;
+    }
+
+    let callback_queue = Arc::new(BoundedQueue::new(config.queue));
+    let mut dropped_messages = 0;
+
+    let nats = rt.block_on(NatsConnectionState::new(
+        &config.nats_opt,
+        callback_queue.clone(),
+    ))?;
+
+    let mut ctx = SubscriberContext::new(rt, msg_sender.clone(), nats, config, partition);
     if let Err(err) = ctx.send_notification() {
         warn!(
             context = db_name,
@@ -169,7 +231,9 @@ fn background_worker_subscriber_main_internal<const N: usize>(
         }
     }
 
-    'bg_loop: while BackgroundWorker::wait_latch(Some(std::time::Duration::from_secs(1))) {
+    let mut wait_timeout = POLL_INTERVAL;
+
+    'bg_loop: while BackgroundWorker::wait_latch(Some(wait_timeout)) {
         let status = check_extension_status(fdw_extension_name);
 
         match status {
@@ -205,7 +269,15 @@ fn background_worker_subscriber_main_internal<const N: usize>(
             }
         }
 
-        while let Ok(message) = msg_receiver.try_recv() {
+        // Control messages go first, then the messages waiting for callbacks. At most a
+        // queue's worth of them is handled per iteration, so that launcher messages, batch
+        // deadlines and the status are still served under a steady stream of messages.
+        let mut callbacks_left = callback_queue.limit();
+
+        while let Some(message) = msg_receiver.try_recv().ok().or_else(|| {
+            callbacks_left = callbacks_left.checked_sub(1)?;
+            callback_queue.pop()
+        }) {
             if ctx.is_replica() {
                 debug!("Received internal message on replica. Ignoring.");
                 continue;
@@ -213,6 +285,38 @@ fn background_worker_subscriber_main_internal<const N: usize>(
 
             handle_internal_message(&mut ctx, message, sub_table_name, db_name);
         }
+
+        if ctx.is_master() {
+            ctx.handle_batch_flush(db_name, call_subscription_function);
+        }
+
+        // Wake up in time to deliver a batch whose linger time runs out before the next poll,
+        // or right away if callbacks are still waiting.
+        wait_timeout = if callback_queue.len() > 0 {
+            Duration::ZERO
+        } else {
+            ctx.batch_wait_timeout(POLL_INTERVAL)
+        };
+
+        if let Err(err) = ctx.publish_status(db_oid) {
+            debug!(
+                context = db_name,
+                "Failed to publish subscription status: {}", err
+            );
+        }
+
+        let dropped = callback_queue.dropped();
+
+        if dropped > dropped_messages {
+            warn!(
+                context = db_name,
+                "Callback queue is full ({} messages): dropped {} messages ({} in total)",
+                callback_queue.len(),
+                dropped - dropped_messages,
+                dropped
+            );
+            dropped_messages = dropped;
+        }
     }
 
     log!(context = db_name, "END");
@@ -258,16 +362,28 @@ fn handle_message_from_shared_queue(
                 );
             }
         }
-        SubscriberMessage::Subscribe { subject, fn_name } => {
+        SubscriberMessage::Subscribe {
+            subject,
+            fn_name,
+            queue_group,
+            retry,
+            batch,
+        } => {
             debug!(
                 context = db_name,
-                "Handling Subscribe for subject '{}', fn '{}'", subject, fn_name
+                "Handling Subscribe for subject '{}', fn '{}', queue group {:?}",
+                subject,
+                fn_name,
+                queue_group
             );
 
             let _ = sender.send(InternalWorkerMessage::Subscribe {
                 register: true,
                 subject: subject.to_string(),
                 fn_name: fn_name.to_string(),
+                queue_group,
+                retry,
+                batch,
             });
         }
         SubscriberMessage::Unsubscribe { subject, fn_name } => {
@@ -281,6 +397,200 @@ fn handle_message_from_shared_queue(
                 fn_name: Arc::from(fn_name.as_str()),
             });
         }
+        SubscriberMessage::KvWatch {
+            bucket,
+            key_pattern,
+            fn_name,
+        } => {
+            debug!(
+                context = db_name,
+                "Handling KvWatch for bucket '{}', key pattern '{}', fn '{}'",
+                bucket,
+                key_pattern,
+                fn_name
+            );
+
+            let _ = sender.send(InternalWorkerMessage::KvWatch {
+                register: true,
+                bucket,
+                key_pattern,
+                fn_name,
+            });
+        }
+        SubscriberMessage::KvUnwatch {
+            bucket,
+            key_pattern,
+            fn_name,
+        } => {
+            debug!(
+                context = db_name,
+                "Handling KvUnwatch for bucket '{}', key pattern '{}', fn '{}'",
+                bucket,
+                key_pattern,
+                fn_name
+            );
+
+            let _ = sender.send(InternalWorkerMessage::KvUnwatch {
+                bucket: Arc::from(bucket.as_str()),
+                key_pattern: Arc::from(key_pattern.as_str()),
+                fn_name: Arc::from(fn_name.as_str()),
+            });
+        }
+        SubscriberMessage::ObjectWatch { store, fn_name } => {
+            debug!(
+                context = db_name,
+                "Handling ObjectWatch for store '{}', fn '{}'", store, fn_name
+            );
+
+            let _ = sender.send(InternalWorkerMessage::ObjectWatch {
+                register: true,
+                store,
+                fn_name,
+            });
+        }
+        SubscriberMessage::ObjectUnwatch { store, fn_name } => {
+            debug!(
+                context = db_name,
+                "Handling ObjectUnwatch for store '{}', fn '{}'", store, fn_name
+            );
+
+            let _ = sender.send(InternalWorkerMessage::ObjectUnwatch {
+                store: Arc::from(store.as_str()),
+                fn_name: Arc::from(fn_name.as_str()),
+            });
+        }
+        SubscriberMessage::StreamSubscribe {
+            stream,
+            consumer,
+            fn_name,
+            retry,
+        } => {
+            debug!(
+                context = db_name,
+                "Handling StreamSubscribe for stream '{}', consumer '{}', fn '{}'",
+                stream,
+                consumer,
+                fn_name
+            );
+
+            let _ = sender.send(InternalWorkerMessage::StreamSubscribe {
+                register: true,
+                stream,
+                consumer,
+                fn_name,
+                retry,
+            });
+        }
+        SubscriberMessage::StreamUnsubscribe {
+            stream,
+            consumer,
+            fn_name,
+        } => {
+            debug!(
+                context = db_name,
+                "Handling StreamUnsubscribe for stream '{}', consumer '{}', fn '{}'",
+                stream,
+                consumer,
+                fn_name
+            );
+
+            let _ = sender.send(InternalWorkerMessage::StreamUnsubscribe {
+                stream: Arc::from(stream.as_str()),
+                consumer: Arc::from(consumer.as_str()),
+                fn_name: Arc::from(fn_name.as_str()),
+            });
+        }
+        SubscriberMessage::Respond {
+            subject,
+            fn_name,
+            queue_group,
+        } => {
+            debug!(
+                context = db_name,
+                "Handling Respond for subject '{}', fn '{}', queue group {:?}",
+                subject,
+                fn_name,
+                queue_group
+            );
+
+            let _ = sender.send(InternalWorkerMessage::Respond {
+                register: true,
+                subject,
+                fn_name,
+                queue_group,
+            });
+        }
+        SubscriberMessage::Unrespond { subject } => {
+            debug!(
+                context = db_name,
+                "Handling Unrespond for subject '{}'", subject
+            );
+
+            let _ = sender.send(InternalWorkerMessage::Unrespond {
+                subject: Arc::from(subject.as_str()),
+            });
+        }
+        SubscriberMessage::ServiceAdd {
+            name,
+            version,
+            description,
+        } => {
+            debug!(
+                context = db_name,
+                "Handling ServiceAdd for service '{}', version '{}'", name, version
+            );
+
+            let _ = sender.send(InternalWorkerMessage::ServiceAdd {
+                register: true,
+                name,
+                version,
+                description,
+            });
+        }
+        SubscriberMessage::ServiceRemove { name } => {
+            debug!(
+                context = db_name,
+                "Handling ServiceRemove for service '{}'", name
+            );
+
+            let _ = sender.send(InternalWorkerMessage::ServiceRemove {
+                name: Arc::from(name.as_str()),
+            });
+        }
+        SubscriberMessage::ServiceEndpointAdd {
+            service,
+            endpoint,
+            subject,
+            fn_name,
+        } => {
+            debug!(
+                context = db_name,
+                "Handling ServiceEndpointAdd for service '{}', endpoint '{}', subject '{}', fn '{}'",
+                service,
+                endpoint,
+                subject,
+                fn_name
+            );
+
+            let _ = sender.send(InternalWorkerMessage::ServiceEndpointAdd {
+                register: true,
+                service,
+                endpoint,
+                subject,
+                fn_name,
+            });
+        }
+        SubscriberMessage::ServiceEndpointRemove { service, endpoint } => {
+            debug!(
+                context = db_name,
+                "Handling ServiceEndpointRemove for service '{}', endpoint '{}'", service, endpoint
+            );
+
+            let _ = sender.send(InternalWorkerMessage::ServiceEndpointRemove {
+                service: Arc::from(service.as_str()),
+                endpoint: Arc::from(endpoint.as_str()),
+            });
+        }
         #[cfg(any(test, feature = "pg_test"))]
         SubscriberMessage::ChangeStatus { is_master } => {
             if is_master {
@@ -303,15 +613,28 @@ fn handle_internal_message(
             register,
             subject,
             fn_name,
+            queue_group,
+            retry,
+            batch,
         } => {
             debug!(
                 context = db_name,
-                "Received subscription request: subject='{}', fn='{}'", subject, fn_name
+                "Received subscription request: subject='{}', fn='{}', queue_group={:?}",
+                subject,
+                fn_name,
+                queue_group
             );
 
             if register {
                 if let Err(error) = BackgroundWorker::transaction(|| {
-                    insert_subject_callback(subscriptions_table_name, &subject, &fn_name)
+                    insert_subject_callback(
+                        subscriptions_table_name,
+                        &subject,
+                        &fn_name,
+                        queue_group.as_deref(),
+                        &retry,
+                        batch,
+                    )
                 }) {
                     warn!(
                         context = db_name,
@@ -328,7 +651,20 @@ fn handle_internal_message(
                 }
             }
 
-            ctx.handle_subscribe(Arc::from(subject), Arc::from(fn_name));
+            if let Err(error) = ctx.handle_subscribe(
+                SubscriptionKey {
+                    subject: Arc::from(subject.as_str()),
+                    queue_group: queue_group.map(Arc::from),
+                },
+                Arc::from(fn_name.as_str()),
+                retry,
+                batch,
+            ) {
+                warn!(
+                    context = db_name,
+                    "Failed to subscribe: subject='{}', callback='{}': {}", subject, fn_name, error
+                );
+            }
         }
         InternalWorkerMessage::Unsubscribe { subject, fn_name } => {
             debug!(
@@ -355,26 +691,684 @@ fn handle_internal_message(
 
             ctx.handle_unsubscribe(subject, fn_name);
         }
-        InternalWorkerMessage::CallbackCall { subject, data } => {
+        InternalWorkerMessage::CallbackCall {
+            subject,
+            queue_group,
+            message,
+        } => {
             debug!(
                 context = db_name,
                 "Dispatching callbacks for subject '{}'", subject
             );
 
-            ctx.handle_callback(&subject, data, db_name, |callback, data| {
-                BackgroundWorker::transaction(|| call_function(callback, data))
-            });
+            ctx.handle_callback(
+                &SubscriptionKey {
+                    subject,
+                    queue_group,
+                },
+                &message,
+                db_name,
+                call_subscription_function,
+            );
         }
-        InternalWorkerMessage::UnsubscribeSubject { subject, reason } => {
+        InternalWorkerMessage::CallbackRetry {
+            subject,
+            fn_name,
+            messages,
+            attempt,
+        } => {
+            debug!(
+                context = db_name,
+                "Retrying callback '{}' for subject '{}' (attempt {})", fn_name, subject, attempt
+            );
+
+            ctx.handle_callback_retry(
+                &subject,
+                fn_name,
+                &messages,
+                attempt,
+                db_name,
+                call_subscription_function,
+            );
+        }
+        InternalWorkerMessage::UnsubscribeSubject {
+            subject,
+            queue_group,
+            reason,
+        } => {
             warn!(
                 context = db_name,
                 "Unsubscribing subject due to: {}", reason
             );
-            ctx.handle_unsubscribe_subject(&subject)
+            ctx.handle_unsubscribe_subject(&SubscriptionKey {
+                subject,
+                queue_group,
+            })
+        }
+        InternalWorkerMessage::KvWatch {
+            register,
+            bucket,
+            key_pattern,
+            fn_name,
+        } => {
+            debug!(
+                context = db_name,
+                "Received KV watch request: bucket='{}', key_pattern='{}', fn='{}'",
+                bucket,
+                key_pattern,
+                fn_name
+            );
+
+            if register {
+                if let Err(error) = BackgroundWorker::transaction(|| {
+                    insert_kv_watch(KV_WATCHES_TABLE_NAME, &bucket, &key_pattern, &fn_name)
+                }) {
+                    warn!(
+                        context = db_name,
+                        "Failed to register KV watch in catalog: bucket='{}', key_pattern='{}', callback='{}': {}",
+                        bucket,
+                        key_pattern,
+                        fn_name,
+                        error
+                    );
+                } else {
+                    debug!(
+                        context = db_name,
+                        "Inserted KV watch: bucket='{}', key_pattern='{}', callback='{}'",
+                        bucket,
+                        key_pattern,
+                        fn_name
+                    );
+                }
+            }
+
+            ctx.handle_kv_watch(
+                KvWatchKey {
+                    bucket: Arc::from(bucket),
+                    key_pattern: Arc::from(key_pattern),
+                },
+                Arc::from(fn_name),
+            );
+        }
+        InternalWorkerMessage::KvUnwatch {
+            bucket,
+            key_pattern,
+            fn_name,
+        } => {
+            debug!(
+                context = db_name,
+                "Received KV unwatch request: bucket='{}', key_pattern='{}', fn='{}'",
+                bucket,
+                key_pattern,
+                fn_name
+            );
+
+            if let Err(error) = BackgroundWorker::transaction(|| {
+                delete_kv_watch(KV_WATCHES_TABLE_NAME, &bucket, &key_pattern, &fn_name)
+            }) {
+                warn!(
+                    context = db_name,
+                    "Failed to remove KV watch from catalog: bucket='{}', key_pattern='{}', callback='{}': {}",
+                    bucket,
+                    key_pattern,
+                    fn_name,
+                    error
+                );
+            } else {
+                debug!(
+                    context = db_name,
+                    "Deleted KV watch: bucket='{}', key_pattern='{}', callback='{}'",
+                    bucket,
+                    key_pattern,
+                    fn_name
+                );
+            }
+
+            ctx.handle_kv_unwatch(
+                KvWatchKey {
+                    bucket,
+                    key_pattern,
+                },
+                fn_name,
+            );
+        }
+        InternalWorkerMessage::KvWatchCall {
+            bucket,
+            key_pattern,
+            entry,
+        } => {
+            debug!(
+                context = db_name,
+                "Dispatching KV watch callbacks for bucket '{}', key '{}'", bucket, entry.key
+            );
+
+            let key = KvWatchKey {
+                bucket,
+                key_pattern,
+            };
+
+            ctx.handle_kv_watch_callback(&key, db_name, |callback| {
+                BackgroundWorker::transaction(|| {
+                    call_kv_watch_function(
+                        KV_WATCHES_TABLE_NAME,
+                        &key.bucket,
+                        &key.key_pattern,
+                        callback,
+                        &entry,
+                    )
+                })
+            });
+        }
+        InternalWorkerMessage::KvUnwatchPattern {
+            bucket,
+            key_pattern,
+            reason,
+        } => {
+            warn!(
+                context = db_name,
+                "Stopping KV watch on bucket '{}' due to: {}", bucket, reason
+            );
+            ctx.handle_kv_unwatch_pattern(&KvWatchKey {
+                bucket,
+                key_pattern,
+            })
+        }
+        InternalWorkerMessage::ObjectWatch {
+            register,
+            store,
+            fn_name,
+        } => {
+            debug!(
+                context = db_name,
+                "Received object watch request: store='{}', fn='{}'", store, fn_name
+            );
+
+            if register {
+                if let Err(error) = BackgroundWorker::transaction(|| {
+                    insert_object_watch(OBJECT_WATCHES_TABLE_NAME, &store, &fn_name)
+                }) {
+                    warn!(
+                        context = db_name,
+                        "Failed to register object watch in catalog: store='{}', callback='{}': {}",
+                        store,
+                        fn_name,
+                        error
+                    );
+                } else {
+                    debug!(
+                        context = db_name,
+                        "Inserted object watch: store='{}', callback='{}'", store, fn_name
+                    );
+                }
+            }
+
+            ctx.handle_object_watch(Arc::from(store), Arc::from(fn_name));
+        }
+        InternalWorkerMessage::ObjectUnwatch { store, fn_name } => {
+            debug!(
+                context = db_name,
+                "Received object unwatch request: store='{}', fn='{}'", store, fn_name
+            );
+
+            if let Err(error) = BackgroundWorker::transaction(|| {
+                delete_object_watch(OBJECT_WATCHES_TABLE_NAME, &store, &fn_name)
+            }) {
+                warn!(
+                    context = db_name,
+                    "Failed to remove object watch from catalog: store='{}', callback='{}': {}",
+                    store,
+                    fn_name,
+                    error
+                );
+            } else {
+                debug!(
+                    context = db_name,
+                    "Deleted object watch: store='{}', callback='{}'", store, fn_name
+                );
+            }
+
+            ctx.handle_object_unwatch(store, fn_name);
+        }
+        InternalWorkerMessage::ObjectWatchCall { store, entry } => {
+            debug!(
+                context = db_name,
+                "Dispatching object watch callbacks for store '{}', object '{}'", store, entry.name
+            );
+
+            ctx.handle_object_watch_callback(&store, db_name, |callback| {
+                BackgroundWorker::transaction(|| {
+                    call_object_watch_function(OBJECT_WATCHES_TABLE_NAME, &store, callback, &entry)
+                })
+            });
+        }
+        InternalWorkerMessage::ObjectUnwatchStore { store, reason } => {
+            warn!(
+                context = db_name,
+                "Stopping object watch on store '{}' due to: {}", store, reason
+            );
+            ctx.handle_object_unwatch_store(&store)
+        }
+        InternalWorkerMessage::StreamSubscribe {
+            register,
+            stream,
+            consumer,
+            fn_name,
+            retry,
+        } => {
+            debug!(
+                context = db_name,
+                "Received stream subscription request: stream='{}', consumer='{}', fn='{}'",
+                stream,
+                consumer,
+                fn_name
+            );
+
+            if register {
+                if let Err(error) = BackgroundWorker::transaction(|| {
+                    insert_stream_subscription(
+                        STREAM_SUBSCRIPTIONS_TABLE_NAME,
+                        &stream,
+                        &consumer,
+                        &fn_name,
+                        &retry,
+                    )
+                }) {
+                    warn!(
+                        context = db_name,
+                        "Failed to register stream subscription in catalog: stream='{}', consumer='{}', callback='{}': {}",
+                        stream,
+                        consumer,
+                        fn_name,
+                        error
+                    );
+                } else {
+                    debug!(
+                        context = db_name,
+                        "Inserted stream subscription: stream='{}', consumer='{}', callback='{}'",
+                        stream,
+                        consumer,
+                        fn_name
+                    );
+                }
+            }
+
+            if let Err(error) = ctx.handle_stream_subscribe(
+                StreamSubscriptionKey {
+                    stream: Arc::from(stream.as_str()),
+                    consumer: Arc::from(consumer.as_str()),
+                },
+                Arc::from(fn_name.as_str()),
+                retry,
+            ) {
+                warn!(
+                    context = db_name,
+                    "Failed to subscribe to stream: stream='{}', consumer='{}', callback='{}': {}",
+                    stream,
+                    consumer,
+                    fn_name,
+                    error
+                );
+            }
+        }
+        InternalWorkerMessage::StreamUnsubscribe {
+            stream,
+            consumer,
+            fn_name,
+        } => {
+            debug!(
+                context = db_name,
+                "Received stream unsubscription request: stream='{}', consumer='{}', fn='{}'",
+                stream,
+                consumer,
+                fn_name
+            );
+
+            if let Err(error) = BackgroundWorker::transaction(|| {
+                delete_stream_subscription(
+                    STREAM_SUBSCRIPTIONS_TABLE_NAME,
+                    &stream,
+                    &consumer,
+                    &fn_name,
+                )
+            }) {
+                warn!(
+                    context = db_name,
+                    "Failed to remove stream subscription from catalog: stream='{}', consumer='{}', callback='{}': {}",
+                    stream,
+                    consumer,
+                    fn_name,
+                    error
+                );
+            } else {
+                debug!(
+                    context = db_name,
+                    "Deleted stream subscription: stream='{}', consumer='{}', callback='{}'",
+                    stream,
+                    consumer,
+                    fn_name
+                );
+            }
+
+            ctx.handle_stream_unsubscribe(StreamSubscriptionKey { stream, consumer }, fn_name);
+        }
+        InternalWorkerMessage::StreamCallbackCall {
+            stream,
+            consumer,
+            message,
+            delivery,
+        } => {
+            debug!(
+                context = db_name,
+                "Dispatching stream callbacks for stream '{}', consumer '{}'", stream, consumer
+            );
+
+            if let Err(err) = ctx.handle_stream_callback(
+                &StreamSubscriptionKey { stream, consumer },
+                &message,
+                delivery,
+                db_name,
+                |callback, signature, message| {
+                    call_subscription_function(callback, signature, std::slice::from_ref(message))
+                },
+            ) {
+                warn!(
+                    context = db_name,
+                    "Failed to acknowledge stream message: {}", err
+                );
+            }
+        }
+        InternalWorkerMessage::StreamUnsubscribeConsumer {
+            stream,
+            consumer,
+            reason,
+        } => {
+            warn!(
+                context = db_name,
+                "Stopping subscription to stream '{}', consumer '{}' due to: {}",
+                stream,
+                consumer,
+                reason
+            );
+            ctx.handle_stream_unsubscribe_consumer(&StreamSubscriptionKey { stream, consumer })
+        }
+        InternalWorkerMessage::Respond {
+            register,
+            subject,
+            fn_name,
+            queue_group,
+        } => {
+            debug!(
+                context = db_name,
+                "Received responder request: subject='{}', fn='{}', queue_group={:?}",
+                subject,
+                fn_name,
+                queue_group
+            );
+
+            if register {
+                if let Err(error) = BackgroundWorker::transaction(|| {
+                    insert_responder(
+                        RESPONDERS_TABLE_NAME,
+                        &subject,
+                        &fn_name,
+                        queue_group.as_deref(),
+                    )
+                }) {
+                    warn!(
+                        context = db_name,
+                        "Failed to register responder in catalog: subject='{}', callback='{}': {}",
+                        subject,
+                        fn_name,
+                        error
+                    );
+                } else {
+                    debug!(
+                        context = db_name,
+                        "Inserted responder: subject='{}', callback='{}'", subject, fn_name
+                    );
+                }
+            }
+
+            if let Err(error) = ctx.handle_respond(
+                Arc::from(subject.as_str()),
+                Arc::from(fn_name.as_str()),
+                queue_group.map(Arc::from),
+            ) {
+                warn!(
+                    context = db_name,
+                    "Failed to register responder: subject='{}', callback='{}': {}",
+                    subject,
+                    fn_name,
+                    error
+                );
+            }
+        }
+        InternalWorkerMessage::Unrespond { subject } => {
+            debug!(
+                context = db_name,
+                "Received responder removal request: subject='{}'", subject
+            );
+
+            if let Err(error) =
+                BackgroundWorker::transaction(|| delete_responder(RESPONDERS_TABLE_NAME, &subject))
+            {
+                warn!(
+                    context = db_name,
+                    "Failed to remove responder from catalog: subject='{}': {}", subject, error
+                );
+            } else {
+                debug!(
+                    context = db_name,
+                    "Deleted responder: subject='{}'", subject
+                );
+            }
+
+            ctx.handle_unrespond(&subject);
+        }
+        InternalWorkerMessage::ResponderCall { subject, message } => {
+            debug!(
+                context = db_name,
+                "Dispatching request on subject '{}'", subject
+            );
+
+            if let Err(err) = ctx.handle_responder_call(
+                &subject,
+                &message,
+                db_name,
+                |callback, function, message| {
+                    BackgroundWorker::transaction(|| call_responder(callback, function, message))
+                },
+            ) {
+                warn!(context = db_name, "Failed to send response: {}", err);
+            }
+        }
+        InternalWorkerMessage::UnrespondSubject { subject, reason } => {
+            warn!(
+                context = db_name,
+                "Stopping responder on subject '{}' due to: {}", subject, reason
+            );
+            ctx.handle_unrespond(&subject)
+        }
+        InternalWorkerMessage::ServiceAdd {
+            register,
+            name,
+            version,
+            description,
+        } => {
+            debug!(
+                context = db_name,
+                "Received service registration: name='{}', version='{}'", name, version
+            );
+
+            if register {
+                if let Err(error) = BackgroundWorker::transaction(|| {
+                    insert_service(SERVICES_TABLE_NAME, &name, &version, description.as_deref())
+                }) {
+                    warn!(
+                        context = db_name,
+                        "Failed to register service in catalog: name='{}': {}", name, error
+                    );
+                } else {
+                    debug!(
+                        context = db_name,
+                        "Inserted service: name='{}', version='{}'", name, version
+                    );
+                }
+            }
+
+            if let Err(error) =
+                ctx.handle_service_add(Arc::from(name.as_str()), version, description)
+            {
+                warn!(
+                    context = db_name,
+                    "Failed to start service: name='{}': {}", name, error
+                );
+            }
+        }
+        InternalWorkerMessage::ServiceRemove { name } => {
+            debug!(
+                context = db_name,
+                "Received service removal: name='{}'", name
+            );
+
+            if let Err(error) =
+                BackgroundWorker::transaction(|| delete_service(SERVICES_TABLE_NAME, &name))
+            {
+                warn!(
+                    context = db_name,
+                    "Failed to remove service from catalog: name='{}': {}", name, error
+                );
+            } else {
+                debug!(context = db_name, "Deleted service: name='{}'", name);
+            }
+
+            ctx.handle_service_remove(&name);
+        }
+        InternalWorkerMessage::ServiceEndpointAdd {
+            register,
+            service,
+            endpoint,
+            subject,
+            fn_name,
+        } => {
+            debug!(
+                context = db_name,
+                "Received service endpoint request: service='{}', endpoint='{}', subject='{}', fn='{}'",
+                service,
+                endpoint,
+                subject,
+                fn_name
+            );
+
+            if register {
+                if let Err(error) = BackgroundWorker::transaction(|| {
+                    insert_service_endpoint(
+                        SERVICE_ENDPOINTS_TABLE_NAME,
+                        &service,
+                        &endpoint,
+                        &subject,
+                        &fn_name,
+                    )
+                }) {
+                    warn!(
+                        context = db_name,
+                        "Failed to register service endpoint in catalog: service='{}', endpoint='{}', callback='{}': {}",
+                        service,
+                        endpoint,
+                        fn_name,
+                        error
+                    );
+                } else {
+                    debug!(
+                        context = db_name,
+                        "Inserted service endpoint: service='{}', endpoint='{}', callback='{}'",
+                        service,
+                        endpoint,
+                        fn_name
+                    );
+                }
+            }
+
+            if let Err(error) = ctx.handle_service_endpoint_add(
+                Arc::from(service.as_str()),
+                Arc::from(endpoint.as_str()),
+                subject,
+                Arc::from(fn_name.as_str()),
+            ) {
+                warn!(
+                    context = db_name,
+                    "Failed to add service endpoint: service='{}', endpoint='{}', callback='{}': {}",
+                    service,
+                    endpoint,
+                    fn_name,
+                    error
+                );
+            }
+        }
+        InternalWorkerMessage::ServiceEndpointRemove { service, endpoint } => {
+            debug!(
+                context = db_name,
+                "Received service endpoint removal: service='{}', endpoint='{}'", service, endpoint
+            );
+
+            if let Err(error) = BackgroundWorker::transaction(|| {
+                delete_service_endpoint(SERVICE_ENDPOINTS_TABLE_NAME, &service, &endpoint)
+            }) {
+                warn!(
+                    context = db_name,
+                    "Failed to remove service endpoint from catalog: service='{}', endpoint='{}': {}",
+                    service,
+                    endpoint,
+                    error
+                );
+            } else {
+                debug!(
+                    context = db_name,
+                    "Deleted service endpoint: service='{}', endpoint='{}'", service, endpoint
+                );
+            }
+
+            ctx.handle_service_endpoint_remove(&service, &endpoint);
+        }
+        InternalWorkerMessage::ServiceRequest {
+            service,
+            endpoint,
+            message,
+            request,
+        } => {
+            debug!(
+                context = db_name,
+                "Dispatching request for service '{}', endpoint '{}'", service, endpoint
+            );
+
+            if let Err(err) = ctx.handle_service_request(
+                &service,
+                &endpoint,
+                &message,
+                request,
+                db_name,
+                |callback, function, message| {
+                    BackgroundWorker::transaction(|| call_responder(callback, function, message))
+                },
+            ) {
+                warn!(
+                    context = db_name,
+                    "Failed to answer service request: {}", err
+                );
+            }
         }
     }
 }
 
+/// Calls a subscription callback for one message, or a batch of them, in a transaction.
+fn call_subscription_function(
+    callback: &str,
+    signature: CallbackSignature,
+    messages: &[CallbackMessage],
+) -> Result<(), CallError> {
+    BackgroundWorker::transaction(|| call_function(callback, signature, messages))
+}
+
 fn check_extension_status(fdw_extension_name: &str) -> ExtensionStatus {
     let is_installed = BackgroundWorker::transaction(|| is_extension_installed(EXTENSION_NAME));
 
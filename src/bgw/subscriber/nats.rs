@@ -1,14 +1,28 @@
 use std::{
     collections::{hash_map::Entry, HashMap, HashSet},
     sync::{mpsc::Sender, Arc},
+    time::Duration,
 };
 
+use async_nats::{
+    jetstream::{
+        consumer::pull::{self, MessagesErrorKind},
+        AckKind,
+    },
+    service::{endpoint::Endpoint, Service, ServiceExt},
+};
+use futures::future::{self, Either};
 use tokio::task::JoinHandle;
 use tokio_stream::StreamExt;
 
 use crate::{
-    bgw::subscriber::{pg_api::CallError, InternalWorkerMessage},
-    config::{NatsConnectionOptions, NatsTlsOptions},
+    bgw::subscriber::{
+        pg_api::{CallError, CallbackMessage, KvWatchEntry, ObjectWatchEntry},
+        queue::BoundedQueue,
+        InternalWorkerMessage,
+    },
+    config::{CallbackQueueOptions, NatsConnectionOptions, NatsTlsOptions},
+    utils::{extract_headers, headers_to_json, kv_operation_name},
     warn,
 };
 
@@ -17,28 +31,136 @@ pub(super) struct NatsSubscription {
     funcs: HashSet<Arc<str>>,
 }
 
+pub(super) struct NatsStreamSubscription {
+    subscription: NatsSubscription,
+    /// Delivery limit of the durable consumer, applied when the consumer is created.
+    max_deliver: i64,
+}
+
+pub(super) struct NatsResponder {
+    handler: JoinHandle<()>,
+    fn_name: Arc<str>,
+    queue_group: Option<Arc<str>>,
+}
+
+/// A NATS micro service hosted by the worker. Endpoints are added to and removed from the
+/// running instance; a new version or description starts a new instance of the service.
+pub(super) struct NatsService {
+    version: String,
+    description: Option<String>,
+    endpoints: HashMap<Arc<str>, NatsServiceEndpoint>,
+    running: Option<RunningService>,
+}
+
+struct NatsServiceEndpoint {
+    subject: String,
+    fn_name: Arc<str>,
+}
+
+struct RunningService {
+    service: Service,
+    endpoints: HashMap<Arc<str>, RunningEndpoint>,
+}
+
+struct RunningEndpoint {
+    stop: tokio::sync::oneshot::Sender<()>,
+    handler: JoinHandle<()>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(super) struct SubscriptionKey {
+    pub(super) subject: Arc<str>,
+    pub(super) queue_group: Option<Arc<str>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(super) struct KvWatchKey {
+    pub(super) bucket: Arc<str>,
+    pub(super) key_pattern: Arc<str>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(super) struct StreamSubscriptionKey {
+    pub(super) stream: Arc<str>,
+    pub(super) consumer: Arc<str>,
+}
+
+/// How a stream message was delivered to the worker, used to settle it.
+pub(super) struct StreamDelivery {
+    pub(super) ack_subject: String,
+    pub(super) stream_sequence: u64,
+    pub(super) delivered: i64,
+}
+
+/// Delay before the first redelivery of a message whose callback failed.
+/// Doubles with every further delivery attempt, up to [`RETRY_MAX_DELAY`].
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(300);
+
+/// Headers describing why a message was published to a dead-letter subject.
+const DEAD_LETTER_SUBJECT_HEADER: &str = "Pgnats-Subject";
+const DEAD_LETTER_CALLBACK_HEADER: &str = "Pgnats-Callback";
+const DEAD_LETTER_ERROR_HEADER: &str = "Pgnats-Error";
+const DEAD_LETTER_ATTEMPTS_HEADER: &str = "Pgnats-Attempts";
+
+/// Headers carrying the error of a failed request, as used by NATS services.
+pub(super) const SERVICE_ERROR_HEADER: &str = "Nats-Service-Error";
+pub(super) const SERVICE_ERROR_CODE_HEADER: &str = "Nats-Service-Error-Code";
+
 pub(super) struct NatsConnectionState {
     client: async_nats::Client,
-    subscriptions: HashMap<Arc<str>, NatsSubscription>,
+    subscriptions: HashMap<SubscriptionKey, NatsSubscription>,
+    kv_watches: HashMap<KvWatchKey, NatsSubscription>,
+    object_watches: HashMap<Arc<str>, NatsSubscription>,
+    stream_subscriptions: HashMap<StreamSubscriptionKey, NatsStreamSubscription>,
+    responders: HashMap<Arc<str>, NatsResponder>,
+    services: HashMap<Arc<str>, NatsService>,
+    callback_queue: Arc<BoundedQueue<InternalWorkerMessage>>,
 }
 
 impl NatsConnectionState {
-    pub(super) async fn new(config: &NatsConnectionOptions) -> anyhow::Result<Self> {
+    pub(super) async fn new(
+        config: &NatsConnectionOptions,
+        callback_queue: Arc<BoundedQueue<InternalWorkerMessage>>,
+    ) -> anyhow::Result<Self> {
         let client = Self::connect_nats(config).await?;
         Ok(Self {
             client,
+            callback_queue,
             subscriptions: HashMap::new(),
+            kv_watches: HashMap::new(),
+            object_watches: HashMap::new(),
+            stream_subscriptions: HashMap::new(),
+            responders: HashMap::new(),
+            services: HashMap::new(),
         })
     }
 
     pub(super) fn subscribe(
         &mut self,
-        subject: Arc<str>,
+        key: SubscriptionKey,
         fn_name: Arc<str>,
         rt: &tokio::runtime::Runtime,
         sender: Sender<InternalWorkerMessage>,
     ) {
-        match self.subscriptions.entry(subject.clone()) {
+        // A function is subscribed to a subject at most once, so subscribing it again
+        // with another queue group moves it to that group.
+        self.subscriptions.retain(|k, sub| {
+            if k.subject != key.subject || k.queue_group == key.queue_group {
+                return true;
+            }
+
+            let _ = sub.funcs.remove(&fn_name);
+
+            if sub.funcs.is_empty() {
+                sub.handler.abort();
+                false
+            } else {
+                true
+            }
+        });
+
+        match self.subscriptions.entry(key.clone()) {
             // Subject already exists; update or add the function handler
             Entry::Occupied(mut s) => {
                 let _ = s.get_mut().funcs.insert(fn_name);
@@ -46,8 +168,13 @@ impl NatsConnectionState {
             // First time subscribing to this subject
             Entry::Vacant(se) => {
                 // Spawn a new handler task for the function
-                let handler =
-                    Self::spawn_subscription_task(self.client.clone(), rt, sender, subject.clone());
+                let handler = Self::spawn_subscription_task(
+                    self.client.clone(),
+                    rt,
+                    sender,
+                    self.callback_queue.clone(),
+                    key,
+                );
 
                 let _ = se.insert(NatsSubscription {
                     handler,
@@ -58,23 +185,29 @@ impl NatsConnectionState {
     }
 
     pub(super) fn unsubscribe(&mut self, subject: Arc<str>, fn_name: Arc<str>) {
-        if let Entry::Occupied(mut e) = self.subscriptions.entry(subject.clone()) {
-            let _ = e.get_mut().funcs.remove(&fn_name);
+        self.subscriptions.retain(|key, sub| {
+            if key.subject != subject {
+                return true;
+            }
 
-            if e.get().funcs.is_empty() {
-                let sub = e.remove();
+            let _ = sub.funcs.remove(&fn_name);
+
+            if sub.funcs.is_empty() {
                 sub.handler.abort();
+                false
+            } else {
+                true
             }
-        }
+        });
     }
 
-    pub(super) fn unsubscribe_subject(&mut self, subject: &str) {
-        if let Some(sub) = self.subscriptions.remove(subject) {
+    pub(super) fn unsubscribe_subject(&mut self, key: &SubscriptionKey) {
+        if let Some(sub) = self.subscriptions.remove(key) {
             sub.handler.abort();
         }
     }
 
-    pub(super) fn unsubscribe_all(&mut self) -> HashMap<Arc<str>, NatsSubscription> {
+    pub(super) fn unsubscribe_all(&mut self) -> HashMap<SubscriptionKey, NatsSubscription> {
         let subs = std::mem::take(&mut self.subscriptions);
         for sub in subs.values() {
             sub.handler.abort();
@@ -83,37 +216,504 @@ impl NatsConnectionState {
         subs
     }
 
-    pub(super) fn run_callbacks(
+    pub(super) fn kv_watch(
         &mut self,
-        subject: &str,
+        key: KvWatchKey,
+        fn_name: Arc<str>,
+        rt: &tokio::runtime::Runtime,
+        sender: Sender<InternalWorkerMessage>,
+    ) {
+        match self.kv_watches.entry(key.clone()) {
+            Entry::Occupied(mut w) => {
+                let _ = w.get_mut().funcs.insert(fn_name);
+            }
+            Entry::Vacant(we) => {
+                let handler = Self::spawn_kv_watch_task(
+                    self.client.clone(),
+                    rt,
+                    sender,
+                    self.callback_queue.clone(),
+                    key,
+                );
+
+                let _ = we.insert(NatsSubscription {
+                    handler,
+                    funcs: HashSet::from([fn_name]),
+                });
+            }
+        }
+    }
+
+    pub(super) fn kv_unwatch(&mut self, key: KvWatchKey, fn_name: Arc<str>) {
+        if let Entry::Occupied(mut e) = self.kv_watches.entry(key) {
+            let _ = e.get_mut().funcs.remove(&fn_name);
+
+            if e.get().funcs.is_empty() {
+                let watch = e.remove();
+                watch.handler.abort();
+            }
+        }
+    }
+
+    pub(super) fn kv_unwatch_pattern(&mut self, key: &KvWatchKey) {
+        if let Some(watch) = self.kv_watches.remove(key) {
+            watch.handler.abort();
+        }
+    }
+
+    pub(super) fn kv_unwatch_all(&mut self) -> HashMap<KvWatchKey, NatsSubscription> {
+        let watches = std::mem::take(&mut self.kv_watches);
+        for watch in watches.values() {
+            watch.handler.abort();
+        }
+
+        watches
+    }
+
+    pub(super) fn run_kv_watch_callbacks(
+        &mut self,
+        key: &KvWatchKey,
         db_name: &str,
-        data: Arc<[u8]>,
-        callback: impl Fn(&str, &[u8]) -> Result<(), CallError>,
+        callback: impl Fn(&str) -> Result<(), CallError>,
     ) {
-        if let Some(subject) = self.subscriptions.get_mut(subject) {
-            subject.funcs.retain(|fnname| {
-                if let Err(err) = callback(fnname, &data) {
-                    match err {
-                        CallError::NotFound => {
-                            warn!(
-                                context = db_name,
-                                "Function '{fnname}' was dropped, unregistering...",
-                            );
-                            false
-                        }
-                        CallError::Other(err) => {
-                            warn!(
-                                context = db_name,
-                                "Error while calling subscriber function '{fnname}': {err:?}",
-                            );
-                            true
-                        }
+        if let Some(watch) = self.kv_watches.get_mut(key) {
+            let _ = call_functions(&mut watch.funcs, "KV watch", db_name, |fnname| {
+                callback(fnname)
+            });
+        }
+    }
+
+    pub(super) fn object_watch(
+        &mut self,
+        store: Arc<str>,
+        fn_name: Arc<str>,
+        rt: &tokio::runtime::Runtime,
+        sender: Sender<InternalWorkerMessage>,
+    ) {
+        match self.object_watches.entry(store.clone()) {
+            Entry::Occupied(mut w) => {
+                let _ = w.get_mut().funcs.insert(fn_name);
+            }
+            Entry::Vacant(we) => {
+                let handler = Self::spawn_object_watch_task(
+                    self.client.clone(),
+                    rt,
+                    sender,
+                    self.callback_queue.clone(),
+                    store,
+                );
+
+                let _ = we.insert(NatsSubscription {
+                    handler,
+                    funcs: HashSet::from([fn_name]),
+                });
+            }
+        }
+    }
+
+    pub(super) fn object_unwatch(&mut self, store: Arc<str>, fn_name: Arc<str>) {
+        if let Entry::Occupied(mut e) = self.object_watches.entry(store) {
+            let _ = e.get_mut().funcs.remove(&fn_name);
+
+            if e.get().funcs.is_empty() {
+                let watch = e.remove();
+                watch.handler.abort();
+            }
+        }
+    }
+
+    pub(super) fn object_unwatch_store(&mut self, store: &str) {
+        if let Some(watch) = self.object_watches.remove(store) {
+            watch.handler.abort();
+        }
+    }
+
+    pub(super) fn object_unwatch_all(&mut self) -> HashMap<Arc<str>, NatsSubscription> {
+        let watches = std::mem::take(&mut self.object_watches);
+        for watch in watches.values() {
+            watch.handler.abort();
+        }
+
+        watches
+    }
+
+    pub(super) fn run_object_watch_callbacks(
+        &mut self,
+        store: &str,
+        db_name: &str,
+        callback: impl Fn(&str) -> Result<(), CallError>,
+    ) {
+        if let Some(watch) = self.object_watches.get_mut(store) {
+            let _ = call_functions(&mut watch.funcs, "object watch", db_name, |fnname| {
+                callback(fnname)
+            });
+        }
+    }
+
+    /// Registers a function on a durable consumer. A consumer that does not exist yet is
+    /// created delivering every message at most `max_deliver` times.
+    pub(super) fn stream_subscribe(
+        &mut self,
+        key: StreamSubscriptionKey,
+        fn_name: Arc<str>,
+        max_deliver: i64,
+        rt: &tokio::runtime::Runtime,
+        sender: Sender<InternalWorkerMessage>,
+    ) {
+        match self.stream_subscriptions.entry(key.clone()) {
+            Entry::Occupied(mut s) => {
+                let _ = s.get_mut().subscription.funcs.insert(fn_name);
+            }
+            Entry::Vacant(se) => {
+                let handler = Self::spawn_stream_subscription_task(
+                    self.client.clone(),
+                    rt,
+                    sender,
+                    self.callback_queue.clone(),
+                    key,
+                    max_deliver,
+                );
+
+                let _ = se.insert(NatsStreamSubscription {
+                    subscription: NatsSubscription {
+                        handler,
+                        funcs: HashSet::from([fn_name]),
+                    },
+                    max_deliver,
+                });
+            }
+        }
+    }
+
+    pub(super) fn stream_unsubscribe(&mut self, key: StreamSubscriptionKey, fn_name: Arc<str>) {
+        if let Entry::Occupied(mut e) = self.stream_subscriptions.entry(key) {
+            let _ = e.get_mut().subscription.funcs.remove(&fn_name);
+
+            if e.get().subscription.funcs.is_empty() {
+                let sub = e.remove();
+                sub.subscription.handler.abort();
+            }
+        }
+    }
+
+    pub(super) fn stream_unsubscribe_consumer(&mut self, key: &StreamSubscriptionKey) {
+        if let Some(sub) = self.stream_subscriptions.remove(key) {
+            sub.subscription.handler.abort();
+        }
+    }
+
+    pub(super) fn stream_unsubscribe_all(
+        &mut self,
+    ) -> HashMap<StreamSubscriptionKey, NatsStreamSubscription> {
+        let subs = std::mem::take(&mut self.stream_subscriptions);
+        for sub in subs.values() {
+            sub.subscription.handler.abort();
+        }
+
+        subs
+    }
+
+    /// Runs every callback registered for the consumer and returns the functions that
+    /// failed, together with their error text, or `None` if no callback is left.
+    pub(super) fn run_stream_callbacks(
+        &mut self,
+        key: &StreamSubscriptionKey,
+        db_name: &str,
+        message: &CallbackMessage,
+        mut callback: impl FnMut(&Arc<str>, &CallbackMessage) -> Result<(), CallError>,
+    ) -> Option<Vec<(Arc<str>, String)>> {
+        let Entry::Occupied(mut sub) = self.stream_subscriptions.entry(key.clone()) else {
+            return None;
+        };
+
+        let failed = call_functions(
+            &mut sub.get_mut().subscription.funcs,
+            "stream subscriber",
+            db_name,
+            |fnname| callback(fnname, message),
+        );
+
+        // Nobody is left to process the message, so it stays in the durable consumer
+        // until a callback is registered again.
+        if sub.get().subscription.funcs.is_empty() {
+            sub.remove().subscription.handler.abort();
+            return None;
+        }
+
+        Some(failed)
+    }
+
+    /// Registers the function answering requests on a subject, replacing the previous one.
+    /// The subject is subscribed again only if the queue group changed.
+    pub(super) fn respond(
+        &mut self,
+        subject: Arc<str>,
+        fn_name: Arc<str>,
+        queue_group: Option<Arc<str>>,
+        rt: &tokio::runtime::Runtime,
+        sender: Sender<InternalWorkerMessage>,
+    ) {
+        match self.responders.entry(subject.clone()) {
+            Entry::Occupied(mut r) if r.get().queue_group == queue_group => {
+                r.get_mut().fn_name = fn_name;
+            }
+            entry => {
+                let handler = Self::spawn_responder_task(
+                    self.client.clone(),
+                    rt,
+                    sender,
+                    self.callback_queue.clone(),
+                    subject,
+                    queue_group.clone(),
+                );
+
+                let responder = NatsResponder {
+                    handler,
+                    fn_name,
+                    queue_group,
+                };
+
+                match entry {
+                    Entry::Occupied(mut r) => r.insert(responder).handler.abort(),
+                    Entry::Vacant(r) => {
+                        let _ = r.insert(responder);
                     }
-                } else {
-                    true
                 }
+            }
+        }
+    }
+
+    pub(super) fn unrespond(&mut self, subject: &str) {
+        if let Some(responder) = self.responders.remove(subject) {
+            responder.handler.abort();
+        }
+    }
+
+    pub(super) fn unrespond_all(&mut self) -> HashMap<Arc<str>, NatsResponder> {
+        let responders = std::mem::take(&mut self.responders);
+        for responder in responders.values() {
+            responder.handler.abort();
+        }
+
+        responders
+    }
+
+    /// Calls the function registered for the subject and returns the response to send,
+    /// or `None` if the subject has no responder anymore.
+    pub(super) fn run_responder(
+        &mut self,
+        subject: &str,
+        db_name: &str,
+        message: &CallbackMessage,
+        callback: impl FnOnce(&str, &CallbackMessage) -> Result<Vec<u8>, CallError>,
+    ) -> Option<Result<Vec<u8>, String>> {
+        let fnname = self.responders.get(subject)?.fn_name.clone();
+
+        match callback(&fnname, message) {
+            Ok(response) => Some(Ok(response)),
+            Err(CallError::NotFound) => {
+                warn!(
+                    context = db_name,
+                    "Function '{fnname}' was dropped, unregistering...",
+                );
+                self.unrespond(subject);
+
+                Some(Err(format!("Function '{fnname}' does not exist")))
+            }
+            Err(CallError::Other(err)) => {
+                warn!(
+                    context = db_name,
+                    "Error while calling responder function '{fnname}': {err:?}",
+                );
+
+                Some(Err(err.to_string()))
+            }
+        }
+    }
+
+    /// Registers a service or updates its version and description, keeping its endpoints.
+    pub(super) fn service_add(
+        &mut self,
+        name: Arc<str>,
+        version: String,
+        description: Option<String>,
+        rt: &tokio::runtime::Runtime,
+    ) -> anyhow::Result<()> {
+        let service = self
+            .services
+            .entry(name.clone())
+            .or_insert_with(|| NatsService {
+                version: String::new(),
+                description: None,
+                endpoints: HashMap::new(),
+                running: None,
             });
+
+        if service.running.is_some()
+            && service.version == version
+            && service.description == description
+        {
+            return Ok(());
+        }
+
+        service.version = version;
+        service.description = description;
+
+        Self::restart_service(
+            &self.client,
+            rt,
+            self.callback_queue.clone(),
+            &name,
+            service,
+        )
+    }
+
+    pub(super) fn service_remove(&mut self, name: &str, rt: &tokio::runtime::Runtime) {
+        if let Some(mut service) = self.services.remove(name) {
+            Self::stop_service(rt, &mut service);
+        }
+    }
+
+    pub(super) fn service_endpoint_add(
+        &mut self,
+        name: Arc<str>,
+        endpoint: Arc<str>,
+        subject: String,
+        fn_name: Arc<str>,
+        rt: &tokio::runtime::Runtime,
+    ) -> anyhow::Result<()> {
+        let Some(service) = self.services.get_mut(&name) else {
+            anyhow::bail!("Service '{name}' is not registered");
+        };
+
+        let definition = NatsServiceEndpoint { subject, fn_name };
+
+        let Some(running) = &mut service.running else {
+            let _ = service.endpoints.insert(endpoint, definition);
+            return Self::restart_service(
+                &self.client,
+                rt,
+                self.callback_queue.clone(),
+                &name,
+                service,
+            );
+        };
+
+        // Endpoint names are unique within a service, so a redefined endpoint is stopped first.
+        let _ = service.endpoints.remove(&endpoint);
+        if let Some(previous) = running.endpoints.remove(&endpoint) {
+            Self::stop_endpoint(rt, previous);
+        }
+
+        let started = rt.block_on(Self::start_endpoint(
+            &running.service,
+            self.callback_queue.clone(),
+            &name,
+            &endpoint,
+            &definition,
+        ))?;
+
+        let _ = running.endpoints.insert(endpoint.clone(), started);
+        let _ = service.endpoints.insert(endpoint, definition);
+
+        Ok(())
+    }
+
+    pub(super) fn service_endpoint_remove(
+        &mut self,
+        name: &Arc<str>,
+        endpoint: &str,
+        rt: &tokio::runtime::Runtime,
+    ) {
+        let Some(service) = self.services.get_mut(name) else {
+            return;
+        };
+
+        if service.endpoints.remove(endpoint).is_none() {
+            return;
+        }
+
+        // A service without endpoints is withdrawn until an endpoint is added again.
+        if service.endpoints.is_empty() {
+            Self::stop_service(rt, service);
+            return;
+        }
+
+        if let Some(running) = &mut service.running {
+            if let Some(running_endpoint) = running.endpoints.remove(endpoint) {
+                Self::stop_endpoint(rt, running_endpoint);
+            }
+        }
+    }
+
+    pub(super) fn service_endpoint_function(&self, name: &str, endpoint: &str) -> Option<Arc<str>> {
+        self.services
+            .get(name)?
+            .endpoints
+            .get(endpoint)
+            .map(|endpoint| endpoint.fn_name.clone())
+    }
+
+    pub(super) fn service_stop_all(
+        &mut self,
+        rt: &tokio::runtime::Runtime,
+    ) -> HashMap<Arc<str>, NatsService> {
+        let mut services = std::mem::take(&mut self.services);
+        for service in services.values_mut() {
+            Self::stop_service(rt, service);
         }
+
+        services
+    }
+
+    /// Runs every callback of a subscription and returns the functions that failed,
+    /// together with their error text.
+    pub(super) fn run_callbacks(
+        &mut self,
+        key: &SubscriptionKey,
+        db_name: &str,
+        message: &CallbackMessage,
+        mut callback: impl FnMut(&Arc<str>, &CallbackMessage) -> Result<(), CallError>,
+    ) -> Vec<(Arc<str>, String)> {
+        let Some(subject) = self.subscriptions.get_mut(key) else {
+            return vec![];
+        };
+
+        call_functions(&mut subject.funcs, "subscriber", db_name, |fnname| {
+            callback(fnname, message)
+        })
+    }
+
+    /// Subject and function of every subscribed callback, and whether the task reading
+    /// its subscription is still running.
+    pub(super) fn subscribed_callbacks(
+        &self,
+    ) -> impl Iterator<Item = (&Arc<str>, &Arc<str>, bool)> {
+        self.subscriptions.iter().flat_map(|(key, sub)| {
+            let active = !sub.handler.is_finished();
+            sub.funcs
+                .iter()
+                .map(move |fn_name| (&key.subject, fn_name, active))
+        })
+    }
+
+    /// Number of messages of every subject waiting in the callback queue.
+    pub(super) fn queued_callbacks(&self) -> HashMap<Arc<str>, usize> {
+        self.callback_queue.count_by(|message| match message {
+            InternalWorkerMessage::CallbackCall { subject, .. } => Some(subject.clone()),
+            _ => None,
+        })
+    }
+
+    pub(super) fn set_callback_queue_options(&self, options: CallbackQueueOptions) {
+        self.callback_queue.set_options(options);
+    }
+
+    /// Whether `fn_name` is still subscribed to `subject` with any queue group.
+    pub(super) fn is_subscribed(&self, subject: &str, fn_name: &str) -> bool {
+        self.subscriptions
+            .iter()
+            .any(|(key, sub)| &*key.subject == subject && sub.funcs.contains(fn_name))
     }
 
     pub(super) fn reconnect_nats(
@@ -126,13 +726,92 @@ impl NatsConnectionState {
 
         let mut subs = self.unsubscribe_all();
 
-        for (subject, sub) in &mut subs {
-            sub.handler =
-                Self::spawn_subscription_task(client.clone(), rt, sender.clone(), subject.clone());
+        for (key, sub) in &mut subs {
+            sub.handler = Self::spawn_subscription_task(
+                client.clone(),
+                rt,
+                sender.clone(),
+                self.callback_queue.clone(),
+                key.clone(),
+            );
+        }
+
+        let mut watches = self.kv_unwatch_all();
+
+        for (key, watch) in &mut watches {
+            watch.handler = Self::spawn_kv_watch_task(
+                client.clone(),
+                rt,
+                sender.clone(),
+                self.callback_queue.clone(),
+                key.clone(),
+            );
+        }
+
+        let mut object_watches = self.object_unwatch_all();
+
+        for (store, watch) in &mut object_watches {
+            watch.handler = Self::spawn_object_watch_task(
+                client.clone(),
+                rt,
+                sender.clone(),
+                self.callback_queue.clone(),
+                store.clone(),
+            );
+        }
+
+        let mut stream_subs = self.stream_unsubscribe_all();
+
+        for (key, sub) in &mut stream_subs {
+            sub.subscription.handler = Self::spawn_stream_subscription_task(
+                client.clone(),
+                rt,
+                sender.clone(),
+                self.callback_queue.clone(),
+                key.clone(),
+                sub.max_deliver,
+            );
+        }
+
+        let mut responders = self.unrespond_all();
+
+        for (subject, responder) in &mut responders {
+            responder.handler = Self::spawn_responder_task(
+                client.clone(),
+                rt,
+                sender.clone(),
+                self.callback_queue.clone(),
+                subject.clone(),
+                responder.queue_group.clone(),
+            );
         }
 
+        let mut services = self.service_stop_all(rt);
+
         self.client = client;
         self.subscriptions = subs;
+        self.kv_watches = watches;
+        self.object_watches = object_watches;
+        self.stream_subscriptions = stream_subs;
+        self.responders = responders;
+
+        let mut errors = Vec::new();
+
+        for (name, service) in &mut services {
+            if let Err(err) =
+                Self::restart_service(&self.client, rt, self.callback_queue.clone(), name, service)
+            {
+                errors.push(err.to_string());
+            }
+        }
+
+        self.services = services;
+
+        anyhow::ensure!(
+            errors.is_empty(),
+            "Failed to restart services: {}",
+            errors.join("; ")
+        );
 
         Ok(())
     }
@@ -143,6 +822,91 @@ impl NatsConnectionState {
 
         Ok(())
     }
+
+    pub(super) async fn ack_stream_message(
+        &self,
+        reply: String,
+        kind: AckKind,
+    ) -> anyhow::Result<()> {
+        self.client.publish(reply, kind.into()).await?;
+        self.client.flush().await?;
+
+        Ok(())
+    }
+
+    /// Publishes a message whose callback kept failing to a dead-letter subject, keeping
+    /// its headers and describing the failure in `Pgnats-*` headers.
+    pub(super) async fn publish_dead_letter(
+        &self,
+        dead_letter_subject: String,
+        message: &CallbackMessage,
+        fn_name: &str,
+        error: &str,
+        attempts: u32,
+    ) -> anyhow::Result<()> {
+        let mut headers = message
+            .headers
+            .clone()
+            .map(extract_headers)
+            .unwrap_or_default();
+        headers.insert(DEAD_LETTER_SUBJECT_HEADER, message.subject.as_str());
+        headers.insert(DEAD_LETTER_CALLBACK_HEADER, fn_name);
+        headers.insert(DEAD_LETTER_ERROR_HEADER, service_error_status(error));
+        headers.insert(DEAD_LETTER_ATTEMPTS_HEADER, attempts.to_string());
+
+        self.client
+            .publish_with_headers(dead_letter_subject, headers, message.payload.clone().into())
+            .await?;
+        self.client.flush().await?;
+
+        Ok(())
+    }
+
+    /// Answers a service request; errors are counted in the endpoint statistics.
+    pub(super) async fn send_service_response(
+        &self,
+        request: async_nats::service::Request,
+        response: Result<Vec<u8>, String>,
+    ) -> anyhow::Result<()> {
+        let response = match response {
+            Ok(payload) => Ok(payload.into()),
+            Err(error) => Err(async_nats::service::error::Error {
+                status: service_error_status(&error),
+                code: 500,
+            }),
+        };
+
+        request.respond(response).await?;
+        self.client.flush().await?;
+
+        Ok(())
+    }
+
+    /// Publishes the response to a request, or its error as service error headers with an
+    /// empty payload.
+    pub(super) async fn send_response(
+        &self,
+        reply: String,
+        response: Result<Vec<u8>, String>,
+    ) -> anyhow::Result<()> {
+        match response {
+            Ok(payload) => self.client.publish(reply, payload.into()).await?,
+            Err(error) => {
+                let mut headers = async_nats::HeaderMap::new();
+                headers.insert(SERVICE_ERROR_HEADER, service_error_status(&error));
+                headers.insert(SERVICE_ERROR_CODE_HEADER, "500");
+
+                self.client
+                    .publish_with_headers(reply, headers, Default::default())
+                    .await?
+            }
+        }
+
+        self.client.flush().await?;
+
+        Ok(())
+    }
+
     pub(super) async fn drain(&self) -> anyhow::Result<()> {
         self.client.drain().await?;
 
@@ -177,20 +941,428 @@ impl NatsConnectionState {
         client: async_nats::Client,
         rt: &tokio::runtime::Runtime,
         sender: Sender<InternalWorkerMessage>,
-        subject: Arc<str>,
+        callback_queue: Arc<BoundedQueue<InternalWorkerMessage>>,
+        key: SubscriptionKey,
     ) -> JoinHandle<()> {
         rt.spawn(async move {
-            match client.subscribe(subject.to_string()).await {
+            let sub = match &key.queue_group {
+                Some(queue_group) => {
+                    client
+                        .queue_subscribe(key.subject.to_string(), queue_group.to_string())
+                        .await
+                }
+                None => client.subscribe(key.subject.to_string()).await,
+            };
+
+            match sub {
                 Ok(mut sub) => {
                     while let Some(msg) = sub.next().await {
-                        let _ = sender.send(InternalWorkerMessage::CallbackCall {
-                            subject: subject.clone(),
-                            data: Arc::from(msg.payload.to_vec()),
-                        });
+                        callback_queue
+                            .push(InternalWorkerMessage::CallbackCall {
+                                subject: key.subject.clone(),
+                                queue_group: key.queue_group.clone(),
+                                message: CallbackMessage {
+                                    subject: msg.subject.to_string(),
+                                    payload: msg.payload.to_vec(),
+                                    headers: msg.headers.as_ref().map(headers_to_json),
+                                    reply: msg.reply.map(|reply| reply.to_string()),
+                                },
+                            })
+                            .await;
                     }
                 }
                 Err(err) => {
                     let _ = sender.send(InternalWorkerMessage::UnsubscribeSubject {
+                        subject: key.subject.clone(),
+                        queue_group: key.queue_group.clone(),
+                        reason: err.to_string(),
+                    });
+                }
+            }
+        })
+    }
+
+    fn spawn_kv_watch_task(
+        client: async_nats::Client,
+        rt: &tokio::runtime::Runtime,
+        sender: Sender<InternalWorkerMessage>,
+        callback_queue: Arc<BoundedQueue<InternalWorkerMessage>>,
+        key: KvWatchKey,
+    ) -> JoinHandle<()> {
+        rt.spawn(async move {
+            let watch = async {
+                let jetstream = async_nats::jetstream::new(client);
+
+                let store = jetstream.get_key_value(&*key.bucket).await.map_err(|err| {
+                    anyhow::anyhow!("KV bucket '{}' is not available: {err}", key.bucket)
+                })?;
+
+                // Starts with the latest revision of every matching key, so that a mirror
+                // can be rebuilt after a restart, and then follows new changes. Revisions a
+                // callback has already processed are skipped when it is called.
+                let mut entries = store.watch_with_history(&*key.key_pattern).await?;
+
+                while let Some(entry) = entries.next().await {
+                    let entry = entry?;
+
+                    callback_queue
+                        .push(InternalWorkerMessage::KvWatchCall {
+                            bucket: key.bucket.clone(),
+                            key_pattern: key.key_pattern.clone(),
+                            entry: KvWatchEntry {
+                                key: entry.key,
+                                value: entry.value.to_vec(),
+                                revision: entry.revision,
+                                operation: kv_operation_name(entry.operation),
+                            },
+                        })
+                        .await;
+                }
+
+                anyhow::Ok(())
+            };
+
+            if let Err(err) = watch.await {
+                let _ = sender.send(InternalWorkerMessage::KvUnwatchPattern {
+                    bucket: key.bucket.clone(),
+                    key_pattern: key.key_pattern.clone(),
+                    reason: err.to_string(),
+                });
+            }
+        })
+    }
+
+    fn spawn_object_watch_task(
+        client: async_nats::Client,
+        rt: &tokio::runtime::Runtime,
+        sender: Sender<InternalWorkerMessage>,
+        callback_queue: Arc<BoundedQueue<InternalWorkerMessage>>,
+        store: Arc<str>,
+    ) -> JoinHandle<()> {
+        rt.spawn(async move {
+            let watch = async {
+                let jetstream = async_nats::jetstream::new(client);
+
+                let object_store = jetstream.get_object_store(&*store).await.map_err(|err| {
+                    anyhow::anyhow!("Object store '{store}' is not available: {err}")
+                })?;
+
+                // Starts with the latest state of every object, so that files uploaded
+                // while the worker was down are not missed, and then follows new changes.
+                // Changes a callback has already processed are skipped when it is called.
+                let mut objects = object_store.watch_with_history().await?;
+
+                while let Some(object) = objects.next().await {
+                    let object = object?;
+
+                    callback_queue
+                        .push(InternalWorkerMessage::ObjectWatchCall {
+                            store: store.clone(),
+                            entry: ObjectWatchEntry {
+                                name: object.name,
+                                nuid: object.nuid,
+                                size: object.size as u64,
+                                modified: object.modified.map(|m| m.unix_timestamp_nanos()),
+                                operation: if object.deleted { "DEL" } else { "PUT" },
+                            },
+                        })
+                        .await;
+                }
+
+                anyhow::Ok(())
+            };
+
+            if let Err(err) = watch.await {
+                let _ = sender.send(InternalWorkerMessage::ObjectUnwatchStore {
+                    store: store.clone(),
+                    reason: err.to_string(),
+                });
+            }
+        })
+    }
+
+    fn spawn_stream_subscription_task(
+        client: async_nats::Client,
+        rt: &tokio::runtime::Runtime,
+        sender: Sender<InternalWorkerMessage>,
+        callback_queue: Arc<BoundedQueue<InternalWorkerMessage>>,
+        key: StreamSubscriptionKey,
+        max_deliver: i64,
+    ) -> JoinHandle<()> {
+        rt.spawn(async move {
+            let subscription = async {
+                let jetstream = async_nats::jetstream::new(client);
+
+                let stream = jetstream.get_stream(&*key.stream).await?;
+                let consumer = stream
+                    .get_or_create_consumer(
+                        &key.consumer,
+                        pull::Config {
+                            durable_name: Some(key.consumer.to_string()),
+                            max_deliver,
+                            ..Default::default()
+                        },
+                    )
+                    .await?;
+
+                let mut messages = consumer.messages().await?;
+
+                while let Some(message) = messages.next().await {
+                    let message = match message {
+                        Ok(message) => message,
+                        // The pull stream recovers from missed heartbeats on its own.
+                        Err(err) if err.kind() == MessagesErrorKind::MissingHeartbeat => continue,
+                        Err(err) => return Err(err.into()),
+                    };
+
+                    let Some(ack_subject) = message.reply.as_ref().map(|reply| reply.to_string())
+                    else {
+                        continue;
+                    };
+                    let Ok(info) = message.info() else {
+                        continue;
+                    };
+                    let delivery = StreamDelivery {
+                        ack_subject,
+                        stream_sequence: info.stream_sequence,
+                        delivered: info.delivered,
+                    };
+
+                    callback_queue
+                        .push(InternalWorkerMessage::StreamCallbackCall {
+                            stream: key.stream.clone(),
+                            consumer: key.consumer.clone(),
+                            message: CallbackMessage {
+                                subject: message.subject.to_string(),
+                                payload: message.payload.to_vec(),
+                                headers: message.headers.as_ref().map(headers_to_json),
+                                reply: None,
+                            },
+                            delivery,
+                        })
+                        .await;
+                }
+
+                anyhow::Ok(())
+            };
+
+            if let Err(err) = subscription.await {
+                let _ = sender.send(InternalWorkerMessage::StreamUnsubscribeConsumer {
+                    stream: key.stream.clone(),
+                    consumer: key.consumer.clone(),
+                    reason: err.to_string(),
+                });
+            }
+        })
+    }
+
+    fn restart_service(
+        client: &async_nats::Client,
+        rt: &tokio::runtime::Runtime,
+        callback_queue: Arc<BoundedQueue<InternalWorkerMessage>>,
+        name: &Arc<str>,
+        service: &mut NatsService,
+    ) -> anyhow::Result<()> {
+        Self::stop_service(rt, service);
+
+        // A service without endpoints is not announced until its first endpoint is added.
+        if !service.endpoints.is_empty() {
+            service.running = Some(Self::start_service(
+                client,
+                rt,
+                callback_queue,
+                name,
+                service,
+            )?);
+        }
+
+        Ok(())
+    }
+
+    fn stop_service(rt: &tokio::runtime::Runtime, service: &mut NatsService) {
+        if let Some(running) = service.running.take() {
+            let _ = rt.block_on(running.service.stop());
+
+            for endpoint in running.endpoints.values() {
+                endpoint.handler.abort();
+            }
+        }
+    }
+
+    /// Removes an endpoint from its running service and waits until it no longer receives
+    /// requests.
+    fn stop_endpoint(rt: &tokio::runtime::Runtime, endpoint: RunningEndpoint) {
+        let _ = endpoint.stop.send(());
+        let _ = rt.block_on(endpoint.handler);
+    }
+
+    async fn start_endpoint(
+        service: &Service,
+        callback_queue: Arc<BoundedQueue<InternalWorkerMessage>>,
+        name: &Arc<str>,
+        endpoint_name: &Arc<str>,
+        endpoint: &NatsServiceEndpoint,
+    ) -> anyhow::Result<RunningEndpoint> {
+        let requests = service
+            .endpoint_builder()
+            .name(endpoint_name)
+            .add(endpoint.subject.clone())
+            .await
+            .map_err(|err| {
+                anyhow::anyhow!(
+                    "Failed to add endpoint '{endpoint_name}' to service '{name}': {err}"
+                )
+            })?;
+
+        let (stop, stopped) = tokio::sync::oneshot::channel();
+        let handler = tokio::spawn(Self::forward_service_requests(
+            requests,
+            stopped,
+            callback_queue,
+            name.clone(),
+            endpoint_name.clone(),
+        ));
+
+        Ok(RunningEndpoint { stop, handler })
+    }
+
+    fn start_service(
+        client: &async_nats::Client,
+        rt: &tokio::runtime::Runtime,
+        callback_queue: Arc<BoundedQueue<InternalWorkerMessage>>,
+        name: &Arc<str>,
+        service: &NatsService,
+    ) -> anyhow::Result<RunningService> {
+        rt.block_on(async {
+            let mut builder = client.service_builder();
+
+            if let Some(description) = &service.description {
+                builder = builder.description(description);
+            }
+
+            let running = builder
+                .start(name.to_string(), service.version.clone())
+                .await
+                .map_err(|err| anyhow::anyhow!("Failed to start service '{name}': {err}"))?;
+
+            let mut endpoints = HashMap::with_capacity(service.endpoints.len());
+
+            for (endpoint_name, endpoint) in &service.endpoints {
+                match Self::start_endpoint(
+                    &running,
+                    callback_queue.clone(),
+                    name,
+                    endpoint_name,
+                    endpoint,
+                )
+                .await
+                {
+                    Ok(started) => {
+                        let _ = endpoints.insert(endpoint_name.clone(), started);
+                    }
+                    Err(err) => {
+                        for started in endpoints.values() {
+                            started.handler.abort();
+                        }
+                        let _ = running.stop().await;
+
+                        return Err(err);
+                    }
+                }
+            }
+
+            Ok(RunningService {
+                service: running,
+                endpoints,
+            })
+        })
+    }
+
+    async fn forward_service_requests(
+        mut requests: Endpoint,
+        mut stopped: tokio::sync::oneshot::Receiver<()>,
+        callback_queue: Arc<BoundedQueue<InternalWorkerMessage>>,
+        service: Arc<str>,
+        endpoint: Arc<str>,
+    ) {
+        loop {
+            let next = std::pin::pin!(requests.next());
+            let request = match future::select(next, &mut stopped).await {
+                Either::Left((request, _)) => request,
+                Either::Right(_) => break,
+            };
+            let Some(request) = request else {
+                return;
+            };
+
+            // Nobody waits for the answer to a plain publish.
+            let msg = &request.message;
+            let Some(reply) = msg.reply.as_ref() else {
+                continue;
+            };
+
+            let message = CallbackMessage {
+                subject: msg.subject.to_string(),
+                payload: msg.payload.to_vec(),
+                headers: msg.headers.as_ref().map(headers_to_json),
+                reply: Some(reply.to_string()),
+            };
+
+            callback_queue
+                .push(InternalWorkerMessage::ServiceRequest {
+                    service: service.clone(),
+                    endpoint: endpoint.clone(),
+                    message,
+                    request,
+                })
+                .await;
+        }
+
+        // Withdraws the endpoint from the discovery responses of the running service.
+        let _ = requests.stop().await;
+    }
+
+    fn spawn_responder_task(
+        client: async_nats::Client,
+        rt: &tokio::runtime::Runtime,
+        sender: Sender<InternalWorkerMessage>,
+        callback_queue: Arc<BoundedQueue<InternalWorkerMessage>>,
+        subject: Arc<str>,
+        queue_group: Option<Arc<str>>,
+    ) -> JoinHandle<()> {
+        rt.spawn(async move {
+            let sub = match &queue_group {
+                Some(queue_group) => {
+                    client
+                        .queue_subscribe(subject.to_string(), queue_group.to_string())
+                        .await
+                }
+                None => client.subscribe(subject.to_string()).await,
+            };
+
+            match sub {
+                Ok(mut sub) => {
+                    while let Some(msg) = sub.next().await {
+                        // Nobody waits for the answer to a plain publish.
+                        let Some(reply) = msg.reply else {
+                            continue;
+                        };
+
+                        callback_queue
+                            .push(InternalWorkerMessage::ResponderCall {
+                                subject: subject.clone(),
+                                message: CallbackMessage {
+                                    subject: msg.subject.to_string(),
+                                    payload: msg.payload.to_vec(),
+                                    headers: msg.headers.as_ref().map(headers_to_json),
+                                    reply: Some(reply.to_string()),
+                                },
+                            })
+                            .await;
+                    }
+                }
+                Err(err) => {
+                    let _ = sender.send(InternalWorkerMessage::UnrespondSubject {
                         subject: subject.clone(),
                         reason: err.to_string(),
                     });
@@ -200,8 +1372,71 @@ impl NatsConnectionState {
     }
 }
 
+/// Error text of a failed call; header values must stay on a single line.
+fn service_error_status(error: &str) -> String {
+    error.replace(['\r', '\n'], " ")
+}
+
+/// Redelivery delay for a message that failed on its `attempt`-th delivery.
+pub(super) fn retry_delay(attempt: i64) -> Duration {
+    let exponent = u32::try_from(attempt.saturating_sub(1))
+        .unwrap_or(0)
+        .min(16);
+
+    RETRY_BASE_DELAY
+        .saturating_mul(1 << exponent)
+        .min(RETRY_MAX_DELAY)
+}
+
+/// Calls every function registered for a message, unregistering the ones that were
+/// dropped, and returns the functions that failed, together with their error text.
+fn call_functions(
+    funcs: &mut HashSet<Arc<str>>,
+    kind: &str,
+    db_name: &str,
+    mut callback: impl FnMut(&Arc<str>) -> Result<(), CallError>,
+) -> Vec<(Arc<str>, String)> {
+    let mut failed = vec![];
+
+    funcs.retain(|fnname| match callback(fnname) {
+        Ok(()) => true,
+        Err(CallError::NotFound) => {
+            warn!(
+                context = db_name,
+                "Function '{fnname}' was dropped, unregistering...",
+            );
+            false
+        }
+        Err(CallError::Other(err)) => {
+            warn!(
+                context = db_name,
+                "Error while calling {kind} function '{fnname}': {err:?}",
+            );
+            failed.push((fnname.clone(), err.to_string()));
+            true
+        }
+    });
+
+    failed
+}
+
 impl Drop for NatsConnectionState {
     fn drop(&mut self) {
         let _ = self.unsubscribe_all();
+        let _ = self.kv_unwatch_all();
+        let _ = self.object_unwatch_all();
+        let _ = self.stream_unsubscribe_all();
+        let _ = self.unrespond_all();
+
+        // The services themselves stop with the drained connection.
+        for running in self
+            .services
+            .values()
+            .filter_map(|service| service.running.as_ref())
+        {
+            for endpoint in running.endpoints.values() {
+                endpoint.handler.abort();
+            }
+        }
     }
 }
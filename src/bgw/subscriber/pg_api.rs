@@ -1,6 +1,10 @@
-use pgrx::{PgSqlErrorCode, PgTryBuilder, Spi};
+use std::panic::UnwindSafe;
+
+use pgrx::{datum::DatumWithOid, pg_sys, FromDatum, IntoDatum, PgSqlErrorCode, PgTryBuilder, Spi};
 use serde::{Deserialize, Serialize};
 
+use crate::utils::{CallbackPayload, CallbackSignature};
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum PgInstanceStatus {
     Master,
@@ -13,6 +17,69 @@ pub enum CallError {
     Other(anyhow::Error),
 }
 
+/// A received message, as passed to a subscription callback.
+#[derive(Clone, Debug)]
+pub struct CallbackMessage {
+    pub subject: String,
+    pub payload: Vec<u8>,
+    pub headers: Option<serde_json::Value>,
+    pub reply: Option<String>,
+}
+
+/// A change of a watched KV key, as passed to a KV watch callback.
+#[derive(Clone, Debug)]
+pub struct KvWatchEntry {
+    pub key: String,
+    pub value: Vec<u8>,
+    pub revision: u64,
+    pub operation: &'static str,
+}
+
+/// A change of a watched object store, as passed to an object watch callback.
+#[derive(Clone, Debug)]
+pub struct ObjectWatchEntry {
+    pub name: String,
+    pub nuid: String,
+    pub size: u64,
+    /// Time the change was published, in nanoseconds since the Unix epoch.
+    pub modified: Option<i128>,
+    pub operation: &'static str,
+}
+
+/// How failed calls of a subscription callback are handled. A message that still fails
+/// after `max_retries` retries is a dead letter: it is published to `dead_letter_subject`
+/// and recorded in `pgnats.dead_letters` when requested, and dropped otherwise.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub dead_letter_subject: Option<String>,
+    pub dead_letter_table: bool,
+}
+
+/// How messages are grouped for a batch callback: a batch is delivered once it holds
+/// `size` messages or its oldest message has waited `linger_ms` milliseconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchPolicy {
+    pub size: u32,
+    pub linger_ms: u64,
+}
+
+impl Default for BatchPolicy {
+    fn default() -> Self {
+        Self {
+            size: 1,
+            linger_ms: 0,
+        }
+    }
+}
+
+/// Argument and return types of a responder function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResponderFunction {
+    pub signature: CallbackSignature,
+    pub return_type: CallbackPayload,
+}
+
 pub fn fetch_status() -> PgInstanceStatus {
     // SAFETY: Calling Postgres backend function which takes no arguments,
     // has no side effects, and does not rely on any Rust-managed memory.
@@ -24,19 +91,76 @@ pub fn fetch_status() -> PgInstanceStatus {
     }
 }
 
-pub fn fetch_subject_with_callbacks(table_name: &str) -> anyhow::Result<Vec<(String, String)>> {
-    PgTryBuilder::new(|| {
+pub fn fetch_subscriptions(
+    table_name: &str,
+) -> anyhow::Result<Vec<(String, String, Option<String>, RetryPolicy, BatchPolicy)>> {
+    try_spi(|| {
         Spi::connect_mut(|client| {
-            let sql = format!("SELECT subject, callback FROM {table_name}");
+            let sql = format!(
+                "SELECT subject, callback, queue_group, max_retries, dead_letter_subject, \
+                 dead_letter_table, batch_size, batch_linger_ms FROM {table_name}"
+            );
             let tuples = client.select(&sql, None, &[])?;
-            let subject_callbacks: Vec<(String, String)> = tuples
+            let subscriptions: Vec<_> = tuples
+                .into_iter()
+                .filter_map(|tuple| {
+                    let subject = tuple.get_by_name::<String, _>("subject").ok()??;
+                    let fn_name = tuple.get_by_name::<String, _>("callback").ok()??;
+                    let queue_group = tuple.get_by_name::<String, _>("queue_group").ok()?;
+                    let max_retries = tuple.get_by_name::<i32, _>("max_retries").ok()?;
+                    let dead_letter_subject =
+                        tuple.get_by_name::<String, _>("dead_letter_subject").ok()?;
+                    let dead_letter_table =
+                        tuple.get_by_name::<bool, _>("dead_letter_table").ok()?;
+
+                    let retry = RetryPolicy {
+                        max_retries: max_retries
+                            .and_then(|v| u32::try_from(v).ok())
+                            .unwrap_or_default(),
+                        dead_letter_subject,
+                        dead_letter_table: dead_letter_table.unwrap_or_default(),
+                    };
+
+                    let batch_size = tuple.get_by_name::<i32, _>("batch_size").ok()?;
+                    let batch_linger_ms = tuple.get_by_name::<i64, _>("batch_linger_ms").ok()?;
+
+                    let batch = BatchPolicy {
+                        size: batch_size
+                            .and_then(|v| u32::try_from(v).ok())
+                            .filter(|v| *v > 0)
+                            .unwrap_or(1),
+                        linger_ms: batch_linger_ms
+                            .and_then(|v| u64::try_from(v).ok())
+                            .unwrap_or_default(),
+                    };
+
+                    Some((subject, fn_name, queue_group, retry, batch))
+                })
+                .collect();
+
+            Ok(subscriptions)
+        })
+    })
+}
+
+pub fn fetch_subject_with_callbacks(
+    table_name: &str,
+) -> anyhow::Result<Vec<(String, String, Option<String>)>> {
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!("SELECT subject, callback, queue_group FROM {table_name}");
+            let tuples = client.select(&sql, None, &[])?;
+            let subject_callbacks: Vec<(String, String, Option<String>)> = tuples
                 .into_iter()
                 .filter_map(|tuple| {
                     let subject = tuple.get_by_name::<String, _>("subject");
                     let fn_oid = tuple.get_by_name::<String, _>("callback");
+                    let queue_group = tuple.get_by_name::<String, _>("queue_group");
 
-                    match (subject, fn_oid) {
-                        (Ok(Some(subject)), Ok(Some(fn_oid))) => Some((subject, fn_oid)),
+                    match (subject, fn_oid, queue_group) {
+                        (Ok(Some(subject)), Ok(Some(fn_oid)), Ok(queue_group)) => {
+                            Some((subject, fn_oid, queue_group))
+                        }
                         _ => None,
                     }
                 })
@@ -45,41 +169,53 @@ pub fn fetch_subject_with_callbacks(table_name: &str) -> anyhow::Result<Vec<(Str
             Ok(subject_callbacks)
         })
     })
-    .catch_others(|e| match e {
-        pgrx::pg_sys::panic::CaughtError::PostgresError(err) => Err(anyhow::anyhow!(
-            "Code '{}': {}. ({:?})",
-            err.sql_error_code(),
-            err.message(),
-            err.hint()
-        )),
-        _ => Err(anyhow::anyhow!("{e:?}")),
-    })
-    .execute()
 }
 
 pub fn insert_subject_callback(
     table_name: &str,
     subject: &str,
     fn_name: &str,
+    queue_group: Option<&str>,
+    retry: &RetryPolicy,
+    batch: BatchPolicy,
 ) -> anyhow::Result<()> {
-    PgTryBuilder::new(|| {
+    let max_retries = i32::try_from(retry.max_retries)?;
+    let batch_size = i32::try_from(batch.size)?;
+    let batch_linger_ms = i64::try_from(batch.linger_ms)?;
+
+    try_spi(|| {
         Spi::connect_mut(|client| {
-            let sql = format!("INSERT INTO {table_name} VALUES ($1, $2)");
-            let _ = client.update(&sql, None, &[subject.into(), fn_name.into()])?;
+            let sql = format!(
+                "INSERT INTO {table_name} \
+                 (subject, callback, queue_group, max_retries, dead_letter_subject, dead_letter_table, \
+                 batch_size, batch_linger_ms) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8) \
+                 ON CONFLICT (subject, callback) DO UPDATE \
+                 SET queue_group = EXCLUDED.queue_group, \
+                 max_retries = EXCLUDED.max_retries, \
+                 dead_letter_subject = EXCLUDED.dead_letter_subject, \
+                 dead_letter_table = EXCLUDED.dead_letter_table, \
+                 batch_size = EXCLUDED.batch_size, \
+                 batch_linger_ms = EXCLUDED.batch_linger_ms"
+            );
+            let _ = client.update(
+                &sql,
+                None,
+                &[
+                    subject.into(),
+                    fn_name.into(),
+                    queue_group.into(),
+                    max_retries.into(),
+                    retry.dead_letter_subject.as_deref().into(),
+                    retry.dead_letter_table.into(),
+                    batch_size.into(),
+                    batch_linger_ms.into(),
+                ],
+            )?;
 
             Ok(())
         })
     })
-    .catch_others(|e| match e {
-        pgrx::pg_sys::panic::CaughtError::PostgresError(err) => Err(anyhow::anyhow!(
-            "Code '{}': {}. ({:?})",
-            err.sql_error_code(),
-            err.message(),
-            err.hint()
-        )),
-        _ => Err(anyhow::anyhow!("{e:?}")),
-    })
-    .execute()
 }
 
 pub fn delete_subject_callback(
@@ -87,7 +223,7 @@ pub fn delete_subject_callback(
     subject: &str,
     callback: &str,
 ) -> anyhow::Result<()> {
-    PgTryBuilder::new(|| {
+    try_spi(|| {
         Spi::connect_mut(|client| {
             let sql = format!("DELETE FROM {table_name} WHERE subject = $1 AND callback = $2",);
             let _ = client.update(&sql, None, &[subject.into(), callback.into()])?;
@@ -95,19 +231,814 @@ pub fn delete_subject_callback(
             Ok(())
         })
     })
-    .catch_others(|e| match e {
-        pgrx::pg_sys::panic::CaughtError::PostgresError(err) => Err(anyhow::anyhow!(
-            "Code '{}': {}. ({:?})",
-            err.sql_error_code(),
-            err.message(),
-            err.hint()
-        )),
-        _ => Err(anyhow::anyhow!("{e:?}")),
+}
+
+pub fn fetch_kv_watches(table_name: &str) -> anyhow::Result<Vec<(String, String, String)>> {
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!("SELECT bucket, key_pattern, callback FROM {table_name}");
+            let tuples = client.select(&sql, None, &[])?;
+            let watches: Vec<(String, String, String)> = tuples
+                .into_iter()
+                .filter_map(|tuple| {
+                    let bucket = tuple.get_by_name::<String, _>("bucket");
+                    let key_pattern = tuple.get_by_name::<String, _>("key_pattern");
+                    let callback = tuple.get_by_name::<String, _>("callback");
+
+                    match (bucket, key_pattern, callback) {
+                        (Ok(Some(bucket)), Ok(Some(key_pattern)), Ok(Some(callback))) => {
+                            Some((bucket, key_pattern, callback))
+                        }
+                        _ => None,
+                    }
+                })
+                .collect();
+
+            Ok(watches)
+        })
+    })
+}
+
+pub fn insert_kv_watch(
+    table_name: &str,
+    bucket: &str,
+    key_pattern: &str,
+    fn_name: &str,
+) -> anyhow::Result<()> {
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!(
+                "INSERT INTO {table_name} (bucket, key_pattern, callback) VALUES ($1, $2, $3)"
+            );
+            let _ = client.update(
+                &sql,
+                None,
+                &[bucket.into(), key_pattern.into(), fn_name.into()],
+            )?;
+
+            Ok(())
+        })
+    })
+}
+
+pub fn delete_kv_watch(
+    table_name: &str,
+    bucket: &str,
+    key_pattern: &str,
+    callback: &str,
+) -> anyhow::Result<()> {
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!(
+                "DELETE FROM {table_name} WHERE bucket = $1 AND key_pattern = $2 AND callback = $3"
+            );
+            let _ = client.update(
+                &sql,
+                None,
+                &[bucket.into(), key_pattern.into(), callback.into()],
+            )?;
+
+            Ok(())
+        })
+    })
+}
+
+pub fn fetch_object_watches(table_name: &str) -> anyhow::Result<Vec<(String, String)>> {
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!("SELECT store, callback FROM {table_name}");
+            let tuples = client.select(&sql, None, &[])?;
+            let watches: Vec<(String, String)> = tuples
+                .into_iter()
+                .filter_map(|tuple| {
+                    let store = tuple.get_by_name::<String, _>("store");
+                    let callback = tuple.get_by_name::<String, _>("callback");
+
+                    match (store, callback) {
+                        (Ok(Some(store)), Ok(Some(callback))) => Some((store, callback)),
+                        _ => None,
+                    }
+                })
+                .collect();
+
+            Ok(watches)
+        })
+    })
+}
+
+pub fn insert_object_watch(table_name: &str, store: &str, fn_name: &str) -> anyhow::Result<()> {
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!("INSERT INTO {table_name} (store, callback) VALUES ($1, $2)");
+            let _ = client.update(&sql, None, &[store.into(), fn_name.into()])?;
+
+            Ok(())
+        })
+    })
+}
+
+pub fn delete_object_watch(table_name: &str, store: &str, callback: &str) -> anyhow::Result<()> {
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!("DELETE FROM {table_name} WHERE store = $1 AND callback = $2");
+            let _ = client.update(&sql, None, &[store.into(), callback.into()])?;
+
+            Ok(())
+        })
+    })
+}
+
+pub fn fetch_stream_subscriptions(
+    table_name: &str,
+) -> anyhow::Result<Vec<(String, String, String, RetryPolicy)>> {
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!(
+                "SELECT stream, consumer, callback, max_retries, dead_letter_subject, \
+                 dead_letter_table FROM {table_name}"
+            );
+            let tuples = client.select(&sql, None, &[])?;
+            let subscriptions: Vec<_> = tuples
+                .into_iter()
+                .filter_map(|tuple| {
+                    let stream = tuple.get_by_name::<String, _>("stream").ok()??;
+                    let consumer = tuple.get_by_name::<String, _>("consumer").ok()??;
+                    let callback = tuple.get_by_name::<String, _>("callback").ok()??;
+                    let max_retries = tuple.get_by_name::<i32, _>("max_retries").ok()?;
+                    let dead_letter_subject =
+                        tuple.get_by_name::<String, _>("dead_letter_subject").ok()?;
+                    let dead_letter_table =
+                        tuple.get_by_name::<bool, _>("dead_letter_table").ok()?;
+
+                    let retry = RetryPolicy {
+                        max_retries: max_retries
+                            .and_then(|v| u32::try_from(v).ok())
+                            .unwrap_or_default(),
+                        dead_letter_subject,
+                        dead_letter_table: dead_letter_table.unwrap_or_default(),
+                    };
+
+                    Some((stream, consumer, callback, retry))
+                })
+                .collect();
+
+            Ok(subscriptions)
+        })
+    })
+}
+
+pub fn insert_stream_subscription(
+    table_name: &str,
+    stream: &str,
+    consumer: &str,
+    fn_name: &str,
+    retry: &RetryPolicy,
+) -> anyhow::Result<()> {
+    let max_retries = i32::try_from(retry.max_retries)?;
+
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!(
+                "INSERT INTO {table_name} \
+                 (stream, consumer, callback, max_retries, dead_letter_subject, dead_letter_table) \
+                 VALUES ($1, $2, $3, $4, $5, $6) \
+                 ON CONFLICT (stream, consumer, callback) DO UPDATE \
+                 SET max_retries = EXCLUDED.max_retries, \
+                 dead_letter_subject = EXCLUDED.dead_letter_subject, \
+                 dead_letter_table = EXCLUDED.dead_letter_table"
+            );
+            let _ = client.update(
+                &sql,
+                None,
+                &[
+                    stream.into(),
+                    consumer.into(),
+                    fn_name.into(),
+                    max_retries.into(),
+                    retry.dead_letter_subject.as_deref().into(),
+                    retry.dead_letter_table.into(),
+                ],
+            )?;
+
+            Ok(())
+        })
+    })
+}
+
+pub fn delete_stream_subscription(
+    table_name: &str,
+    stream: &str,
+    consumer: &str,
+    callback: &str,
+) -> anyhow::Result<()> {
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!(
+                "DELETE FROM {table_name} WHERE stream = $1 AND consumer = $2 AND callback = $3"
+            );
+            let _ = client.update(
+                &sql,
+                None,
+                &[stream.into(), consumer.into(), callback.into()],
+            )?;
+
+            Ok(())
+        })
+    })
+}
+
+pub fn insert_responder(
+    table_name: &str,
+    subject: &str,
+    fn_name: &str,
+    queue_group: Option<&str>,
+) -> anyhow::Result<()> {
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!(
+                "INSERT INTO {table_name} (subject, callback, queue_group) VALUES ($1, $2, $3) \
+                 ON CONFLICT (subject) DO UPDATE \
+                 SET callback = EXCLUDED.callback, queue_group = EXCLUDED.queue_group"
+            );
+            let _ = client.update(
+                &sql,
+                None,
+                &[subject.into(), fn_name.into(), queue_group.into()],
+            )?;
+
+            Ok(())
+        })
+    })
+}
+
+pub fn delete_responder(table_name: &str, subject: &str) -> anyhow::Result<()> {
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!("DELETE FROM {table_name} WHERE subject = $1");
+            let _ = client.update(&sql, None, &[subject.into()])?;
+
+            Ok(())
+        })
+    })
+}
+
+pub fn fetch_services(table_name: &str) -> anyhow::Result<Vec<(String, String, Option<String>)>> {
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!("SELECT name, version, description FROM {table_name}");
+            let tuples = client.select(&sql, None, &[])?;
+            let services: Vec<(String, String, Option<String>)> = tuples
+                .into_iter()
+                .filter_map(|tuple| {
+                    let name = tuple.get_by_name::<String, _>("name");
+                    let version = tuple.get_by_name::<String, _>("version");
+                    let description = tuple.get_by_name::<String, _>("description");
+
+                    match (name, version, description) {
+                        (Ok(Some(name)), Ok(Some(version)), Ok(description)) => {
+                            Some((name, version, description))
+                        }
+                        _ => None,
+                    }
+                })
+                .collect();
+
+            Ok(services)
+        })
+    })
+}
+
+pub fn insert_service(
+    table_name: &str,
+    name: &str,
+    version: &str,
+    description: Option<&str>,
+) -> anyhow::Result<()> {
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!(
+                "INSERT INTO {table_name} (name, version, description) VALUES ($1, $2, $3) \
+                 ON CONFLICT (name) DO UPDATE \
+                 SET version = EXCLUDED.version, description = EXCLUDED.description"
+            );
+            let _ = client.update(
+                &sql,
+                None,
+                &[name.into(), version.into(), description.into()],
+            )?;
+
+            Ok(())
+        })
+    })
+}
+
+pub fn delete_service(table_name: &str, name: &str) -> anyhow::Result<()> {
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!("DELETE FROM {table_name} WHERE name = $1");
+            let _ = client.update(&sql, None, &[name.into()])?;
+
+            Ok(())
+        })
+    })
+}
+
+pub fn fetch_service_endpoints(
+    table_name: &str,
+) -> anyhow::Result<Vec<(String, String, String, String)>> {
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!("SELECT service, endpoint, subject, callback FROM {table_name}");
+            let tuples = client.select(&sql, None, &[])?;
+            let endpoints: Vec<(String, String, String, String)> = tuples
+                .into_iter()
+                .filter_map(|tuple| {
+                    let service = tuple.get_by_name::<String, _>("service");
+                    let endpoint = tuple.get_by_name::<String, _>("endpoint");
+                    let subject = tuple.get_by_name::<String, _>("subject");
+                    let callback = tuple.get_by_name::<String, _>("callback");
+
+                    match (service, endpoint, subject, callback) {
+                        (
+                            Ok(Some(service)),
+                            Ok(Some(endpoint)),
+                            Ok(Some(subject)),
+                            Ok(Some(callback)),
+                        ) => Some((service, endpoint, subject, callback)),
+                        _ => None,
+                    }
+                })
+                .collect();
+
+            Ok(endpoints)
+        })
+    })
+}
+
+pub fn insert_service_endpoint(
+    table_name: &str,
+    service: &str,
+    endpoint: &str,
+    subject: &str,
+    fn_name: &str,
+) -> anyhow::Result<()> {
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!(
+                "INSERT INTO {table_name} (service, endpoint, subject, callback) \
+                 VALUES ($1, $2, $3, $4) \
+                 ON CONFLICT (service, endpoint) DO UPDATE \
+                 SET subject = EXCLUDED.subject, callback = EXCLUDED.callback"
+            );
+            let _ = client.update(
+                &sql,
+                None,
+                &[
+                    service.into(),
+                    endpoint.into(),
+                    subject.into(),
+                    fn_name.into(),
+                ],
+            )?;
+
+            Ok(())
+        })
+    })
+}
+
+pub fn delete_service_endpoint(
+    table_name: &str,
+    service: &str,
+    endpoint: &str,
+) -> anyhow::Result<()> {
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!("DELETE FROM {table_name} WHERE service = $1 AND endpoint = $2");
+            let _ = client.update(&sql, None, &[service.into(), endpoint.into()])?;
+
+            Ok(())
+        })
+    })
+}
+
+pub fn insert_dead_letters(
+    table_name: &str,
+    callback: &str,
+    messages: &[CallbackMessage],
+    error: &str,
+    attempts: u32,
+) -> anyhow::Result<()> {
+    let attempts = i32::try_from(attempts)?;
+
+    try_spi(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!(
+                "INSERT INTO {table_name} (subject, callback, payload, headers, error, attempts) \
+                 VALUES ($1, $2, $3, $4, $5, $6)"
+            );
+
+            for message in messages {
+                let _ = client.update(
+                    &sql,
+                    None,
+                    &[
+                        message.subject.as_str().into(),
+                        callback.into(),
+                        message.payload.as_slice().into(),
+                        message.headers.clone().map(pgrx::JsonB).into(),
+                        error.into(),
+                        attempts.into(),
+                    ],
+                )?;
+            }
+
+            Ok(())
+        })
     })
+}
+
+/// Resolves the argument shape of a callback. Of several functions with the same name,
+/// the first one taking supported arguments is called, as the arguments passed select it.
+pub fn fetch_callback_signature(callback: &str) -> anyhow::Result<CallbackSignature> {
+    fetch_function_types(callback)?
+        .into_iter()
+        .find_map(|(arg_types, _)| CallbackSignature::from_arg_types(&arg_types))
+        .ok_or_else(|| anyhow::anyhow!("Function '{callback}' has unsupported argument types"))
+}
+
+pub fn fetch_responder_function(callback: &str) -> anyhow::Result<ResponderFunction> {
+    fetch_function_types(callback)?
+        .into_iter()
+        .find_map(|(arg_types, return_type)| {
+            let signature = CallbackSignature::from_arg_types(&arg_types)
+                .filter(|signature| !signature.batch)?;
+            let return_type = CallbackPayload::from_type(return_type)?;
+
+            Some(ResponderFunction {
+                signature,
+                return_type,
+            })
+        })
+        .ok_or_else(|| {
+            anyhow::anyhow!("Function '{callback}' has unsupported argument or return types")
+        })
+}
+
+/// Argument and return types of every function with the schema-qualified name
+/// `callback`, including overloads.
+fn fetch_function_types(callback: &str) -> anyhow::Result<Vec<(Vec<pg_sys::Oid>, pg_sys::Oid)>> {
+    let (schema, name) = callback
+        .split_once('.')
+        .ok_or_else(|| anyhow::anyhow!("Function name '{callback}' is not schema-qualified"))?;
+
+    let functions = try_spi(|| {
+        Spi::connect_mut(|client| {
+            let tuples = client.select(
+                "SELECT p.proargtypes::oid[] AS arg_types, p.prorettype AS return_type \
+                 FROM pg_proc p JOIN pg_namespace n ON n.oid = p.pronamespace \
+                 WHERE n.nspname = $1 AND p.proname = $2 ORDER BY p.oid",
+                None,
+                &[schema.into(), name.into()],
+            )?;
+
+            let functions: Vec<_> = tuples
+                .into_iter()
+                .filter_map(|tuple| {
+                    let arg_types = tuple
+                        .get_by_name::<Vec<pg_sys::Oid>, _>("arg_types")
+                        .ok()??;
+                    let return_type = tuple.get_by_name::<pg_sys::Oid, _>("return_type").ok()??;
+
+                    Some((arg_types, return_type))
+                })
+                .collect();
+
+            Ok(functions)
+        })
+    })?;
+
+    anyhow::ensure!(
+        !functions.is_empty(),
+        "Function '{callback}' does not exist"
+    );
+
+    Ok(functions)
+}
+
+/// Calls a subscription callback for `messages`: a batch callback is called once with
+/// all of them, any other callback once per message.
+pub fn call_function(
+    callback: &str,
+    signature: CallbackSignature,
+    messages: &[CallbackMessage],
+) -> Result<(), CallError> {
+    if signature.batch {
+        return call_function_with_args(callback, &batch_callback_args(signature, messages)?);
+    }
+
+    messages.iter().try_for_each(|message| {
+        call_function_with_args(callback, &callback_args(signature, message)?)
+    })
+}
+
+/// Calls a responder function and returns its result encoded as a reply payload.
+/// A `NULL` result becomes an empty payload.
+pub fn call_responder(
+    callback: &str,
+    function: ResponderFunction,
+    message: &CallbackMessage,
+) -> Result<Vec<u8>, CallError> {
+    let args = callback_args(function.signature, message)?;
+    let sql = callback_query(callback, args.len())?;
+
+    PgTryBuilder::new(|| {
+        Spi::connect_mut(|client| {
+            let tuples = client
+                .update(&sql, None, &args)
+                .map_err(|err| CallError::Other(err.into()))?;
+            let tuple = tuples.first();
+
+            let payload = match function.return_type {
+                CallbackPayload::Bytea => tuple.get_one::<Vec<u8>>(),
+                CallbackPayload::Text => {
+                    tuple.get_one::<String>().map(|v| v.map(String::into_bytes))
+                }
+                CallbackPayload::Json => tuple
+                    .get_one::<pgrx::Json>()
+                    .map(|v| v.map(|v| v.0.to_string().into_bytes())),
+                CallbackPayload::Jsonb => tuple
+                    .get_one::<pgrx::JsonB>()
+                    .map(|v| v.map(|v| v.0.to_string().into_bytes())),
+            }
+            .map_err(|err| CallError::Other(err.into()))?;
+
+            Ok(payload.unwrap_or_default())
+        })
+    })
+    .catch_others(catch_call_error)
     .execute()
 }
 
-pub fn call_function(callback: &str, data: &[u8]) -> Result<(), CallError> {
+fn callback_args<'a>(
+    signature: CallbackSignature,
+    message: &'a CallbackMessage,
+) -> Result<Vec<DatumWithOid<'a>>, CallError> {
+    let payload: DatumWithOid<'_> = match signature.payload {
+        CallbackPayload::Bytea => message.payload.as_slice().into(),
+        CallbackPayload::Text => std::str::from_utf8(&message.payload)
+            .map_err(|err| CallError::Other(anyhow::anyhow!("Payload is not UTF-8: {err}")))?
+            .into(),
+        CallbackPayload::Json => pgrx::Json(
+            serde_json::from_slice(&message.payload)
+                .map_err(|err| CallError::Other(anyhow::anyhow!("Payload is not JSON: {err}")))?,
+        )
+        .into(),
+        CallbackPayload::Jsonb => pgrx::JsonB(
+            serde_json::from_slice(&message.payload)
+                .map_err(|err| CallError::Other(anyhow::anyhow!("Payload is not JSON: {err}")))?,
+        )
+        .into(),
+    };
+
+    if signature.with_message_info {
+        Ok(vec![
+            payload,
+            message.subject.as_str().into(),
+            message.headers.clone().map(pgrx::JsonB).into(),
+            message.reply.as_deref().into(),
+        ])
+    } else {
+        Ok(vec![payload])
+    }
+}
+
+/// Checks that a payload can be passed to a callback taking `payload`, so that a malformed
+/// message can be set aside before it fails the whole batch it would be part of.
+pub fn check_payload(payload: CallbackPayload, data: &[u8]) -> anyhow::Result<()> {
+    match payload {
+        CallbackPayload::Bytea => {}
+        CallbackPayload::Text => {
+            let _ = std::str::from_utf8(data)
+                .map_err(|err| anyhow::anyhow!("Payload is not UTF-8: {err}"))?;
+        }
+        CallbackPayload::Json | CallbackPayload::Jsonb => {
+            let _ = serde_json::from_slice::<serde::de::IgnoredAny>(data)
+                .map_err(|err| anyhow::anyhow!("Payload is not JSON: {err}"))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn batch_callback_args<'a>(
+    signature: CallbackSignature,
+    messages: &'a [CallbackMessage],
+) -> Result<Vec<DatumWithOid<'a>>, CallError> {
+    let payloads: DatumWithOid<'_> = match signature.payload {
+        CallbackPayload::Bytea => messages
+            .iter()
+            .map(|message| message.payload.as_slice())
+            .collect::<Vec<_>>()
+            .into(),
+        CallbackPayload::Text => messages
+            .iter()
+            .map(|message| std::str::from_utf8(&message.payload))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| CallError::Other(anyhow::anyhow!("Payload is not UTF-8: {err}")))?
+            .into(),
+        CallbackPayload::Json => messages
+            .iter()
+            .map(|message| serde_json::from_slice(&message.payload).map(pgrx::Json))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| CallError::Other(anyhow::anyhow!("Payload is not JSON: {err}")))?
+            .into(),
+        CallbackPayload::Jsonb => messages
+            .iter()
+            .map(|message| serde_json::from_slice(&message.payload).map(pgrx::JsonB))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| CallError::Other(anyhow::anyhow!("Payload is not JSON: {err}")))?
+            .into(),
+    };
+
+    if signature.with_message_info {
+        Ok(vec![
+            payloads,
+            messages
+                .iter()
+                .map(|message| message.subject.as_str())
+                .collect::<Vec<_>>()
+                .into(),
+            messages
+                .iter()
+                .map(|message| message.headers.clone().map(pgrx::JsonB))
+                .collect::<Vec<_>>()
+                .into(),
+            messages
+                .iter()
+                .map(|message| message.reply.as_deref())
+                .collect::<Vec<_>>()
+                .into(),
+        ])
+    } else {
+        Ok(vec![payloads])
+    }
+}
+
+/// Calls a KV watch callback for a change and records its revision for the callback in
+/// the same transaction. A watch restarts with the latest revision of every key, so
+/// changes the callback has already processed are skipped.
+pub fn call_kv_watch_function(
+    table_name: &str,
+    bucket: &str,
+    key_pattern: &str,
+    callback: &str,
+    entry: &KvWatchEntry,
+) -> Result<(), CallError> {
+    let revision = i64::try_from(entry.revision).map_err(|_| {
+        CallError::Other(anyhow::anyhow!(
+            "Revision {} is out of range",
+            entry.revision
+        ))
+    })?;
+    let watch = "bucket = $1 AND key_pattern = $2 AND callback = $3";
+
+    let last_revision: Option<i64> = fetch_watch_position(
+        &format!("SELECT last_revision FROM {table_name} WHERE {watch}"),
+        &[bucket.into(), key_pattern.into(), callback.into()],
+    )?;
+
+    if last_revision.is_some_and(|last_revision| last_revision >= revision) {
+        return Ok(());
+    }
+
+    call_function_with_args(
+        callback,
+        &[
+            entry.key.as_str().into(),
+            entry.value.as_slice().into(),
+            revision.into(),
+            entry.operation.into(),
+        ],
+    )?;
+
+    update_watch_position(
+        &format!("UPDATE {table_name} SET last_revision = $4 WHERE {watch}"),
+        &[
+            bucket.into(),
+            key_pattern.into(),
+            callback.into(),
+            revision.into(),
+        ],
+    )
+}
+
+/// Calls an object watch callback for a change and records its publish time for the
+/// callback in the same transaction, so that changes the callback has already processed
+/// are skipped when the watch restarts.
+pub fn call_object_watch_function(
+    table_name: &str,
+    store: &str,
+    callback: &str,
+    entry: &ObjectWatchEntry,
+) -> Result<(), CallError> {
+    let size = i64::try_from(entry.size).unwrap_or(i64::MAX);
+    let modified = entry
+        .modified
+        .map(|modified| {
+            i64::try_from(modified).map_err(|_| {
+                CallError::Other(anyhow::anyhow!(
+                    "Modification time {modified} is out of range"
+                ))
+            })
+        })
+        .transpose()?;
+    let watch = "store = $1 AND callback = $2";
+
+    let last_modified: Option<i64> = fetch_watch_position(
+        &format!("SELECT last_modified_ns FROM {table_name} WHERE {watch}"),
+        &[store.into(), callback.into()],
+    )?;
+
+    if let (Some(last_modified), Some(modified)) = (last_modified, modified) {
+        if last_modified >= modified {
+            return Ok(());
+        }
+    }
+
+    call_function_with_args(
+        callback,
+        &[
+            entry.name.as_str().into(),
+            entry.nuid.as_str().into(),
+            size.into(),
+            entry.operation.into(),
+        ],
+    )?;
+
+    match modified {
+        Some(modified) => update_watch_position(
+            &format!("UPDATE {table_name} SET last_modified_ns = $3 WHERE {watch}"),
+            &[store.into(), callback.into(), modified.into()],
+        ),
+        None => Ok(()),
+    }
+}
+
+fn call_function_with_args(callback: &str, args: &[DatumWithOid<'_>]) -> Result<(), CallError> {
+    let sql = callback_query(callback, args.len())?;
+
+    PgTryBuilder::new(|| {
+        Spi::connect_mut(|client| {
+            let _ = client
+                .update(&sql, None, args)
+                .map_err(|err| CallError::Other(err.into()))?;
+            Ok(())
+        })
+    })
+    .catch_others(catch_call_error)
+    .execute()
+}
+
+/// Reads the position up to which a watch callback has processed the changes, `None`
+/// if it has not processed any yet.
+fn fetch_watch_position<T: FromDatum + IntoDatum>(
+    sql: &str,
+    args: &[DatumWithOid<'_>],
+) -> Result<Option<T>, CallError> {
+    PgTryBuilder::new(|| {
+        Spi::connect_mut(|client| {
+            let tuples = client
+                .select(sql, None, args)
+                .map_err(|err| CallError::Other(err.into()))?;
+
+            if tuples.is_empty() {
+                return Ok(None);
+            }
+
+            tuples
+                .first()
+                .get_one::<T>()
+                .map_err(|err| CallError::Other(err.into()))
+        })
+    })
+    .catch_others(catch_call_error)
+    .execute()
+}
+
+fn update_watch_position(sql: &str, args: &[DatumWithOid<'_>]) -> Result<(), CallError> {
+    PgTryBuilder::new(|| {
+        Spi::connect_mut(|client| {
+            let _ = client
+                .update(sql, None, args)
+                .map_err(|err| CallError::Other(err.into()))?;
+            Ok(())
+        })
+    })
+    .catch_others(catch_call_error)
+    .execute()
+}
+
+fn callback_query(callback: &str, arg_count: usize) -> Result<String, CallError> {
     if !callback
         .chars()
         .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
@@ -117,16 +1048,31 @@ pub fn call_function(callback: &str, data: &[u8]) -> Result<(), CallError> {
         )));
     }
 
-    PgTryBuilder::new(|| {
-        Spi::connect_mut(|client| {
-            let sql = format!("SELECT {callback}($1)");
-            let _ = client
-                .update(&sql, None, &[data.into()])
-                .map_err(|err| CallError::Other(err.into()))?;
-            Ok(())
+    let placeholders = (1..=arg_count)
+        .map(|n| format!("${n}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    Ok(format!("SELECT {callback}({placeholders})"))
+}
+
+/// Runs `f`, turning a PostgreSQL error raised in it into an error result.
+fn try_spi<T>(f: impl FnOnce() -> anyhow::Result<T> + UnwindSafe) -> anyhow::Result<T> {
+    PgTryBuilder::new(f)
+        .catch_others(|e| match e {
+            pgrx::pg_sys::panic::CaughtError::PostgresError(err) => Err(anyhow::anyhow!(
+                "Code '{}': {}. ({:?})",
+                err.sql_error_code(),
+                err.message(),
+                err.hint()
+            )),
+            _ => Err(anyhow::anyhow!("{e:?}")),
         })
-    })
-    .catch_others(|e| match e {
+        .execute()
+}
+
+fn catch_call_error<T>(e: pgrx::pg_sys::panic::CaughtError) -> Result<T, CallError> {
+    match e {
         pgrx::pg_sys::panic::CaughtError::PostgresError(err) => {
             if err.sql_error_code() == PgSqlErrorCode::ERRCODE_UNDEFINED_FUNCTION {
                 Err(CallError::NotFound)
@@ -140,6 +1086,5 @@ pub fn call_function(callback: &str, data: &[u8]) -> Result<(), CallError> {
             }
         }
         _ => Err(CallError::Other(anyhow::anyhow!("{e:?}"))),
-    })
-    .execute()
+    }
 }
@@ -0,0 +1,191 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use tokio::sync::Notify;
+
+use crate::config::{CallbackQueueOptions, QueueOverflowPolicy};
+
+/// Queue of messages received by subscription tasks and waiting for their callbacks in
+/// the main loop of the worker.
+///
+/// At most `limit` messages are queued; a message arriving at a full queue is handled
+/// according to the overflow policy, and messages dropped by it are counted.
+pub(super) struct BoundedQueue<T> {
+    state: Mutex<QueueState<T>>,
+    space: Notify,
+}
+
+struct QueueState<T> {
+    items: VecDeque<T>,
+    options: CallbackQueueOptions,
+    dropped: u64,
+}
+
+impl<T> BoundedQueue<T> {
+    pub(super) fn new(options: CallbackQueueOptions) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                items: VecDeque::new(),
+                options,
+                dropped: 0,
+            }),
+            space: Notify::new(),
+        }
+    }
+
+    /// Queues `item`. With [`QueueOverflowPolicy::Block`] waits until the queue has room.
+    pub(super) async fn push(&self, item: T) {
+        loop {
+            let mut notified = std::pin::pin!(self.space.notified());
+            // Registers for the wakeup before checking the queue, so that a message
+            // popped in between is not missed.
+            let _ = notified.as_mut().enable();
+
+            {
+                let mut state = self.lock();
+
+                if state.items.len() < state.options.limit {
+                    state.items.push_back(item);
+                    return;
+                }
+
+                match state.options.overflow {
+                    QueueOverflowPolicy::Block => {}
+                    QueueOverflowPolicy::DropOldest => {
+                        let _ = state.items.pop_front();
+                        state.items.push_back(item);
+                        state.dropped += 1;
+                        return;
+                    }
+                    QueueOverflowPolicy::DropNewest => {
+                        state.dropped += 1;
+                        return;
+                    }
+                }
+            }
+
+            notified.await;
+        }
+    }
+
+    pub(super) fn pop(&self) -> Option<T> {
+        let item = self.lock().items.pop_front();
+
+        if item.is_some() {
+            self.space.notify_waiters();
+        }
+
+        item
+    }
+
+    /// Maximum number of queued items.
+    pub(super) fn limit(&self) -> usize {
+        self.lock().options.limit
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.lock().items.len()
+    }
+
+    /// Counts the queued items by the key returned by `key`, skipping items without one.
+    pub(super) fn count_by<K: Eq + Hash>(
+        &self,
+        key: impl Fn(&T) -> Option<K>,
+    ) -> HashMap<K, usize> {
+        let mut counts = HashMap::new();
+
+        for key in self.lock().items.iter().filter_map(key) {
+            *counts.entry(key).or_default() += 1;
+        }
+
+        counts
+    }
+
+    /// Total number of messages dropped because the queue was full.
+    pub(super) fn dropped(&self) -> u64 {
+        self.lock().dropped
+    }
+
+    pub(super) fn set_options(&self, options: CallbackQueueOptions) {
+        self.lock().options = options;
+        self.space.notify_waiters();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::*;
+
+    fn options(limit: usize, overflow: QueueOverflowPolicy) -> CallbackQueueOptions {
+        CallbackQueueOptions { limit, overflow }
+    }
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_drop_newest() {
+        let rt = runtime();
+        let queue = BoundedQueue::new(options(2, QueueOverflowPolicy::DropNewest));
+
+        for i in 0..4 {
+            rt.block_on(queue.push(i));
+        }
+
+        assert_eq!(queue.dropped(), 2);
+        assert_eq!(queue.pop(), Some(0));
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let rt = runtime();
+        let queue = BoundedQueue::new(options(2, QueueOverflowPolicy::DropOldest));
+
+        for i in 0..4 {
+            rt.block_on(queue.push(i));
+        }
+
+        assert_eq!(queue.dropped(), 2);
+        assert_eq!(queue.pop(), Some(2));
+        assert_eq!(queue.pop(), Some(3));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn test_block_until_popped() {
+        let rt = runtime();
+        let queue = Arc::new(BoundedQueue::new(options(1, QueueOverflowPolicy::Block)));
+
+        rt.block_on(queue.push(0));
+
+        let task = rt.spawn({
+            let queue = queue.clone();
+            async move { queue.push(1).await }
+        });
+
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(!task.is_finished());
+        assert_eq!(queue.len(), 1);
+
+        assert_eq!(queue.pop(), Some(0));
+        rt.block_on(task).unwrap();
+
+        assert_eq!(queue.dropped(), 0);
+        assert_eq!(queue.pop(), Some(1));
+    }
+}
@@ -3,7 +3,8 @@ use std::{borrow::Cow, collections::HashMap};
 use pgrx::{PgTryBuilder, Spi};
 
 use crate::constants::{
-    DEFAULT_NATS_CAPACITY, DEFAULT_NATS_HOST, DEFAULT_NATS_PORT, DEFAULT_NOTIFY_SUBJECT,
+    DEFAULT_CALLBACK_QUEUE_LIMIT, DEFAULT_NATS_CAPACITY, DEFAULT_NATS_HOST, DEFAULT_NATS_PORT,
+    DEFAULT_NOTIFY_SUBJECT, DEFAULT_SUBSCRIBER_WORKERS,
 };
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -28,12 +29,47 @@ pub struct NatsConnectionOptions {
     pub tls: Option<NatsTlsOptions>,
 }
 
+/// What a subscriber worker does with a message received while its callback queue is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "sub", derive(serde::Serialize, serde::Deserialize))]
+pub enum QueueOverflowPolicy {
+    /// Stop reading the subscription until the queue has room again.
+    #[default]
+    Block,
+    /// Drop the oldest queued message to make room for the new one.
+    DropOldest,
+    /// Drop the new message.
+    DropNewest,
+}
+
+impl QueueOverflowPolicy {
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        match value {
+            "block" => Ok(Self::Block),
+            "drop_oldest" => Ok(Self::DropOldest),
+            "drop_newest" => Ok(Self::DropNewest),
+            _ => anyhow::bail!(
+                "Unknown queue overflow policy \"{value}\", expected \"block\", \"drop_oldest\" or \"drop_newest\""
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "sub", derive(serde::Serialize, serde::Deserialize))]
+pub struct CallbackQueueOptions {
+    pub limit: usize,
+    pub overflow: QueueOverflowPolicy,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "sub", derive(serde::Serialize, serde::Deserialize))]
 pub struct Config {
     pub nats_opt: NatsConnectionOptions,
     pub notify_subject: String,
     pub patroni_url: Option<String>,
+    pub workers: usize,
+    pub queue: CallbackQueueOptions,
 }
 
 pub fn fetch_config(fdw_extension_name: &str) -> Config {
@@ -52,9 +88,7 @@ pub fn fetch_config(fdw_extension_name: &str) -> Config {
     // SAFETY:
     //
     // 1. We pass a correct arguments to `GetForeignServerByName` and check if the result is null.
-    // 2. We ensure that the `options_list` is not null before iterating over it.
-    // 3. We ensure that the `defname` and `arg` fields are not null before accessing them.
-    // 4. Node casting is safe according to Postgres documentation
+    // 2. The server options list is owned by the foreign server entry returned above.
     unsafe {
         let server = pgrx::pg_sys::GetForeignServerByName(fdw_server_name.as_ptr(), true);
 
@@ -62,47 +96,71 @@ pub fn fetch_config(fdw_extension_name: &str) -> Config {
             return parse_config(&options);
         }
 
-        let options_list = (*server).options;
-        if !options_list.is_null() {
-            let list: pgrx::PgList<pgrx::pg_sys::DefElem> = pgrx::PgList::from_pg(options_list);
+        options = parse_def_elem_options((*server).options);
+    };
 
-            for def_elem in list.iter_ptr() {
-                if def_elem.is_null() || (*def_elem).defname.is_null() {
-                    continue;
-                }
+    parse_config(&options)
+}
 
-                let key = std::ffi::CStr::from_ptr((*def_elem).defname)
-                    .to_string_lossy()
-                    .to_string();
+/// Collects string options from a list of `DefElem` nodes, as stored in the
+/// `options` field of foreign servers and foreign tables.
+///
+/// # Safety
+///
+/// `options_list` must be null or point to a valid Postgres `List` of `DefElem` nodes.
+pub unsafe fn parse_def_elem_options(
+    options_list: *mut pgrx::pg_sys::List,
+) -> HashMap<Cow<'static, str>, Cow<'static, str>> {
+    let mut options = HashMap::new();
 
-                if (*def_elem).arg.is_null() {
-                    continue;
-                }
+    if options_list.is_null() {
+        return options;
+    }
 
-                let node = (*def_elem).arg;
+    // SAFETY:
+    //
+    // 1. The caller guarantees that `options_list` is a valid list of `DefElem` nodes.
+    // 2. We ensure that the `defname` and `arg` fields are not null before accessing them.
+    // 3. Node casting is safe according to Postgres documentation
+    unsafe {
+        let list: pgrx::PgList<pgrx::pg_sys::DefElem> = pgrx::PgList::from_pg(options_list);
 
-                if (*node).type_ != pgrx::pg_sys::NodeTag::T_String {
-                    continue;
-                }
+        for def_elem in list.iter_ptr() {
+            if def_elem.is_null() || (*def_elem).defname.is_null() {
+                continue;
+            }
+
+            let key = std::ffi::CStr::from_ptr((*def_elem).defname)
+                .to_string_lossy()
+                .to_string();
 
-                #[cfg(feature = "pg14")]
-                let val = (*(node as *mut pgrx::pg_sys::Value)).val.str_;
+            if (*def_elem).arg.is_null() {
+                continue;
+            }
 
-                #[cfg(not(feature = "pg14"))]
-                let val = (*(node as *mut pgrx::pg_sys::String)).sval;
+            let node = (*def_elem).arg;
+
+            if (*node).type_ != pgrx::pg_sys::NodeTag::T_String {
+                continue;
+            }
 
-                if val.is_null() {
-                    continue;
-                }
+            #[cfg(feature = "pg14")]
+            let val = (*(node as *mut pgrx::pg_sys::Value)).val.str_;
 
-                let value = std::ffi::CStr::from_ptr(val).to_string_lossy().to_string();
+            #[cfg(not(feature = "pg14"))]
+            let val = (*(node as *mut pgrx::pg_sys::String)).sval;
 
-                let _ = options.insert(key.into(), value.into());
+            if val.is_null() {
+                continue;
             }
+
+            let value = std::ffi::CStr::from_ptr(val).to_string_lossy().to_string();
+
+            let _ = options.insert(key.into(), value.into());
         }
-    };
+    }
 
-    parse_config(&options)
+    options
 }
 
 pub fn parse_config(options: &HashMap<Cow<'_, str>, Cow<'_, str>>) -> Config {
@@ -144,6 +202,23 @@ pub fn parse_config(options: &HashMap<Cow<'_, str>, Cow<'_, str>>) -> Config {
 
     let patroni_url = options.get("patroni_url").map(|v| v.to_string());
 
+    let workers = options
+        .get("workers")
+        .and_then(|w| w.parse::<usize>().ok())
+        .filter(|w| *w > 0)
+        .unwrap_or(DEFAULT_SUBSCRIBER_WORKERS);
+
+    let queue_limit = options
+        .get("queue_limit")
+        .and_then(|l| l.parse::<usize>().ok())
+        .filter(|l| *l > 0)
+        .unwrap_or(DEFAULT_CALLBACK_QUEUE_LIMIT);
+
+    let queue_overflow = options
+        .get("queue_overflow")
+        .and_then(|v| QueueOverflowPolicy::parse(v).ok())
+        .unwrap_or_default();
+
     Config {
         nats_opt: NatsConnectionOptions {
             host,
@@ -153,7 +228,33 @@ pub fn parse_config(options: &HashMap<Cow<'_, str>, Cow<'_, str>>) -> Config {
         },
         notify_subject,
         patroni_url,
+        workers,
+        queue: CallbackQueueOptions {
+            limit: queue_limit,
+            overflow: queue_overflow,
+        },
+    }
+}
+
+/// Checks the options of the `pgnats_fdw` server that [`parse_config`] would otherwise
+/// replace by their defaults when they are invalid.
+pub fn validate_server_options(
+    options: &HashMap<Cow<'_, str>, Cow<'_, str>>,
+) -> anyhow::Result<()> {
+    for name in ["workers", "queue_limit"] {
+        if let Some(value) = options.get(name) {
+            anyhow::ensure!(
+                value.parse::<usize>().is_ok_and(|v| v > 0),
+                "Option \"{name}\" must be a positive integer, got \"{value}\""
+            );
+        }
+    }
+
+    if let Some(value) = options.get("queue_overflow") {
+        let _ = QueueOverflowPolicy::parse(value)?;
     }
+
+    Ok(())
 }
 
 pub fn fetch_fdw_server_name(fdw_name: &str) -> Option<String> {
@@ -175,3 +276,30 @@ pub fn fetch_fdw_server_name(fdw_name: &str) -> Option<String> {
     .catch_others(|_| None)
     .execute()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options<'a>(pairs: &[(&'a str, &'a str)]) -> HashMap<Cow<'a, str>, Cow<'a, str>> {
+        pairs
+            .iter()
+            .map(|(k, v)| (Cow::Borrowed(*k), Cow::Borrowed(*v)))
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_server_options() {
+        assert!(validate_server_options(&options(&[("host", "localhost")])).is_ok());
+        assert!(validate_server_options(&options(&[
+            ("workers", "4"),
+            ("queue_limit", "100"),
+            ("queue_overflow", "drop_oldest"),
+        ]))
+        .is_ok());
+
+        assert!(validate_server_options(&options(&[("queue_overflow", "drop-oldest")])).is_err());
+        assert!(validate_server_options(&options(&[("queue_limit", "0")])).is_err());
+        assert!(validate_server_options(&options(&[("workers", "two")])).is_err());
+    }
+}
@@ -5,3 +5,5 @@ pub const DEFAULT_NATS_HOST: &str = "127.0.0.1";
 pub const DEFAULT_NATS_PORT: u16 = 4222;
 pub const DEFAULT_NATS_CAPACITY: usize = 128;
 pub const DEFAULT_NOTIFY_SUBJECT: &str = "pgnats.postgresql.replication.status";
+pub const DEFAULT_SUBSCRIBER_WORKERS: usize = 1;
+pub const DEFAULT_CALLBACK_QUEUE_LIMIT: usize = 10_000;
@@ -1,6 +1,11 @@
 use std::cell::RefCell;
 
-use crate::{config::fetch_config, constants::FDW_EXTENSION_NAME, nats_client::NatsClient};
+use async_nats::jetstream::AckKind;
+use pgrx::{register_xact_callback, PgXactCallbackEvent};
+
+use crate::{
+    config::fetch_config, constants::FDW_EXTENSION_NAME, error, nats_client::NatsClient, warn,
+};
 
 thread_local! {
     pub static CTX: RefCell<Context> = RefCell::new(create_context());
@@ -9,6 +14,43 @@ thread_local! {
 pub struct Context {
     pub nats_connection: NatsClient,
     pub rt: tokio::runtime::Runtime,
+    /// Acknowledgements of fetched messages waiting for the current transaction to commit.
+    pub pending_acks: Vec<(String, AckKind)>,
+}
+
+impl Context {
+    /// Queues an acknowledgement of a fetched message. It is sent when the current
+    /// transaction commits and discarded if the transaction aborts. A transaction with
+    /// queued acknowledgements cannot be prepared, as neither would happen then.
+    pub fn queue_ack(&mut self, reply: &str, kind: AckKind) {
+        if self.pending_acks.is_empty() {
+            let _ = register_xact_callback(PgXactCallbackEvent::Commit, send_pending_acks);
+            let _ = register_xact_callback(PgXactCallbackEvent::Abort, || {
+                CTX.with_borrow_mut(|ctx| ctx.pending_acks.clear())
+            });
+            let _ = register_xact_callback(PgXactCallbackEvent::PrePrepare, || {
+                CTX.with_borrow_mut(|ctx| ctx.pending_acks.clear());
+                error!("Cannot PREPARE a transaction that has acknowledged NATS messages");
+            });
+        }
+
+        self.pending_acks.push((reply.to_string(), kind));
+    }
+}
+
+/// Sends the queued acknowledgements. The transaction has already committed at this
+/// point, so a failure can only be reported.
+pub fn send_pending_acks() {
+    CTX.with_borrow_mut(|ctx| {
+        for (reply, kind) in std::mem::take(&mut ctx.pending_acks) {
+            if let Err(err) = ctx
+                .rt
+                .block_on(ctx.nats_connection.ack_message(&reply, kind))
+            {
+                warn!("Failed to acknowledge message '{reply}': {err}");
+            }
+        }
+    })
 }
 
 // The extension is useless without tokio runtime. It has to panic if the runtime cannot be initialized.
@@ -21,5 +63,6 @@ fn create_context() -> Context {
             .enable_all()
             .build()
             .expect("Failed to initialize Tokio runtime"),
+        pending_acks: Vec::new(),
     }
 }
@@ -0,0 +1,608 @@
+//! Foreign tables over JetStream key-value buckets.
+//!
+//! ```sql
+//! CREATE FOREIGN TABLE cfg (key text, value jsonb, revision bigint)
+//!     SERVER nats OPTIONS (bucket 'config');
+//! ```
+//!
+//! Columns are matched by name: `key` (`text`), `value` (`bytea`, `text`,
+//! `json` or `jsonb`) and `revision` (`bigint`). Other columns read as `NULL`.
+//! A `key = <constant>` condition is pushed down as a single key lookup,
+//! otherwise the scan reads the latest value of every key in the bucket, which
+//! must already exist.
+//!
+//! Writes are not transactional: every modified row is written to the bucket
+//! right away and stays there if the transaction is rolled back.
+
+use std::ffi::{c_int, c_void, CStr, CString};
+
+use async_nats::jetstream::kv::Entry;
+use pgrx::{pg_guard, pg_sys as sys, FromDatum, IntoDatum, PgList, PgMemoryContexts, PgTupleDesc};
+
+use super::{
+    attribute_name, column_condition, raise, slot_attribute, store_datums, string_node_value,
+};
+use crate::{config::parse_def_elem_options, ctx::CTX, utils::ToBytes};
+
+pub(super) const BUCKET_OPTION: &str = "bucket";
+
+const KEY_COLUMN: &CStr = c"key";
+const VALUE_COLUMN: &str = "value";
+const REVISION_COLUMN: &str = "revision";
+
+/// Name of the row identity column added for `UPDATE` and `DELETE`.
+const KEY_ROW_IDENTITY: &CStr = c"pgnats_key";
+
+const INVALID_ATTNUM: sys::AttrNumber = 0;
+
+const KEY_LOOKUP_ROWS: f64 = 1.0;
+const BUCKET_SCAN_ROWS: f64 = 1000.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Key,
+    Value(sys::Oid),
+    Revision,
+    Other,
+}
+
+struct Row {
+    key: String,
+    value: Vec<u8>,
+    revision: u64,
+}
+
+impl From<Entry> for Row {
+    fn from(entry: Entry) -> Self {
+        Self {
+            key: entry.key,
+            value: entry.value.to_vec(),
+            revision: entry.revision,
+        }
+    }
+}
+
+pub(super) struct ScanState {
+    columns: Vec<Column>,
+    rows: Vec<Row>,
+    next: usize,
+}
+
+struct ModifyState {
+    bucket: String,
+    columns: Vec<Column>,
+    key_row_identity: sys::AttrNumber,
+}
+
+/// Commands supported by foreign tables over KV buckets.
+pub(super) const UPDATABLE_OPERATIONS: c_int = (1 << sys::CmdType::CMD_INSERT as c_int)
+    | (1 << sys::CmdType::CMD_UPDATE as c_int)
+    | (1 << sys::CmdType::CMD_DELETE as c_int);
+
+impl ScanState {
+    /// Fills `slot` with the next entry, returns `false` once the scan is exhausted.
+    ///
+    /// # Safety
+    ///
+    /// `slot` must be the scan slot of the foreign table.
+    pub(super) unsafe fn next_row(
+        &mut self,
+        slot: *mut sys::TupleTableSlot,
+    ) -> anyhow::Result<bool> {
+        let Some(row) = self.rows.get(self.next) else {
+            return Ok(false);
+        };
+        self.next += 1;
+
+        let datums = self
+            .columns
+            .iter()
+            .map(|column| column_datum(*column, row))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        // SAFETY: the caller guarantees that `slot` has one value per table column.
+        unsafe { store_datums(slot, datums) };
+
+        Ok(true)
+    }
+
+    pub(super) fn rewind(&mut self) {
+        self.next = 0;
+    }
+}
+
+/// Estimates the number of rows returned by a scan of the foreign table.
+///
+/// # Safety
+///
+/// `baserel` must be the planner relation of the foreign table `foreigntableid`.
+pub(super) unsafe fn estimate_rows(baserel: *mut sys::RelOptInfo, foreigntableid: sys::Oid) -> f64 {
+    // SAFETY: the caller guarantees that `baserel` is valid.
+    let key = unsafe {
+        find_key_restriction(
+            (*baserel).baserestrictinfo,
+            (*baserel).relid,
+            sys::get_attnum(foreigntableid, KEY_COLUMN.as_ptr()),
+        )
+    };
+
+    if key.is_some() {
+        KEY_LOOKUP_ROWS
+    } else {
+        BUCKET_SCAN_ROWS
+    }
+}
+
+/// Builds the private plan data of a scan, holding the pushed down key if any.
+///
+/// # Safety
+///
+/// `scan_clauses` must be the restriction clauses of the scan of `foreigntableid`.
+pub(super) unsafe fn plan_private(
+    scan_clauses: *mut sys::List,
+    scan_relid: sys::Index,
+    foreigntableid: sys::Oid,
+) -> *mut sys::List {
+    // SAFETY: the caller guarantees that `scan_clauses` is valid. The pushed
+    // down key is copied into a palloc'd `String` node so it survives plan copying.
+    unsafe {
+        let key = find_key_restriction(
+            scan_clauses,
+            scan_relid,
+            sys::get_attnum(foreigntableid, KEY_COLUMN.as_ptr()),
+        );
+
+        let mut fdw_private = PgList::<sys::Node>::new();
+        if let Some(key) = key.and_then(|key| CString::new(key).ok()) {
+            fdw_private.push(sys::makeString(sys::pstrdup(key.as_ptr())).cast());
+        }
+
+        fdw_private.into_pg()
+    }
+}
+
+/// Reads the bucket entries selected by the plan.
+///
+/// # Safety
+///
+/// `relation` must be the scanned foreign table and `fdw_private` the list
+/// built by [`plan_private`].
+pub(super) unsafe fn begin_scan(
+    relation: sys::Relation,
+    fdw_private: &PgList<sys::Node>,
+) -> anyhow::Result<ScanState> {
+    // SAFETY: the caller guarantees that `relation` is a valid, opened foreign
+    // table and that the private list only holds the `String` node of the key.
+    let (bucket, columns, key) = unsafe {
+        (
+            table_bucket((*relation).rd_id)?,
+            table_columns((*relation).rd_att)?,
+            fdw_private
+                .get_ptr(0)
+                .and_then(|node| string_node_value(node)),
+        )
+    };
+
+    Ok(ScanState {
+        columns,
+        rows: fetch_rows(bucket, key)?,
+        next: 0,
+    })
+}
+
+#[pg_guard]
+pub unsafe extern "C-unwind" fn add_foreign_update_targets(
+    root: *mut sys::PlannerInfo,
+    rtindex: sys::Index,
+    _target_rte: *mut sys::RangeTblEntry,
+    target_relation: sys::Relation,
+) {
+    // SAFETY: the planner passes a valid, opened target relation.
+    unsafe {
+        let key_attnum = sys::get_attnum((*target_relation).rd_id, KEY_COLUMN.as_ptr());
+        if key_attnum == INVALID_ATTNUM {
+            raise::<()>(Err(anyhow::anyhow!(
+                "Foreign table must have a \"key\" column to be updated"
+            )));
+        }
+
+        let tupdesc = PgTupleDesc::from_pg_unchecked((*target_relation).rd_att);
+        let Some(attr) = tupdesc.get((key_attnum - 1) as usize) else {
+            return;
+        };
+
+        #[cfg(any(feature = "pg14", feature = "pg15"))]
+        let varno = rtindex;
+
+        #[cfg(not(any(feature = "pg14", feature = "pg15")))]
+        let varno = rtindex as c_int;
+
+        let var = sys::makeVar(
+            varno,
+            key_attnum,
+            attr.atttypid,
+            attr.atttypmod,
+            attr.attcollation,
+            0,
+        );
+
+        sys::add_row_identity_var(root, var, rtindex, KEY_ROW_IDENTITY.as_ptr());
+    }
+}
+
+#[pg_guard]
+pub unsafe extern "C-unwind" fn begin_foreign_modify(
+    mtstate: *mut sys::ModifyTableState,
+    rinfo: *mut sys::ResultRelInfo,
+    _fdw_private: *mut sys::List,
+    _subplan_index: c_int,
+    eflags: c_int,
+) {
+    if eflags & sys::EXEC_FLAG_EXPLAIN_ONLY as c_int != 0 {
+        return;
+    }
+
+    // SAFETY: the executor passes valid modify and result relation states; for
+    // `UPDATE`/`DELETE` the outer plan carries the row identity added by
+    // `add_foreign_update_targets`.
+    unsafe {
+        let relation = (*rinfo).ri_RelationDesc;
+        let bucket = raise(table_bucket((*relation).rd_id));
+        let columns = raise(table_columns((*relation).rd_att));
+
+        if !columns.contains(&Column::Key) {
+            raise::<()>(Err(anyhow::anyhow!(
+                "Foreign table must have a \"key\" column to be modified"
+            )));
+        }
+
+        let key_row_identity = if (*mtstate).operation == sys::CmdType::CMD_INSERT {
+            INVALID_ATTNUM
+        } else {
+            let subplan = (*(*mtstate).ps.lefttree).plan;
+            sys::ExecFindJunkAttributeInTlist((*subplan).targetlist, KEY_ROW_IDENTITY.as_ptr())
+        };
+
+        let state = ModifyState {
+            bucket,
+            columns,
+            key_row_identity,
+        };
+
+        (*rinfo).ri_FdwState = PgMemoryContexts::CurrentMemoryContext
+            .leak_and_drop_on_delete(state)
+            .cast::<c_void>();
+    }
+}
+
+#[pg_guard]
+pub unsafe extern "C-unwind" fn exec_foreign_insert(
+    _estate: *mut sys::EState,
+    rinfo: *mut sys::ResultRelInfo,
+    slot: *mut sys::TupleTableSlot,
+    _plan_slot: *mut sys::TupleTableSlot,
+) -> *mut sys::TupleTableSlot {
+    // SAFETY: `ri_FdwState` was set by `begin_foreign_modify` and `slot` holds
+    // the new row of the foreign table.
+    unsafe {
+        let state = &*((*rinfo).ri_FdwState as *const ModifyState);
+        let key = raise(slot_key(state, slot));
+        let value = raise(slot_value(state, slot));
+
+        let revision = raise(put_value(state, key, value));
+        raise(store_revision(state, slot, revision));
+    }
+
+    slot
+}
+
+#[pg_guard]
+pub unsafe extern "C-unwind" fn exec_foreign_update(
+    _estate: *mut sys::EState,
+    rinfo: *mut sys::ResultRelInfo,
+    slot: *mut sys::TupleTableSlot,
+    plan_slot: *mut sys::TupleTableSlot,
+) -> *mut sys::TupleTableSlot {
+    // SAFETY: `ri_FdwState` was set by `begin_foreign_modify`, `slot` holds the
+    // updated row and `plan_slot` the row identity of the old one.
+    unsafe {
+        let state = &*((*rinfo).ri_FdwState as *const ModifyState);
+        let old_key = raise(row_identity_key(state, plan_slot));
+        let key = raise(slot_key(state, slot));
+
+        if key != old_key {
+            raise::<()>(Err(anyhow::anyhow!(
+                "Changing the key of a KV entry is not supported, delete and insert it instead"
+            )));
+        }
+
+        let value = raise(slot_value(state, slot));
+        let revision = raise(put_value(state, key, value));
+        raise(store_revision(state, slot, revision));
+    }
+
+    slot
+}
+
+#[pg_guard]
+pub unsafe extern "C-unwind" fn exec_foreign_delete(
+    _estate: *mut sys::EState,
+    rinfo: *mut sys::ResultRelInfo,
+    slot: *mut sys::TupleTableSlot,
+    plan_slot: *mut sys::TupleTableSlot,
+) -> *mut sys::TupleTableSlot {
+    // SAFETY: `ri_FdwState` was set by `begin_foreign_modify` and `plan_slot`
+    // holds the row identity of the deleted row.
+    unsafe {
+        let state = &*((*rinfo).ri_FdwState as *const ModifyState);
+        let key = raise(row_identity_key(state, plan_slot));
+
+        raise(CTX.with_borrow_mut(|ctx| {
+            ctx.rt
+                .block_on(ctx.nats_connection.delete_value(&state.bucket, key))
+        }));
+    }
+
+    slot
+}
+
+#[pg_guard]
+pub unsafe extern "C-unwind" fn end_foreign_modify(
+    _estate: *mut sys::EState,
+    rinfo: *mut sys::ResultRelInfo,
+) {
+    // SAFETY: the state itself is owned by the executor memory context and
+    // dropped together with it.
+    unsafe {
+        (*rinfo).ri_FdwState = std::ptr::null_mut();
+    }
+}
+
+/// Reads the `bucket` option of a foreign table.
+///
+/// # Safety
+///
+/// `relid` must be the OID of a foreign table.
+unsafe fn table_bucket(relid: sys::Oid) -> anyhow::Result<String> {
+    // SAFETY: `GetForeignTable` errors out for relations that are not foreign tables.
+    let options = unsafe { parse_def_elem_options((*sys::GetForeignTable(relid)).options) };
+
+    options
+        .get(BUCKET_OPTION)
+        .map(|bucket| bucket.to_string())
+        .ok_or_else(|| anyhow::anyhow!("Foreign table must have a \"{BUCKET_OPTION}\" option"))
+}
+
+/// Maps every column of the foreign table to the part of a KV entry it holds.
+///
+/// # Safety
+///
+/// `tupdesc` must point to a valid tuple descriptor.
+unsafe fn table_columns(tupdesc: sys::TupleDesc) -> anyhow::Result<Vec<Column>> {
+    // SAFETY: the caller guarantees that `tupdesc` is valid; it is not freed here.
+    let tupdesc = unsafe { PgTupleDesc::from_pg_unchecked(tupdesc) };
+
+    tupdesc
+        .iter()
+        .map(|attr| {
+            let Some(name) = attribute_name(attr) else {
+                return Ok(Column::Other);
+            };
+
+            let type_oid = attr.atttypid;
+
+            if name.as_bytes() == KEY_COLUMN.to_bytes() {
+                anyhow::ensure!(
+                    [sys::TEXTOID, sys::VARCHAROID].contains(&type_oid),
+                    "Column \"{name}\" must be of type text"
+                );
+                Ok(Column::Key)
+            } else if name == VALUE_COLUMN {
+                anyhow::ensure!(
+                    [sys::BYTEAOID, sys::TEXTOID, sys::JSONOID, sys::JSONBOID].contains(&type_oid),
+                    "Column \"{name}\" must be of type bytea, text, json or jsonb"
+                );
+                Ok(Column::Value(type_oid))
+            } else if name == REVISION_COLUMN {
+                anyhow::ensure!(
+                    type_oid == sys::INT8OID,
+                    "Column \"{name}\" must be of type bigint"
+                );
+                Ok(Column::Revision)
+            } else {
+                Ok(Column::Other)
+            }
+        })
+        .collect()
+}
+
+/// Looks for a `key = <constant>` condition among the restriction clauses of a scan.
+///
+/// # Safety
+///
+/// `clauses` must be null or a valid list of `RestrictInfo` nodes.
+unsafe fn find_key_restriction(
+    clauses: *mut sys::List,
+    varno: sys::Index,
+    key_attnum: sys::AttrNumber,
+) -> Option<String> {
+    if key_attnum == INVALID_ATTNUM {
+        return None;
+    }
+
+    // SAFETY: the caller guarantees that `clauses` is a list of `RestrictInfo` nodes.
+    unsafe {
+        PgList::<sys::RestrictInfo>::from_pg(clauses)
+            .iter_ptr()
+            .find_map(|rinfo| key_equality((*rinfo).clause.cast(), varno, key_attnum))
+    }
+}
+
+/// Extracts the constant from a `key = <constant>` operator expression.
+///
+/// # Safety
+///
+/// `node` must be null or point to a valid expression node.
+unsafe fn key_equality(
+    node: *mut sys::Node,
+    varno: sys::Index,
+    key_attnum: sys::AttrNumber,
+) -> Option<String> {
+    // SAFETY: the caller guarantees that `node` is valid; the constant comes from it.
+    unsafe {
+        let (opname, constant) = column_condition(node, varno, key_attnum)?;
+        if opname != "=" || ![sys::TEXTOID, sys::VARCHAROID].contains(&(*constant).consttype) {
+            return None;
+        }
+
+        String::from_datum((*constant).constvalue, (*constant).constisnull)
+    }
+}
+
+fn fetch_rows(bucket: String, key: Option<String>) -> anyhow::Result<Vec<Row>> {
+    CTX.with_borrow_mut(|ctx| {
+        let entries = ctx
+            .rt
+            .block_on(ctx.nats_connection.get_entries(bucket, key))?;
+
+        Ok(entries.into_iter().map(Row::from).collect())
+    })
+}
+
+fn column_datum(column: Column, row: &Row) -> anyhow::Result<Option<sys::Datum>> {
+    Ok(match column {
+        Column::Key => row.key.as_str().into_datum(),
+        Column::Value(type_oid) => match type_oid {
+            sys::BYTEAOID => row.value.as_slice().into_datum(),
+            sys::TEXTOID => std::str::from_utf8(&row.value)?.into_datum(),
+            sys::JSONOID => pgrx::Json(serde_json::from_slice(&row.value)?).into_datum(),
+            _ => pgrx::JsonB(serde_json::from_slice(&row.value)?).into_datum(),
+        },
+        Column::Revision => i64::try_from(row.revision)?.into_datum(),
+        Column::Other => None,
+    })
+}
+
+/// Reads the `key` column of a new row.
+///
+/// # Safety
+///
+/// `slot` must hold a row of the modified foreign table.
+unsafe fn slot_key(state: &ModifyState, slot: *mut sys::TupleTableSlot) -> anyhow::Result<String> {
+    let attnum = column_attnum(state, |column| column == Column::Key)
+        .ok_or_else(|| anyhow::anyhow!("Foreign table has no \"key\" column"))?;
+
+    // SAFETY: `attnum` comes from the descriptor of the modified table.
+    let (datum, is_null) = unsafe { slot_attribute(slot, attnum) };
+
+    // SAFETY: the `key` column is validated to be of a text type.
+    unsafe { String::from_datum(datum, is_null) }
+        .ok_or_else(|| anyhow::anyhow!("Column \"key\" must not be null"))
+}
+
+/// Reads the `value` column of a new row as the bytes stored in the bucket.
+///
+/// # Safety
+///
+/// `slot` must hold a row of the modified foreign table.
+unsafe fn slot_value(
+    state: &ModifyState,
+    slot: *mut sys::TupleTableSlot,
+) -> anyhow::Result<Vec<u8>> {
+    let Some((attnum, type_oid)) =
+        state
+            .columns
+            .iter()
+            .zip(1..)
+            .find_map(|(column, attnum)| match column {
+                Column::Value(type_oid) => Some((attnum, *type_oid)),
+                _ => None,
+            })
+    else {
+        anyhow::bail!("Foreign table has no \"{VALUE_COLUMN}\" column");
+    };
+
+    // SAFETY: `attnum` comes from the descriptor of the modified table.
+    let (datum, is_null) = unsafe { slot_attribute(slot, attnum) };
+    anyhow::ensure!(!is_null, "Column \"{VALUE_COLUMN}\" must not be null");
+
+    // SAFETY: the datum type is validated against `type_oid` in `table_columns`.
+    let value = unsafe {
+        match type_oid {
+            sys::BYTEAOID => Vec::<u8>::from_datum(datum, false).map(ToBytes::to_bytes),
+            sys::TEXTOID => String::from_datum(datum, false).map(ToBytes::to_bytes),
+            sys::JSONOID => pgrx::Json::from_datum(datum, false).map(ToBytes::to_bytes),
+            _ => pgrx::JsonB::from_datum(datum, false).map(ToBytes::to_bytes),
+        }
+    };
+
+    value.ok_or_else(|| anyhow::anyhow!("Column \"{VALUE_COLUMN}\" must not be null"))?
+}
+
+/// Reads the key of the row being updated or deleted.
+///
+/// # Safety
+///
+/// `plan_slot` must hold the output row of the modify plan.
+unsafe fn row_identity_key(
+    state: &ModifyState,
+    plan_slot: *mut sys::TupleTableSlot,
+) -> anyhow::Result<String> {
+    anyhow::ensure!(
+        state.key_row_identity != INVALID_ATTNUM,
+        "Row identity of the modified KV entry is missing"
+    );
+
+    // SAFETY: `key_row_identity` was resolved against the plan target list.
+    unsafe {
+        let (datum, is_null) = slot_attribute(plan_slot, i32::from(state.key_row_identity));
+
+        String::from_datum(datum, is_null)
+            .ok_or_else(|| anyhow::anyhow!("Row identity of the modified KV entry is null"))
+    }
+}
+
+fn column_attnum(state: &ModifyState, predicate: impl Fn(Column) -> bool) -> Option<i32> {
+    state
+        .columns
+        .iter()
+        .zip(1..)
+        .find_map(|(column, attnum)| predicate(*column).then_some(attnum))
+}
+
+/// Writes a value to the bucket and returns its new revision.
+fn put_value(state: &ModifyState, key: String, value: Vec<u8>) -> anyhow::Result<u64> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.put_value(&state.bucket, key, value))
+    })
+}
+
+/// Sets the `revision` column of a written row to the revision assigned by the
+/// bucket, so that `RETURNING revision` reports it.
+///
+/// # Safety
+///
+/// `slot` must hold a row of the modified foreign table.
+unsafe fn store_revision(
+    state: &ModifyState,
+    slot: *mut sys::TupleTableSlot,
+    revision: u64,
+) -> anyhow::Result<()> {
+    let Some(attnum) = column_attnum(state, |column| column == Column::Revision) else {
+        return Ok(());
+    };
+    let datum = i64::try_from(revision)?.into_datum();
+
+    // SAFETY: `attnum` comes from the descriptor of the modified table, and reading
+    // the attribute first deforms the slot values up to it.
+    unsafe {
+        let _ = slot_attribute(slot, attnum);
+        let index = (attnum - 1) as usize;
+
+        *(*slot).tts_values.add(index) = datum.unwrap_or(sys::Datum::from(0));
+        *(*slot).tts_isnull.add(index) = datum.is_none();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,530 @@
+//! Foreign table support for `pgnats_fdw`.
+//!
+//! Foreign tables created on the `pgnats_fdw` server are backed by NATS
+//! resources selected through table options:
+//! - `bucket` - a JetStream key-value bucket, see [`kv`].
+//! - `stream` - a JetStream stream, see [`stream`].
+
+#[cfg(feature = "kv")]
+mod kv;
+mod stream;
+
+use std::ffi::{c_int, c_void, CStr};
+
+use pgrx::{
+    pg_extern, pg_guard, pg_sys as sys,
+    pgrx_sql_entity_graph::metadata::{
+        ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
+    },
+    AllocatedByRust, IntoDatum, PgBox, PgList, PgMemoryContexts,
+};
+
+use crate::config::parse_def_elem_options;
+
+const STARTUP_COST: f64 = 10.0;
+
+/// Kind of the NATS resource behind a foreign table.
+#[derive(Clone, Copy)]
+enum TableKind {
+    #[cfg(feature = "kv")]
+    Bucket,
+    Stream,
+}
+
+/// Executor state of a foreign scan, owned by the executor memory context.
+enum ScanState {
+    #[cfg(feature = "kv")]
+    Bucket(kv::ScanState),
+    Stream(stream::ScanState),
+}
+
+/// `fdw_handler` pseudo-type returned by [`pgnats_fdw_handler`].
+pub struct FdwRoutine(PgBox<sys::FdwRoutine, AllocatedByRust>);
+
+// SAFETY: `fdw_handler` is the SQL pseudo-type Postgres expects from FDW handlers.
+unsafe impl SqlTranslatable for FdwRoutine {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("fdw_handler"))
+    }
+
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("fdw_handler")))
+    }
+}
+
+impl IntoDatum for FdwRoutine {
+    fn into_datum(self) -> Option<sys::Datum> {
+        Some(self.0.into_pg().into())
+    }
+
+    fn type_oid() -> sys::Oid {
+        sys::Oid::INVALID
+    }
+}
+
+#[pg_extern]
+pub fn pgnats_fdw_handler() -> FdwRoutine {
+    let mut routine =
+        PgBox::<sys::FdwRoutine, AllocatedByRust>::alloc_node(sys::NodeTag::T_FdwRoutine);
+
+    routine.GetForeignRelSize = Some(get_foreign_rel_size);
+    routine.GetForeignPaths = Some(get_foreign_paths);
+    routine.GetForeignPlan = Some(get_foreign_plan);
+    routine.BeginForeignScan = Some(begin_foreign_scan);
+    routine.IterateForeignScan = Some(iterate_foreign_scan);
+    routine.ReScanForeignScan = Some(re_scan_foreign_scan);
+    routine.EndForeignScan = Some(end_foreign_scan);
+
+    routine.IsForeignRelUpdatable = Some(is_foreign_rel_updatable);
+
+    #[cfg(feature = "kv")]
+    {
+        routine.AddForeignUpdateTargets = Some(kv::add_foreign_update_targets);
+        routine.BeginForeignModify = Some(kv::begin_foreign_modify);
+        routine.ExecForeignInsert = Some(kv::exec_foreign_insert);
+        routine.ExecForeignUpdate = Some(kv::exec_foreign_update);
+        routine.ExecForeignDelete = Some(kv::exec_foreign_delete);
+        routine.EndForeignModify = Some(kv::end_foreign_modify);
+    }
+
+    FdwRoutine(routine)
+}
+
+/// Checks the options of a foreign table created on the `pgnats_fdw` server.
+pub fn validate_table_options(options: &[String]) -> anyhow::Result<()> {
+    let mut resources = vec![];
+    let mut has_format = false;
+
+    for (name, value) in options.iter().filter_map(|opt| opt.split_once('=')) {
+        match name {
+            #[cfg(feature = "kv")]
+            kv::BUCKET_OPTION => {
+                anyhow::ensure!(!value.is_empty(), "Option \"{name}\" must not be empty");
+                resources.push(name);
+            }
+            stream::STREAM_OPTION => {
+                anyhow::ensure!(!value.is_empty(), "Option \"{name}\" must not be empty");
+                resources.push(name);
+            }
+            stream::FORMAT_OPTION => {
+                let _ = stream::Format::parse(value)?;
+                has_format = true;
+            }
+            _ => anyhow::bail!("Unknown foreign table option \"{name}\""),
+        }
+    }
+
+    anyhow::ensure!(
+        resources.len() == 1,
+        "Foreign table requires exactly one of the \"bucket\" or \"stream\" options"
+    );
+    anyhow::ensure!(
+        !has_format || resources == [stream::STREAM_OPTION],
+        "Option \"{}\" is only supported by stream foreign tables",
+        stream::FORMAT_OPTION
+    );
+
+    Ok(())
+}
+
+#[pg_guard]
+unsafe extern "C-unwind" fn get_foreign_rel_size(
+    _root: *mut sys::PlannerInfo,
+    baserel: *mut sys::RelOptInfo,
+    foreigntableid: sys::Oid,
+) {
+    // SAFETY: the planner passes a valid `RelOptInfo` for the foreign table.
+    unsafe {
+        (*baserel).rows = match raise(table_kind(foreigntableid)) {
+            #[cfg(feature = "kv")]
+            TableKind::Bucket => kv::estimate_rows(baserel, foreigntableid),
+            TableKind::Stream => stream::estimate_rows(baserel, foreigntableid),
+        };
+    }
+}
+
+#[pg_guard]
+unsafe extern "C-unwind" fn get_foreign_paths(
+    root: *mut sys::PlannerInfo,
+    baserel: *mut sys::RelOptInfo,
+    _foreigntableid: sys::Oid,
+) {
+    // SAFETY: the planner passes valid planner structures; null arguments of
+    // `create_foreignscan_path` select its defaults.
+    unsafe {
+        let rows = (*baserel).rows;
+        let total_cost = STARTUP_COST + rows;
+
+        #[cfg(any(feature = "pg14", feature = "pg15", feature = "pg16"))]
+        let path = sys::create_foreignscan_path(
+            root,
+            baserel,
+            std::ptr::null_mut(),
+            rows,
+            STARTUP_COST,
+            total_cost,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        );
+
+        #[cfg(feature = "pg17")]
+        let path = sys::create_foreignscan_path(
+            root,
+            baserel,
+            std::ptr::null_mut(),
+            rows,
+            STARTUP_COST,
+            total_cost,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        );
+
+        #[cfg(feature = "pg18")]
+        let path = sys::create_foreignscan_path(
+            root,
+            baserel,
+            std::ptr::null_mut(),
+            rows,
+            0,
+            STARTUP_COST,
+            total_cost,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        );
+
+        sys::add_path(baserel, path.cast());
+    }
+}
+
+#[pg_guard]
+unsafe extern "C-unwind" fn get_foreign_plan(
+    _root: *mut sys::PlannerInfo,
+    baserel: *mut sys::RelOptInfo,
+    foreigntableid: sys::Oid,
+    _best_path: *mut sys::ForeignPath,
+    tlist: *mut sys::List,
+    scan_clauses: *mut sys::List,
+    outer_plan: *mut sys::Plan,
+) -> *mut sys::ForeignScan {
+    // SAFETY: the planner passes valid planner structures.
+    unsafe {
+        let scan_relid = (*baserel).relid;
+        let fdw_private = match raise(table_kind(foreigntableid)) {
+            #[cfg(feature = "kv")]
+            TableKind::Bucket => kv::plan_private(scan_clauses, scan_relid, foreigntableid),
+            TableKind::Stream => stream::plan_private(scan_clauses, scan_relid, foreigntableid),
+        };
+
+        // All conditions are still checked locally, pushed down conditions only narrow the scan.
+        let quals = sys::extract_actual_clauses(scan_clauses, false);
+
+        sys::make_foreignscan(
+            tlist,
+            quals,
+            scan_relid,
+            std::ptr::null_mut(),
+            fdw_private,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            outer_plan,
+        )
+    }
+}
+
+#[pg_guard]
+unsafe extern "C-unwind" fn begin_foreign_scan(node: *mut sys::ForeignScanState, eflags: c_int) {
+    if eflags & sys::EXEC_FLAG_EXPLAIN_ONLY as c_int != 0 {
+        return;
+    }
+
+    // SAFETY: the executor passes a valid scan state whose plan is the
+    // `ForeignScan` built by `get_foreign_plan`.
+    unsafe {
+        let relation = (*node).ss.ss_currentRelation;
+        let plan = (*node).ss.ps.plan as *mut sys::ForeignScan;
+        let fdw_private = PgList::<sys::Node>::from_pg((*plan).fdw_private);
+
+        let state = match raise(table_kind((*relation).rd_id)) {
+            #[cfg(feature = "kv")]
+            TableKind::Bucket => ScanState::Bucket(raise(kv::begin_scan(relation, &fdw_private))),
+            TableKind::Stream => {
+                ScanState::Stream(raise(stream::begin_scan(relation, &fdw_private)))
+            }
+        };
+
+        (*node).fdw_state = PgMemoryContexts::CurrentMemoryContext
+            .leak_and_drop_on_delete(state)
+            .cast::<c_void>();
+    }
+}
+
+#[pg_guard]
+unsafe extern "C-unwind" fn iterate_foreign_scan(
+    node: *mut sys::ForeignScanState,
+) -> *mut sys::TupleTableSlot {
+    // SAFETY: `fdw_state` was set by `begin_foreign_scan`, the scan slot has
+    // one value per table column, and datums are allocated in the per-tuple
+    // memory context which the executor resets between rows.
+    unsafe {
+        let slot = (*node).ss.ss_ScanTupleSlot;
+        exec_clear_tuple(slot);
+
+        let state = &mut *((*node).fdw_state as *mut ScanState);
+        let per_tuple = (*(*node).ss.ps.ps_ExprContext).ecxt_per_tuple_memory;
+        let stored = PgMemoryContexts::For(per_tuple).switch_to(|_| match state {
+            #[cfg(feature = "kv")]
+            ScanState::Bucket(state) => raise(state.next_row(slot)),
+            ScanState::Stream(state) => raise(state.next_row(slot)),
+        });
+
+        if !stored {
+            return slot;
+        }
+
+        sys::ExecStoreVirtualTuple(slot)
+    }
+}
+
+#[pg_guard]
+unsafe extern "C-unwind" fn re_scan_foreign_scan(node: *mut sys::ForeignScanState) {
+    // SAFETY: `fdw_state` was set by `begin_foreign_scan`.
+    unsafe {
+        match &mut *((*node).fdw_state as *mut ScanState) {
+            #[cfg(feature = "kv")]
+            ScanState::Bucket(state) => state.rewind(),
+            ScanState::Stream(state) => state.rewind(),
+        }
+    }
+}
+
+#[pg_guard]
+unsafe extern "C-unwind" fn end_foreign_scan(node: *mut sys::ForeignScanState) {
+    // SAFETY: `fdw_state` is null for `EXPLAIN` or was set by `begin_foreign_scan`.
+    // The state itself is owned by the executor memory context and dropped together with it.
+    unsafe {
+        if let Some(ScanState::Stream(state)) = ((*node).fdw_state as *mut ScanState).as_mut() {
+            state.close();
+        }
+
+        (*node).fdw_state = std::ptr::null_mut();
+    }
+}
+
+#[pg_guard]
+unsafe extern "C-unwind" fn is_foreign_rel_updatable(rel: sys::Relation) -> c_int {
+    // SAFETY: Postgres passes a valid, opened foreign table relation.
+    let kind = unsafe { table_kind((*rel).rd_id) };
+
+    match raise(kind) {
+        #[cfg(feature = "kv")]
+        TableKind::Bucket => kv::UPDATABLE_OPERATIONS,
+        TableKind::Stream => 0,
+    }
+}
+
+/// Selects the kind of a foreign table from the resource option it was created with.
+///
+/// # Safety
+///
+/// `relid` must be the OID of a foreign table.
+unsafe fn table_kind(relid: sys::Oid) -> anyhow::Result<TableKind> {
+    // SAFETY: `GetForeignTable` errors out for relations that are not foreign tables.
+    let options = unsafe { parse_def_elem_options((*sys::GetForeignTable(relid)).options) };
+
+    #[cfg(feature = "kv")]
+    if options.contains_key(kv::BUCKET_OPTION) {
+        return Ok(TableKind::Bucket);
+    }
+
+    if options.contains_key(stream::STREAM_OPTION) {
+        return Ok(TableKind::Stream);
+    }
+
+    anyhow::bail!("Foreign table must have a \"bucket\" or \"stream\" option")
+}
+
+/// Raises a Postgres `ERROR` if an FDW callback step failed.
+fn raise<T>(result: anyhow::Result<T>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(err) => {
+            crate::error!("{err}");
+            unreachable!("ERROR reports never return")
+        }
+    }
+}
+
+/// Returns the name of a table column, or `None` for dropped columns.
+fn attribute_name(attr: &sys::FormData_pg_attribute) -> Option<String> {
+    if attr.attisdropped {
+        return None;
+    }
+
+    // SAFETY: `attname` is a fixed-size, null-terminated `NameData` buffer.
+    let name = unsafe { CStr::from_ptr(attr.attname.data.as_ptr()) };
+
+    Some(name.to_string_lossy().to_string())
+}
+
+/// Reads the attribute `attnum` (1-based) of `slot`.
+///
+/// # Safety
+///
+/// `slot` must point to a valid tuple table slot and `attnum` must be within
+/// its descriptor.
+unsafe fn slot_attribute(slot: *mut sys::TupleTableSlot, attnum: i32) -> (sys::Datum, bool) {
+    // SAFETY: the caller guarantees that `attnum` is within the slot descriptor,
+    // and `slot_getsomeattrs_int` fills `tts_values`/`tts_isnull` up to it.
+    unsafe {
+        if i32::from((*slot).tts_nvalid) < attnum {
+            sys::slot_getsomeattrs_int(slot, attnum);
+        }
+
+        let index = (attnum - 1) as usize;
+
+        (
+            *(*slot).tts_values.add(index),
+            *(*slot).tts_isnull.add(index),
+        )
+    }
+}
+
+/// Stores one datum per table column into the values of `slot`.
+///
+/// # Safety
+///
+/// `slot` must point to a valid tuple table slot with at least as many
+/// attributes as `datums` yields.
+unsafe fn store_datums(
+    slot: *mut sys::TupleTableSlot,
+    datums: impl IntoIterator<Item = Option<sys::Datum>>,
+) {
+    // SAFETY: the caller guarantees that every index is within the slot descriptor.
+    unsafe {
+        for (index, datum) in datums.into_iter().enumerate() {
+            *(*slot).tts_values.add(index) = datum.unwrap_or(sys::Datum::from(0));
+            *(*slot).tts_isnull.add(index) = datum.is_none();
+        }
+    }
+}
+
+/// Matches a `<column> <operator> <constant>` condition on the column `attnum`
+/// of the scanned relation, in either operand order. The returned operator name
+/// reads with the column on the left.
+///
+/// # Safety
+///
+/// `node` must be null or point to a valid expression node.
+unsafe fn column_condition(
+    node: *mut sys::Node,
+    varno: sys::Index,
+    attnum: sys::AttrNumber,
+) -> Option<(String, *mut sys::Const)> {
+    // SAFETY: node tags are checked before every cast.
+    unsafe {
+        if node.is_null() || (*node).type_ != sys::NodeTag::T_OpExpr {
+            return None;
+        }
+
+        let expr = node as *mut sys::OpExpr;
+        let args = PgList::<sys::Node>::from_pg((*expr).args);
+        if args.len() != 2 {
+            return None;
+        }
+
+        let opname = sys::get_opname((*expr).opno);
+        if opname.is_null() {
+            return None;
+        }
+        let opname = CStr::from_ptr(opname).to_string_lossy().to_string();
+
+        let left = strip_relabel(args.get_ptr(0)?);
+        let right = strip_relabel(args.get_ptr(1)?);
+
+        let (var, constant, opname) = match ((*left).type_, (*right).type_) {
+            (sys::NodeTag::T_Var, sys::NodeTag::T_Const) => (left, right, opname),
+            (sys::NodeTag::T_Const, sys::NodeTag::T_Var) => {
+                let commuted = match opname.as_str() {
+                    "=" | "<>" => opname,
+                    "<" => ">".to_string(),
+                    "<=" => ">=".to_string(),
+                    ">" => "<".to_string(),
+                    ">=" => "<=".to_string(),
+                    _ => return None,
+                };
+                (right, left, commuted)
+            }
+            _ => return None,
+        };
+
+        let var = var as *mut sys::Var;
+        if i64::from((*var).varno) != i64::from(varno)
+            || (*var).varattno != attnum
+            || (*var).varlevelsup != 0
+        {
+            return None;
+        }
+
+        Some((opname, constant as *mut sys::Const))
+    }
+}
+
+/// Skips binary-compatible casts such as `varchar` to `text`.
+///
+/// # Safety
+///
+/// `node` must point to a valid expression node.
+unsafe fn strip_relabel(mut node: *mut sys::Node) -> *mut sys::Node {
+    // SAFETY: the node tag is checked before the cast.
+    unsafe {
+        while !node.is_null() && (*node).type_ == sys::NodeTag::T_RelabelType {
+            node = (*(node as *mut sys::RelabelType)).arg.cast();
+        }
+    }
+
+    node
+}
+
+/// Reads the value of a `String` node stored in the plan's private list.
+///
+/// # Safety
+///
+/// `node` must point to a valid `String` node.
+unsafe fn string_node_value(node: *mut sys::Node) -> Option<String> {
+    // SAFETY: the caller guarantees that `node` is a `String` node.
+    unsafe {
+        #[cfg(feature = "pg14")]
+        let val = (*(node as *mut sys::Value)).val.str_;
+
+        #[cfg(not(feature = "pg14"))]
+        let val = (*(node as *mut sys::String)).sval;
+
+        if val.is_null() {
+            return None;
+        }
+
+        Some(CStr::from_ptr(val).to_string_lossy().to_string())
+    }
+}
+
+/// Empties `slot`, mirroring the inline `ExecClearTuple` from `tuptable.h`.
+///
+/// # Safety
+///
+/// `slot` must point to a valid tuple table slot.
+unsafe fn exec_clear_tuple(slot: *mut sys::TupleTableSlot) {
+    // SAFETY: the caller guarantees that `slot` is valid; `tts_ops` is always set.
+    unsafe {
+        if let Some(clear) = (*(*slot).tts_ops).clear {
+            clear(slot);
+        }
+    }
+}
@@ -0,0 +1,609 @@
+//! Foreign tables over JetStream streams.
+//!
+//! ```sql
+//! CREATE FOREIGN TABLE events (seq bigint, subject text, published timestamptz, payload jsonb)
+//!     SERVER nats OPTIONS (stream 'EVENTS', format 'jsonb');
+//! ```
+//!
+//! Columns are matched by name: `seq` (`bigint`), `subject` (`text`),
+//! `payload` (of the `format` type, `bytea` by default), `headers` (`jsonb`)
+//! and `published` (`timestamptz`). With the `json` or `jsonb` format other
+//! columns are read from the payload field of the same name, otherwise they
+//! read as `NULL`. Comparisons of `seq` with a constant and a
+//! `subject = <constant>` condition are pushed down, so the ephemeral ordered
+//! consumer replaying the stream starts at the first needed sequence, stops
+//! after the last one and only delivers the matching subject.
+
+use std::ffi::{CStr, CString};
+
+use async_nats::jetstream::{self, consumer::push::Ordered};
+use futures::StreamExt;
+use pgrx::{pg_sys as sys, FromDatum, IntoDatum, PgList, PgTupleDesc};
+
+use super::{attribute_name, column_condition, store_datums, string_node_value};
+use crate::{
+    config::parse_def_elem_options,
+    ctx::CTX,
+    utils::{headers_to_json, timestamptz_from_unix_nanos},
+};
+
+pub(super) const STREAM_OPTION: &str = "stream";
+pub(super) const FORMAT_OPTION: &str = "format";
+
+const SEQ_COLUMN: &CStr = c"seq";
+const SUBJECT_COLUMN: &CStr = c"subject";
+const PAYLOAD_COLUMN: &str = "payload";
+const HEADERS_COLUMN: &str = "headers";
+const PUBLISHED_COLUMN: &str = "published";
+
+/// Keys of the pushed down conditions in the plan's private list.
+const START_PRIVATE: &str = "start";
+const END_PRIVATE: &str = "end";
+const SUBJECT_PRIVATE: &str = "subject";
+
+const INVALID_ATTNUM: sys::AttrNumber = 0;
+
+const STREAM_SCAN_ROWS: f64 = 1000.0;
+const SUBJECT_SCAN_ROWS: f64 = 100.0;
+
+/// How long a replay may wait for the next message the consumer reported as pending.
+const REPLAY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Type the message payloads of a stream are decoded to.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Format {
+    Bytea,
+    Text,
+    Json,
+    Jsonb,
+}
+
+impl Format {
+    pub(super) fn parse(value: &str) -> anyhow::Result<Self> {
+        match value {
+            "bytea" => Ok(Self::Bytea),
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "jsonb" => Ok(Self::Jsonb),
+            _ => anyhow::bail!(
+                "Option \"{FORMAT_OPTION}\" must be one of bytea, text, json or jsonb"
+            ),
+        }
+    }
+
+    fn type_oid(self) -> sys::Oid {
+        match self {
+            Self::Bytea => sys::BYTEAOID,
+            Self::Text => sys::TEXTOID,
+            Self::Json => sys::JSONOID,
+            Self::Jsonb => sys::JSONBOID,
+        }
+    }
+
+    fn is_json(self) -> bool {
+        matches!(self, Self::Json | Self::Jsonb)
+    }
+}
+
+enum Column {
+    Seq,
+    Subject,
+    Payload,
+    Headers,
+    Published,
+    /// A payload field, converted through the input function of the column type.
+    Field {
+        name: String,
+        type_oid: sys::Oid,
+        input: sys::Oid,
+        ioparam: sys::Oid,
+        typmod: i32,
+    },
+    Other,
+}
+
+/// Part of the stream selected by the pushed down conditions.
+#[derive(Default)]
+struct Range {
+    start: Option<u64>,
+    end: Option<u64>,
+    subject: Option<String>,
+}
+
+impl Range {
+    fn restrict_seq(&mut self, opname: &str, value: i64) {
+        match opname {
+            "=" => {
+                self.restrict_start(value);
+                self.restrict_end(value);
+            }
+            ">" => self.restrict_start(value.saturating_add(1)),
+            ">=" => self.restrict_start(value),
+            "<" => self.restrict_end(value.saturating_sub(1)),
+            "<=" => self.restrict_end(value),
+            _ => {}
+        }
+    }
+
+    fn restrict_start(&mut self, value: i64) {
+        // Sequence numbers start at 1.
+        let value = u64::try_from(value).unwrap_or(0).max(1);
+        self.start = Some(self.start.map_or(value, |start| start.max(value)));
+    }
+
+    fn restrict_end(&mut self, value: i64) {
+        let value = u64::try_from(value).unwrap_or(0);
+        self.end = Some(self.end.map_or(value, |end| end.min(value)));
+    }
+
+    fn is_empty(&self) -> bool {
+        self.end.is_some_and(|end| end < self.start.unwrap_or(1))
+    }
+
+    fn estimate_rows(&self) -> f64 {
+        let rows = if self.subject.is_some() {
+            SUBJECT_SCAN_ROWS
+        } else {
+            STREAM_SCAN_ROWS
+        };
+
+        match (self.start, self.end) {
+            _ if self.is_empty() => 1.0,
+            (Some(start), Some(end)) => {
+                rows.min(end.saturating_sub(start).saturating_add(1) as f64)
+            }
+            _ => rows,
+        }
+    }
+
+    /// Stores the range as `name=value` `String` nodes, which survive plan copying.
+    ///
+    /// # Safety
+    ///
+    /// Must be called in a memory context that outlives the plan.
+    unsafe fn into_private(self) -> *mut sys::List {
+        let entries = [
+            self.start.map(|start| (START_PRIVATE, start.to_string())),
+            self.end.map(|end| (END_PRIVATE, end.to_string())),
+            self.subject.map(|subject| (SUBJECT_PRIVATE, subject)),
+        ];
+
+        let mut fdw_private = PgList::<sys::Node>::new();
+        for (name, value) in entries.into_iter().flatten() {
+            if let Ok(entry) = CString::new(format!("{name}={value}")) {
+                // SAFETY: `makeString` keeps the pointer, so the entry is copied with `pstrdup`.
+                unsafe {
+                    fdw_private.push(sys::makeString(sys::pstrdup(entry.as_ptr())).cast());
+                }
+            }
+        }
+
+        fdw_private.into_pg()
+    }
+
+    /// Reads a range stored by [`Range::into_private`].
+    ///
+    /// # Safety
+    ///
+    /// `fdw_private` must be the list built by [`Range::into_private`].
+    unsafe fn from_private(fdw_private: &PgList<sys::Node>) -> anyhow::Result<Self> {
+        let mut range = Self::default();
+
+        for node in fdw_private.iter_ptr() {
+            // SAFETY: the caller guarantees that the list only holds `String` nodes.
+            let Some(entry) = (unsafe { string_node_value(node) }) else {
+                continue;
+            };
+
+            match entry.split_once('=') {
+                Some((START_PRIVATE, value)) => range.start = Some(value.parse()?),
+                Some((END_PRIVATE, value)) => range.end = Some(value.parse()?),
+                Some((SUBJECT_PRIVATE, value)) => range.subject = Some(value.to_string()),
+                _ => anyhow::bail!("Unexpected foreign scan plan entry \"{entry}\""),
+            }
+        }
+
+        Ok(range)
+    }
+}
+
+pub(super) struct ScanState {
+    stream: String,
+    format: Format,
+    columns: Vec<Column>,
+    range: Range,
+    messages: Option<Ordered>,
+    exhausted: bool,
+}
+
+impl ScanState {
+    /// Fills `slot` with the next message, returns `false` once the scan is exhausted.
+    ///
+    /// # Safety
+    ///
+    /// `slot` must be the scan slot of the foreign table.
+    pub(super) unsafe fn next_row(
+        &mut self,
+        slot: *mut sys::TupleTableSlot,
+    ) -> anyhow::Result<bool> {
+        let Some(message) = self.next_message()? else {
+            return Ok(false);
+        };
+
+        let datums = self.message_datums(&message)?;
+
+        // SAFETY: the caller guarantees that `slot` has one value per table column.
+        unsafe { store_datums(slot, datums) };
+
+        Ok(true)
+    }
+
+    /// Restarts the scan, the stream is replayed again by a new consumer.
+    pub(super) fn rewind(&mut self) {
+        self.messages = None;
+        self.exhausted = self.range.is_empty();
+    }
+
+    /// Ends the replay, the server removes the ephemeral consumer once it is inactive.
+    pub(super) fn close(&mut self) {
+        self.messages = None;
+        self.exhausted = true;
+    }
+
+    fn next_message(&mut self) -> anyhow::Result<Option<jetstream::Message>> {
+        if self.exhausted {
+            return Ok(None);
+        }
+
+        if self.messages.is_none() {
+            let messages = CTX.with_borrow_mut(|ctx| {
+                ctx.rt.block_on(ctx.nats_connection.replay_stream(
+                    &self.stream,
+                    self.range.start,
+                    self.range.subject.clone(),
+                ))
+            })?;
+
+            let Some(messages) = messages else {
+                self.close();
+                return Ok(None);
+            };
+
+            self.messages = Some(messages);
+        }
+
+        let Some(messages) = self.messages.as_mut() else {
+            return Ok(None);
+        };
+
+        let message = CTX.with_borrow_mut(|ctx| {
+            ctx.rt
+                .block_on(tokio::time::timeout(REPLAY_TIMEOUT, messages.next()))
+        });
+
+        let Ok(message) = message else {
+            self.close();
+            anyhow::bail!("Timed out replaying stream \"{}\"", self.stream);
+        };
+
+        let Some(message) = message else {
+            self.close();
+            return Ok(None);
+        };
+
+        let message = message?;
+        let (sequence, pending) = {
+            let info = message.info().map_err(|e| anyhow::anyhow!(e))?;
+            (info.stream_sequence, info.pending)
+        };
+
+        if self.range.end.is_some_and(|end| sequence > end) {
+            self.close();
+            return Ok(None);
+        }
+
+        if pending == 0 || self.range.end == Some(sequence) {
+            self.close();
+        }
+
+        Ok(Some(message))
+    }
+
+    fn message_datums(
+        &self,
+        message: &jetstream::Message,
+    ) -> anyhow::Result<Vec<Option<sys::Datum>>> {
+        let info = message.info().map_err(|e| anyhow::anyhow!(e))?;
+        let document = if self.format.is_json() {
+            Some(serde_json::from_slice::<serde_json::Value>(
+                &message.payload,
+            )?)
+        } else {
+            None
+        };
+
+        self.columns
+            .iter()
+            .map(|column| {
+                Ok(match column {
+                    Column::Seq => i64::try_from(info.stream_sequence)?.into_datum(),
+                    Column::Subject => message.subject.as_str().into_datum(),
+                    Column::Payload => match (self.format, &document) {
+                        (Format::Json, Some(document)) => pgrx::Json(document.clone()).into_datum(),
+                        (Format::Jsonb, Some(document)) => {
+                            pgrx::JsonB(document.clone()).into_datum()
+                        }
+                        (Format::Text, _) => std::str::from_utf8(&message.payload)?.into_datum(),
+                        _ => message.payload.as_ref().into_datum(),
+                    },
+                    Column::Headers => message
+                        .headers
+                        .as_ref()
+                        .and_then(|headers| pgrx::JsonB(headers_to_json(headers)).into_datum()),
+                    Column::Published => {
+                        timestamptz_from_unix_nanos(info.published.unix_timestamp_nanos())
+                            .and_then(IntoDatum::into_datum)
+                    }
+                    Column::Field {
+                        name,
+                        type_oid,
+                        input,
+                        ioparam,
+                        typmod,
+                    } => {
+                        let Some(value) = document
+                            .as_ref()
+                            .and_then(|document| document.get(name))
+                            .filter(|value| !value.is_null())
+                        else {
+                            return Ok(None);
+                        };
+
+                        match *type_oid {
+                            sys::JSONBOID => pgrx::JsonB(value.clone()).into_datum(),
+                            sys::JSONOID => pgrx::Json(value.clone()).into_datum(),
+                            _ => {
+                                let text = match value {
+                                    serde_json::Value::String(text) => CString::new(text.as_str()),
+                                    value => CString::new(value.to_string()),
+                                }?;
+
+                                // SAFETY: the input function and its I/O parameter were
+                                // looked up for the column type in `table_columns`.
+                                Some(unsafe {
+                                    sys::OidInputFunctionCall(
+                                        *input,
+                                        text.as_ptr().cast_mut(),
+                                        *ioparam,
+                                        *typmod,
+                                    )
+                                })
+                            }
+                        }
+                    }
+                    Column::Other => None,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Estimates the number of rows returned by a scan of the foreign table.
+///
+/// # Safety
+///
+/// `baserel` must be the planner relation of the foreign table `foreigntableid`.
+pub(super) unsafe fn estimate_rows(baserel: *mut sys::RelOptInfo, foreigntableid: sys::Oid) -> f64 {
+    // SAFETY: the caller guarantees that `baserel` is valid.
+    let range = unsafe {
+        find_range(
+            (*baserel).baserestrictinfo,
+            (*baserel).relid,
+            foreigntableid,
+        )
+    };
+
+    range.estimate_rows()
+}
+
+/// Builds the private plan data of a scan, holding the pushed down conditions.
+///
+/// # Safety
+///
+/// `scan_clauses` must be the restriction clauses of the scan of `foreigntableid`.
+pub(super) unsafe fn plan_private(
+    scan_clauses: *mut sys::List,
+    scan_relid: sys::Index,
+    foreigntableid: sys::Oid,
+) -> *mut sys::List {
+    // SAFETY: the caller guarantees that `scan_clauses` is valid; planning runs
+    // in the memory context of the plan.
+    unsafe { find_range(scan_clauses, scan_relid, foreigntableid).into_private() }
+}
+
+/// Prepares the replay of the stream part selected by the plan. The consumer
+/// is created when the first row is read.
+///
+/// # Safety
+///
+/// `relation` must be the scanned foreign table and `fdw_private` the list
+/// built by [`plan_private`].
+pub(super) unsafe fn begin_scan(
+    relation: sys::Relation,
+    fdw_private: &PgList<sys::Node>,
+) -> anyhow::Result<ScanState> {
+    // SAFETY: the caller guarantees that `relation` is a valid, opened foreign
+    // table and that the private list was built by `plan_private`.
+    let (stream, format, columns, range) = unsafe {
+        let (stream, format) = table_options((*relation).rd_id)?;
+        let columns = table_columns((*relation).rd_att, format)?;
+
+        (stream, format, columns, Range::from_private(fdw_private)?)
+    };
+
+    Ok(ScanState {
+        stream,
+        format,
+        columns,
+        exhausted: range.is_empty(),
+        range,
+        messages: None,
+    })
+}
+
+/// Reads the `stream` and `format` options of a foreign table.
+///
+/// # Safety
+///
+/// `relid` must be the OID of a foreign table.
+unsafe fn table_options(relid: sys::Oid) -> anyhow::Result<(String, Format)> {
+    // SAFETY: `GetForeignTable` errors out for relations that are not foreign tables.
+    let options = unsafe { parse_def_elem_options((*sys::GetForeignTable(relid)).options) };
+
+    let stream = options
+        .get(STREAM_OPTION)
+        .map(|stream| stream.to_string())
+        .ok_or_else(|| anyhow::anyhow!("Foreign table must have a \"{STREAM_OPTION}\" option"))?;
+    let format = options
+        .get(FORMAT_OPTION)
+        .map(|format| Format::parse(format))
+        .transpose()?
+        .unwrap_or(Format::Bytea);
+
+    Ok((stream, format))
+}
+
+/// Maps every column of the foreign table to the part of a message it holds.
+///
+/// # Safety
+///
+/// `tupdesc` must point to a valid tuple descriptor.
+unsafe fn table_columns(tupdesc: sys::TupleDesc, format: Format) -> anyhow::Result<Vec<Column>> {
+    // SAFETY: the caller guarantees that `tupdesc` is valid; it is not freed here.
+    let tupdesc = unsafe { PgTupleDesc::from_pg_unchecked(tupdesc) };
+
+    tupdesc
+        .iter()
+        .map(|attr| {
+            let Some(name) = attribute_name(attr) else {
+                return Ok(Column::Other);
+            };
+
+            let type_oid = attr.atttypid;
+
+            if name.as_bytes() == SEQ_COLUMN.to_bytes() {
+                anyhow::ensure!(
+                    type_oid == sys::INT8OID,
+                    "Column \"{name}\" must be of type bigint"
+                );
+                Ok(Column::Seq)
+            } else if name.as_bytes() == SUBJECT_COLUMN.to_bytes() {
+                anyhow::ensure!(
+                    [sys::TEXTOID, sys::VARCHAROID].contains(&type_oid),
+                    "Column \"{name}\" must be of type text"
+                );
+                Ok(Column::Subject)
+            } else if name == PAYLOAD_COLUMN {
+                anyhow::ensure!(
+                    type_oid == format.type_oid(),
+                    "Column \"{name}\" must be of the type given by the \"{FORMAT_OPTION}\" option"
+                );
+                Ok(Column::Payload)
+            } else if name == HEADERS_COLUMN {
+                anyhow::ensure!(
+                    type_oid == sys::JSONBOID,
+                    "Column \"{name}\" must be of type jsonb"
+                );
+                Ok(Column::Headers)
+            } else if name == PUBLISHED_COLUMN {
+                anyhow::ensure!(
+                    type_oid == sys::TIMESTAMPTZOID,
+                    "Column \"{name}\" must be of type timestamptz"
+                );
+                Ok(Column::Published)
+            } else if format.is_json() {
+                let mut input = sys::Oid::INVALID;
+                let mut ioparam = sys::Oid::INVALID;
+
+                // SAFETY: `getTypeInputInfo` errors out for unknown types and
+                // writes both output arguments otherwise.
+                unsafe { sys::getTypeInputInfo(type_oid, &mut input, &mut ioparam) };
+
+                Ok(Column::Field {
+                    name,
+                    type_oid,
+                    input,
+                    ioparam,
+                    typmod: attr.atttypmod,
+                })
+            } else {
+                Ok(Column::Other)
+            }
+        })
+        .collect()
+}
+
+/// Collects the `seq` and `subject` conditions among the restriction clauses of a scan.
+///
+/// # Safety
+///
+/// `clauses` must be null or a valid list of `RestrictInfo` nodes of a scan of `foreigntableid`.
+unsafe fn find_range(
+    clauses: *mut sys::List,
+    varno: sys::Index,
+    foreigntableid: sys::Oid,
+) -> Range {
+    let mut range = Range::default();
+
+    // SAFETY: the caller guarantees that `clauses` is a list of `RestrictInfo`
+    // nodes; constants are read according to their type.
+    unsafe {
+        let seq_attnum = sys::get_attnum(foreigntableid, SEQ_COLUMN.as_ptr());
+        let subject_attnum = sys::get_attnum(foreigntableid, SUBJECT_COLUMN.as_ptr());
+
+        for rinfo in PgList::<sys::RestrictInfo>::from_pg(clauses).iter_ptr() {
+            let clause = (*rinfo).clause.cast::<sys::Node>();
+
+            if seq_attnum != INVALID_ATTNUM {
+                if let Some((opname, constant)) = column_condition(clause, varno, seq_attnum) {
+                    if let Some(value) = integer_constant(constant) {
+                        range.restrict_seq(&opname, value);
+                    }
+                    continue;
+                }
+            }
+
+            if subject_attnum != INVALID_ATTNUM && range.subject.is_none() {
+                if let Some((opname, constant)) = column_condition(clause, varno, subject_attnum) {
+                    if opname == "="
+                        && [sys::TEXTOID, sys::VARCHAROID].contains(&(*constant).consttype)
+                    {
+                        range.subject =
+                            String::from_datum((*constant).constvalue, (*constant).constisnull);
+                    }
+                }
+            }
+        }
+    }
+
+    range
+}
+
+/// Reads a non-null integer constant of any width.
+///
+/// # Safety
+///
+/// `constant` must point to a valid `Const` node.
+unsafe fn integer_constant(constant: *mut sys::Const) -> Option<i64> {
+    // SAFETY: the datum is read according to the constant type.
+    unsafe {
+        let (datum, is_null) = ((*constant).constvalue, (*constant).constisnull);
+
+        match (*constant).consttype {
+            sys::INT8OID => i64::from_datum(datum, is_null),
+            sys::INT4OID => i32::from_datum(datum, is_null).map(i64::from),
+            sys::INT2OID => i16::from_datum(datum, is_null).map(i64::from),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use pgrx::{pg_sys as sys, PgTryBuilder, Spi};
+use tokio::{
+    io::{AsyncRead, ReadBuf},
+    sync::mpsc,
+};
+
+/// Size of the pieces a large object is read and written in.
+pub const CHUNK_SIZE: i32 = 256 * 1024;
+
+/// Number of chunks buffered between the backend and an upload task.
+const CHANNEL_CAPACITY: usize = 4;
+
+pub fn create() -> anyhow::Result<sys::Oid> {
+    Spi::get_one::<sys::Oid>("SELECT lo_create(0)")?
+        .ok_or_else(|| anyhow::anyhow!("Failed to create a large object"))
+}
+
+/// Reads up to `length` bytes of a large object starting at `offset`.
+/// An empty result means the end of the large object.
+///
+/// Errors raised by the server, e.g. for a missing large object, are returned
+/// instead of unwinding, so that callers can stop a running upload first.
+pub fn read(lo_oid: sys::Oid, offset: i64, length: i32) -> anyhow::Result<Vec<u8>> {
+    PgTryBuilder::new(|| {
+        Ok(Spi::get_one_with_args::<Vec<u8>>(
+            "SELECT lo_get($1, $2, $3)",
+            &[lo_oid.into(), offset.into(), length.into()],
+        )?
+        .unwrap_or_default())
+    })
+    .catch_others(|e| match e {
+        pgrx::pg_sys::panic::CaughtError::PostgresError(err) => Err(anyhow::anyhow!(
+            "Code '{}': {}. ({:?})",
+            err.sql_error_code(),
+            err.message(),
+            err.hint()
+        )),
+        _ => Err(anyhow::anyhow!("{e:?}")),
+    })
+    .execute()
+}
+
+pub fn write(lo_oid: sys::Oid, offset: i64, data: &[u8]) -> anyhow::Result<()> {
+    Spi::run_with_args(
+        "SELECT lo_put($1, $2, $3)",
+        &[lo_oid.into(), offset.into(), data.into()],
+    )?;
+
+    Ok(())
+}
+
+pub enum Chunk {
+    Data(Vec<u8>),
+    End,
+}
+
+/// Reads the chunks sent by the backend as one continuous stream.
+///
+/// The stream only ends after an explicit [`Chunk::End`]; if the sender is
+/// dropped before that (the backend failed while reading the large object),
+/// reading fails so that a truncated object is never stored.
+pub struct ChunkReader {
+    receiver: mpsc::Receiver<Chunk>,
+    current: Vec<u8>,
+    position: usize,
+    finished: bool,
+}
+
+impl ChunkReader {
+    pub fn channel() -> (mpsc::Sender<Chunk>, Self) {
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+
+        let reader = Self {
+            receiver,
+            current: Vec::new(),
+            position: 0,
+            finished: false,
+        };
+
+        (sender, reader)
+    }
+}
+
+impl AsyncRead for ChunkReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.finished {
+                return Poll::Ready(Ok(()));
+            }
+
+            let remaining = this.current.get(this.position..).unwrap_or_default();
+            if !remaining.is_empty() {
+                let (head, _) = remaining.split_at(remaining.len().min(buf.remaining()));
+                buf.put_slice(head);
+                this.position += head.len();
+
+                return Poll::Ready(Ok(()));
+            }
+
+            match ready!(this.receiver.poll_recv(cx)) {
+                Some(Chunk::Data(data)) => {
+                    this.current = data;
+                    this.position = 0;
+                }
+                Some(Chunk::End) => this.finished = true,
+                None => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Large object stream was interrupted",
+                    )))
+                }
+            }
+        }
+    }
+}
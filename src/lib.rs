@@ -29,12 +29,18 @@ mod init;
 mod log;
 mod utils;
 
+#[cfg(feature = "object_store")]
+mod large_object;
+
 pub mod api;
 
 #[doc(hidden)]
 #[cfg(feature = "sub")]
 pub mod bgw;
 
+#[doc(hidden)]
+pub mod fdw;
+
 #[doc(hidden)]
 pub mod config;
 
@@ -2,9 +2,12 @@ use std::{collections::HashMap, io::Cursor, time::Duration};
 
 use async_nats::{
     jetstream::{
-        kv::Store,
-        object_store::{ObjectInfo, ObjectStore},
-        Context,
+        self, account,
+        consumer::{self, DeliverPolicy},
+        kv::{Entry, Operation, Store},
+        message::StreamMessage,
+        object_store::{Object, ObjectInfo, ObjectMetadata, ObjectStore},
+        stream, Context,
     },
     Client, Request,
 };
@@ -17,6 +20,12 @@ use crate::{
     utils::{extract_headers, FromBytes, ToBytes},
 };
 
+/// Number of KV operations kept in flight by the bulk calls.
+const KV_PIPELINE_DEPTH: usize = 256;
+
+/// Chunk size used by the object store when an object does not record its own.
+const OBJECT_CHUNK_SIZE: usize = 128 * 1024;
+
 pub struct NatsClient {
     connection: Option<Client>,
     jetstream: Option<Context>,
@@ -118,6 +127,220 @@ impl NatsClient {
         Ok(())
     }
 
+    pub async fn create_stream(&mut self, config: stream::Config) -> anyhow::Result<stream::Info> {
+        let stream = self.get_jetstream().await?.create_stream(config).await?;
+
+        Ok(stream.cached_info().clone())
+    }
+
+    pub async fn update_stream(&mut self, config: stream::Config) -> anyhow::Result<stream::Info> {
+        Ok(self.get_jetstream().await?.update_stream(config).await?)
+    }
+
+    pub async fn delete_stream(&mut self, name: impl AsRef<str>) -> anyhow::Result<()> {
+        let _ = self.get_jetstream().await?.delete_stream(name).await?;
+
+        Ok(())
+    }
+
+    pub async fn get_stream_info(&mut self, name: impl AsRef<str>) -> anyhow::Result<stream::Info> {
+        let stream = self.get_jetstream().await?.get_stream(name).await?;
+
+        Ok(stream.cached_info().clone())
+    }
+
+    pub async fn get_stream_list(&mut self) -> anyhow::Result<Vec<stream::Info>> {
+        let mut vec = vec![];
+        let mut streams = self.get_jetstream().await?.streams();
+
+        while let Some(info) = streams.next().await {
+            vec.push(info?);
+        }
+
+        Ok(vec)
+    }
+
+    pub async fn purge_stream(
+        &mut self,
+        name: impl AsRef<str>,
+        subject: Option<String>,
+        keep: Option<u64>,
+    ) -> anyhow::Result<u64> {
+        let stream = self.get_jetstream().await?.get_stream(name).await?;
+        let purge = stream.purge();
+
+        let response = match (subject, keep) {
+            (Some(subject), Some(keep)) => purge.filter(subject).keep(keep).await?,
+            (Some(subject), None) => purge.filter(subject).await?,
+            (None, Some(keep)) => purge.keep(keep).await?,
+            (None, None) => purge.await?,
+        };
+
+        Ok(response.purged)
+    }
+
+    pub async fn get_stream_message(
+        &mut self,
+        stream: impl AsRef<str>,
+        sequence: u64,
+    ) -> anyhow::Result<Option<StreamMessage>> {
+        let stream = self.get_jetstream().await?.get_stream(stream).await?;
+
+        if stream.cached_info().config.allow_direct {
+            match stream.direct_get(sequence).await {
+                Ok(message) => Ok(Some(message)),
+                Err(e) if matches!(e.kind(), stream::DirectGetErrorKind::NotFound) => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        } else {
+            match stream.get_raw_message(sequence).await {
+                Ok(message) => Ok(Some(message)),
+                Err(e) if matches!(e.kind(), stream::RawMessageErrorKind::NoMessageFound) => {
+                    Ok(None)
+                }
+                Err(e) => Err(e.into()),
+            }
+        }
+    }
+
+    pub async fn get_last_stream_message(
+        &mut self,
+        stream: impl AsRef<str>,
+        subject: &str,
+    ) -> anyhow::Result<Option<StreamMessage>> {
+        let stream = self.get_jetstream().await?.get_stream(stream).await?;
+
+        if stream.cached_info().config.allow_direct {
+            match stream.direct_get_last_for_subject(subject).await {
+                Ok(message) => Ok(Some(message)),
+                Err(e) if matches!(e.kind(), stream::DirectGetErrorKind::NotFound) => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        } else {
+            match stream.get_last_raw_message_by_subject(subject).await {
+                Ok(message) => Ok(Some(message)),
+                Err(e) if matches!(e.kind(), stream::LastRawMessageErrorKind::NoMessageFound) => {
+                    Ok(None)
+                }
+                Err(e) => Err(e.into()),
+            }
+        }
+    }
+
+    pub async fn replay_stream(
+        &mut self,
+        stream: impl AsRef<str>,
+        start_sequence: Option<u64>,
+        subject: Option<String>,
+    ) -> anyhow::Result<Option<consumer::push::Ordered>> {
+        let deliver_subject = self.get_connection().await?.new_inbox();
+        let stream = self.get_jetstream().await?.get_stream(stream).await?;
+        let consumer = stream
+            .create_consumer(consumer::push::OrderedConfig {
+                deliver_subject,
+                deliver_policy: start_sequence.map_or(DeliverPolicy::All, |start_sequence| {
+                    DeliverPolicy::ByStartSequence { start_sequence }
+                }),
+                filter_subject: subject.unwrap_or_default(),
+                ..Default::default()
+            })
+            .await?;
+
+        if consumer.cached_info().num_pending == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(consumer.messages().await?))
+    }
+
+    pub async fn create_consumer(
+        &mut self,
+        stream: impl AsRef<str>,
+        config: consumer::Config,
+    ) -> anyhow::Result<consumer::Info> {
+        let stream = self.get_jetstream().await?.get_stream(stream).await?;
+        let consumer = stream.create_consumer(config).await?;
+
+        Ok(consumer.cached_info().clone())
+    }
+
+    pub async fn delete_consumer(
+        &mut self,
+        stream: impl AsRef<str>,
+        name: &str,
+    ) -> anyhow::Result<()> {
+        let stream = self.get_jetstream().await?.get_stream(stream).await?;
+        let _ = stream.delete_consumer(name).await?;
+
+        Ok(())
+    }
+
+    pub async fn get_consumer_info(
+        &mut self,
+        stream: impl AsRef<str>,
+        name: &str,
+    ) -> anyhow::Result<consumer::Info> {
+        let stream = self.get_jetstream().await?.get_stream(stream).await?;
+
+        Ok(stream.consumer_info(name).await?)
+    }
+
+    pub async fn get_consumer_list(
+        &mut self,
+        stream: impl AsRef<str>,
+    ) -> anyhow::Result<Vec<consumer::Info>> {
+        let stream = self.get_jetstream().await?.get_stream(stream).await?;
+        let mut vec = vec![];
+        let mut consumers = stream.consumers();
+
+        while let Some(info) = consumers.next().await {
+            vec.push(info?);
+        }
+
+        Ok(vec)
+    }
+
+    pub async fn fetch_messages(
+        &mut self,
+        stream: impl AsRef<str>,
+        consumer: &str,
+        batch: usize,
+        expires: Option<Duration>,
+    ) -> anyhow::Result<Vec<jetstream::Message>> {
+        let stream = self.get_jetstream().await?.get_stream(stream).await?;
+        let consumer: consumer::PullConsumer = stream.get_consumer(consumer).await?;
+
+        let mut messages = if let Some(expires) = expires {
+            consumer
+                .batch()
+                .max_messages(batch)
+                .expires(expires)
+                .messages()
+                .await?
+        } else {
+            consumer.fetch().max_messages(batch).messages().await?
+        };
+
+        let mut vec = Vec::with_capacity(batch);
+        while let Some(message) = messages.next().await {
+            vec.push(message.map_err(|e| anyhow::anyhow!(e))?);
+        }
+
+        Ok(vec)
+    }
+
+    pub async fn ack_message(
+        &mut self,
+        reply: impl ToString,
+        kind: jetstream::AckKind,
+    ) -> anyhow::Result<()> {
+        let conn = self.get_connection().await?;
+        conn.publish(reply.to_string(), kind.into()).await?;
+        conn.flush().await?;
+
+        Ok(())
+    }
+
     pub async fn invalidate_connection(&mut self) {
         let connection = { self.connection.take() };
 
@@ -176,6 +399,56 @@ impl NatsClient {
             .transpose()
     }
 
+    pub async fn get_entry(
+        &mut self,
+        bucket: impl ToString,
+        key: impl Into<String>,
+    ) -> anyhow::Result<Option<Entry>> {
+        let bucket = self.get_or_create_bucket(bucket).await?;
+
+        Ok(bucket.entry(key).await?)
+    }
+
+    /// Reads the latest values of an existing bucket, only the one of `key` if given.
+    ///
+    /// Unlike the other KV operations, this fails instead of creating a missing bucket.
+    /// The entries of all keys are looked up in a pipeline.
+    pub async fn get_entries(
+        &mut self,
+        bucket: impl ToString,
+        key: Option<String>,
+    ) -> anyhow::Result<Vec<Entry>> {
+        let bucket = self.get_bucket(bucket).await?;
+
+        let keys = match key {
+            Some(key) => vec![key],
+            None => bucket
+                .keys()
+                .await?
+                .collect::<Vec<_>>()
+                .await
+                .into_iter()
+                .collect::<Result<_, _>>()?,
+        };
+
+        let entries = futures::stream::iter(keys)
+            .map(|key| async move { bucket.entry(key).await })
+            .buffered(KV_PIPELINE_DEPTH)
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut vec = vec![];
+        for entry in entries {
+            if let Some(entry) = entry? {
+                if entry.operation == Operation::Put {
+                    vec.push(entry);
+                }
+            }
+        }
+
+        Ok(vec)
+    }
+
     pub async fn delete_value(
         &mut self,
         bucket: impl ToString,
@@ -187,11 +460,67 @@ impl NatsClient {
         Ok(())
     }
 
+    pub async fn purge_value(
+        &mut self,
+        bucket: impl ToString,
+        key: impl AsRef<str>,
+    ) -> anyhow::Result<()> {
+        let bucket = self.get_or_create_bucket(bucket).await?;
+        bucket.purge(key).await?;
+
+        Ok(())
+    }
+
+    pub async fn put_values(
+        &mut self,
+        bucket: impl ToString,
+        values: Vec<(String, Vec<u8>)>,
+    ) -> anyhow::Result<Vec<(String, u64)>> {
+        let bucket = self.get_or_create_bucket(bucket).await?;
+
+        futures::stream::iter(values)
+            .map(|(key, data)| async move {
+                let version = bucket.put(&key, data.into()).await?;
+                Ok((key, version))
+            })
+            .buffered(KV_PIPELINE_DEPTH)
+            .collect::<Vec<anyhow::Result<_>>>()
+            .await
+            .into_iter()
+            .collect()
+    }
+
+    pub async fn get_entries_by_keys(
+        &mut self,
+        bucket: impl ToString,
+        keys: Vec<String>,
+    ) -> anyhow::Result<Vec<(String, Option<Entry>)>> {
+        let bucket = self.get_or_create_bucket(bucket).await?;
+
+        futures::stream::iter(keys)
+            .map(|key| async move {
+                let entry = bucket
+                    .entry(key.clone())
+                    .await?
+                    .filter(|entry| entry.operation == Operation::Put);
+                Ok((key, entry))
+            })
+            .buffered(KV_PIPELINE_DEPTH)
+            .collect::<Vec<anyhow::Result<_>>>()
+            .await
+            .into_iter()
+            .collect()
+    }
+
     pub async fn get_server_info(&mut self) -> anyhow::Result<async_nats::ServerInfo> {
         let connection = self.get_connection().await?;
         Ok(connection.server_info())
     }
 
+    pub async fn get_account_info(&mut self) -> anyhow::Result<account::Account> {
+        Ok(self.get_jetstream().await?.query_account().await?)
+    }
+
     pub async fn get_file(
         &mut self,
         store: impl ToString,
@@ -206,17 +535,119 @@ impl NatsClient {
         Ok(content)
     }
 
-    pub async fn put_file(
+    /// Reads `length` bytes of an object starting at `offset`.
+    ///
+    /// Objects written the usual way keep every chunk but the last at the
+    /// maximum chunk size in consecutive stream sequences, so only the chunks
+    /// covering the range are fetched. Any other layout is read chunk by chunk
+    /// from the start until the range is covered.
+    pub async fn get_file_range(
         &mut self,
         store: impl ToString,
         name: impl AsRef<str>,
+        offset: u64,
+        length: u64,
+    ) -> anyhow::Result<Vec<u8>> {
+        let mut bucket = store.to_string();
+        let mut info = self.get_file_info(&bucket, name).await?;
+
+        if let Some(link) = info.options.as_ref().and_then(|o| o.link.clone()) {
+            let name = link
+                .name
+                .ok_or_else(|| anyhow::anyhow!("'{}' is a link to a store", info.name))?;
+            info = self.get_file_info(&link.bucket, name).await?;
+            bucket = link.bucket;
+        }
+
+        anyhow::ensure!(!info.deleted, "'{}' was deleted", info.name);
+
+        let end = offset.saturating_add(length).min(info.size as u64);
+        if offset >= end {
+            return Ok(Vec::new());
+        }
+
+        let chunk_size = info
+            .options
+            .as_ref()
+            .and_then(|o| o.max_chunk_size)
+            .unwrap_or(OBJECT_CHUNK_SIZE) as u64;
+        let chunks = info.chunks as u64;
+        let subject = format!("$O.{}.C.{}", bucket, info.nuid);
+        let stream = self
+            .get_jetstream()
+            .await?
+            .get_stream(format!("OBJ_{}", bucket))
+            .await?;
+
+        let first = stream.get_first_raw_message_by_subject(&subject, 1).await?;
+        let last = stream.get_last_raw_message_by_subject(&subject).await?;
+
+        // With `chunks` consecutive sequences, none of them larger than the
+        // chunk size, the last one can only have the expected length if all
+        // the others are full.
+        let fixed_layout = last.sequence - first.sequence + 1 == chunks
+            && chunks == (info.size as u64).div_ceil(chunk_size)
+            && last.payload.len() as u64 == info.size as u64 - (chunks - 1) * chunk_size;
+
+        let mut content = Vec::with_capacity((end - offset) as usize);
+
+        if fixed_layout {
+            for index in offset / chunk_size..=(end - 1) / chunk_size {
+                let sequence = first.sequence + index;
+                let chunk = if sequence == first.sequence {
+                    first.payload.clone()
+                } else if sequence == last.sequence {
+                    last.payload.clone()
+                } else {
+                    stream.get_raw_message(sequence).await?.payload
+                };
+
+                append_range(&mut content, &chunk, index * chunk_size, offset, end);
+            }
+        } else {
+            let mut chunk = first;
+            let mut position = 0;
+            loop {
+                append_range(&mut content, &chunk.payload, position, offset, end);
+                position += chunk.payload.len() as u64;
+
+                if position >= end || chunk.sequence >= last.sequence {
+                    break;
+                }
+
+                chunk = stream
+                    .get_first_raw_message_by_subject(&subject, chunk.sequence + 1)
+                    .await?;
+            }
+        }
+
+        Ok(content)
+    }
+
+    pub async fn open_file(
+        &mut self,
+        store: impl ToString,
+        name: impl AsRef<str> + Send,
+    ) -> anyhow::Result<Object> {
+        let store = self.get_or_create_object_store(store).await?;
+
+        Ok(store.get(name).await?)
+    }
+
+    pub async fn get_object_store(&mut self, store: impl ToString) -> anyhow::Result<ObjectStore> {
+        self.get_or_create_object_store(store).await.cloned()
+    }
+
+    pub async fn put_file(
+        &mut self,
+        store: impl ToString,
+        meta: impl Into<ObjectMetadata>,
         content: Vec<u8>,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<ObjectInfo> {
         let store = self.get_or_create_object_store(store).await?;
         let mut reader = BufReader::new(Cursor::new(content));
-        let _ = store.put(name.as_ref(), &mut reader).await?;
 
-        Ok(())
+        Ok(store.put(meta.into(), &mut reader).await?)
     }
 
     pub async fn delete_file(
@@ -237,20 +668,149 @@ impl NatsClient {
         store.info(name).await.map_err(|e| e.into())
     }
 
-    pub async fn get_file_list(&mut self, store: impl ToString) -> anyhow::Result<Vec<ObjectInfo>> {
-        let store = self.get_or_create_object_store(store).await?;
-        let mut vec = vec![];
-        let mut list = store.list().await?;
+    pub async fn get_file_list(
+        &mut self,
+        store: impl ToString,
+        prefix: Option<&str>,
+        include_deleted: bool,
+    ) -> anyhow::Result<Vec<ObjectInfo>> {
+        let mut vec = if include_deleted {
+            self.get_file_list_with_deleted(store).await?
+        } else {
+            let store = self.get_or_create_object_store(store).await?;
+            let mut vec = vec![];
+            let mut list = store.list().await?;
+
+            while let Some(object) = list.next().await {
+                vec.push(object?);
+            }
 
-        while let Some(object) = list.next().await {
-            vec.push(object?);
+            vec
+        };
+
+        if let Some(prefix) = prefix {
+            vec.retain(|object| object.name.starts_with(prefix));
         }
 
         Ok(vec)
     }
+
+    pub async fn create_object_store(
+        &mut self,
+        config: async_nats::jetstream::object_store::Config,
+    ) -> anyhow::Result<()> {
+        let bucket = config.bucket.clone();
+        let store = self
+            .get_jetstream()
+            .await?
+            .create_object_store(config)
+            .await?;
+        let _ = self.cached_object_stores.insert(bucket, store);
+
+        Ok(())
+    }
+
+    pub async fn seal_object_store(&mut self, store: impl AsRef<str>) -> anyhow::Result<()> {
+        let mut store = self.get_jetstream().await?.get_object_store(store).await?;
+
+        store.seal().await.map_err(|e| e.into())
+    }
+
+    pub async fn delete_object_store(&mut self, store: impl AsRef<str>) -> anyhow::Result<()> {
+        self.get_jetstream()
+            .await?
+            .delete_object_store(store.as_ref())
+            .await?;
+        let _ = self.cached_object_stores.remove(store.as_ref());
+
+        Ok(())
+    }
+
+    pub async fn get_object_store_status(
+        &mut self,
+        store: impl AsRef<str>,
+    ) -> anyhow::Result<async_nats::jetstream::stream::Info> {
+        let stream = self
+            .get_jetstream()
+            .await?
+            .get_stream(format!("OBJ_{}", store.as_ref()))
+            .await?;
+
+        Ok(stream.cached_info().clone())
+    }
+
+    pub async fn put_link(
+        &mut self,
+        store: impl ToString,
+        name: impl ToString,
+        target_store: impl ToString,
+        target_name: impl AsRef<str>,
+    ) -> anyhow::Result<ObjectInfo> {
+        let target = self
+            .get_or_create_object_store(target_store)
+            .await?
+            .info(target_name)
+            .await?;
+        let store = self.get_or_create_object_store(store).await?;
+
+        Ok(store.add_link(name, &target).await?)
+    }
+
+    pub async fn put_store_link(
+        &mut self,
+        store: impl ToString,
+        name: impl ToString,
+        target_store: impl ToString,
+    ) -> anyhow::Result<ObjectInfo> {
+        let store = self.get_or_create_object_store(store).await?;
+
+        Ok(store.add_bucket_link(name, target_store).await?)
+    }
 }
 
 impl NatsClient {
+    /// Lists the latest metadata of every object, including the deleted ones
+    /// that `ObjectStore::list` skips.
+    async fn get_file_list_with_deleted(
+        &mut self,
+        store: impl ToString,
+    ) -> anyhow::Result<Vec<ObjectInfo>> {
+        let bucket = store.to_string();
+        let _ = self.get_or_create_object_store(&bucket).await?;
+
+        let deliver_subject = self.get_connection().await?.new_inbox();
+        let stream = self
+            .get_jetstream()
+            .await?
+            .get_stream(format!("OBJ_{}", bucket))
+            .await?;
+        let consumer = stream
+            .create_consumer(async_nats::jetstream::consumer::push::OrderedConfig {
+                deliver_subject,
+                deliver_policy: DeliverPolicy::LastPerSubject,
+                filter_subject: format!("$O.{}.M.>", bucket),
+                ..Default::default()
+            })
+            .await?;
+
+        let mut vec = vec![];
+        if consumer.cached_info().num_pending == 0 {
+            return Ok(vec);
+        }
+
+        let mut messages = consumer.messages().await?;
+        while let Some(message) = messages.next().await {
+            let message = message?;
+            vec.push(serde_json::from_slice::<ObjectInfo>(&message.payload)?);
+
+            if message.info().map_err(|e| anyhow::anyhow!(e))?.pending == 0 {
+                break;
+            }
+        }
+
+        Ok(vec)
+    }
+
     #[allow(clippy::expect_used)]
     async fn get_connection(&mut self) -> anyhow::Result<&Client> {
         if self.connection.is_none() {
@@ -275,6 +835,26 @@ impl NatsClient {
             .expect("unreachable, must be initialized"))
     }
 
+    /// Looks up an existing bucket, failing instead of creating it.
+    async fn get_bucket(&mut self, bucket: impl ToString) -> anyhow::Result<&Store> {
+        let bucket = bucket.to_string();
+
+        if !self.cached_buckets.contains_key(&bucket) {
+            let store = self
+                .get_jetstream()
+                .await?
+                .get_key_value(&bucket)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to open KV bucket '{bucket}': {e}"))?;
+
+            let _ = self.cached_buckets.insert(bucket.clone(), store);
+        }
+
+        self.cached_buckets
+            .get(&bucket)
+            .ok_or_else(|| anyhow::anyhow!("KV bucket '{bucket}' is not cached"))
+    }
+
     #[allow(clippy::expect_used)]
     async fn get_or_create_bucket(&mut self, bucket: impl ToString) -> anyhow::Result<&Store> {
         let bucket = bucket.to_string();
@@ -376,3 +956,12 @@ impl NatsClient {
         Ok(())
     }
 }
+
+/// Appends the part of `chunk`, which starts at `position` in the object,
+/// that falls into the `offset..end` range.
+fn append_range(content: &mut Vec<u8>, chunk: &[u8], position: u64, offset: u64, end: u64) {
+    let from = offset.saturating_sub(position) as usize;
+    let to = end.saturating_sub(position).min(chunk.len() as u64) as usize;
+
+    content.extend_from_slice(chunk.get(from..to).unwrap_or_default());
+}
@@ -50,6 +50,323 @@ mod tests {
         assert!(res.is_ok(), "nats_publish occurs error: {:?}", res);
     }
 
+    #[pg_test]
+    fn test_pgnats_stream_management() {
+        use pgrx::JsonB;
+        use serde_json::json;
+
+        let name = "TEST_STREAM_MANAGEMENT";
+
+        let create_res = api::nats_stream_create(JsonB(json!({
+            "name": name,
+            "subjects": ["stream_management.a"],
+            "max_msgs": 100,
+            "storage": "memory"
+        })));
+        assert!(
+            create_res.is_ok(),
+            "stream_create failed: {:?}",
+            create_res.as_ref().err()
+        );
+        let info = create_res.unwrap().next().unwrap();
+        assert_eq!(info.0, name);
+        assert_eq!(info.1 .0["max_msgs"], 100);
+        assert_eq!(info.1 .0["storage"], "memory");
+        assert_eq!(info.3, 0);
+
+        let update_res = api::nats_stream_update(JsonB(json!({
+            "name": name,
+            "subjects": ["stream_management.a", "stream_management.b"],
+            "max_msgs": 100,
+            "storage": "memory"
+        })));
+        assert!(
+            update_res.is_ok(),
+            "stream_update failed: {:?}",
+            update_res.as_ref().err()
+        );
+        assert_eq!(
+            update_res.unwrap().next().unwrap().1 .0["subjects"],
+            json!(["stream_management.a", "stream_management.b"])
+        );
+
+        for subject in [
+            "stream_management.a",
+            "stream_management.b",
+            "stream_management.b",
+        ] {
+            api::nats_publish_text_stream(subject, "message".to_string(), None).unwrap();
+        }
+
+        let info = api::nats_stream_info(name).unwrap().next().unwrap();
+        assert_eq!(info.3, 3);
+        assert_eq!(info.5, 1);
+        assert_eq!(info.6, 3);
+
+        assert!(api::nats_stream_list().unwrap().any(|info| info.0 == name));
+
+        assert_eq!(
+            api::nats_stream_purge(name, Some("stream_management.b".to_string()), Some(1)).unwrap(),
+            1
+        );
+        assert_eq!(api::nats_stream_purge(name, None, None).unwrap(), 2);
+
+        let res = api::nats_stream_delete(name);
+        assert!(res.is_ok(), "stream_delete failed: {:?}", res);
+        assert!(api::nats_stream_info(name).is_err());
+    }
+
+    #[pg_test]
+    fn test_pgnats_jetstream_account_info() {
+        use pgrx::JsonB;
+        use serde_json::json;
+
+        let stream = "TEST_JETSTREAM_ACCOUNT_INFO";
+
+        api::nats_stream_create(JsonB(json!({
+            "name": stream,
+            "subjects": ["jetstream_account_info.>"],
+            "storage": "memory"
+        })))
+        .unwrap();
+        api::nats_publish_text_stream("jetstream_account_info.a", "message".to_string(), None)
+            .unwrap();
+
+        let res = api::nats_jetstream_account_info();
+        assert!(
+            res.is_ok(),
+            "nats_jetstream_account_info failed: {:?}",
+            res.as_ref().err()
+        );
+        let info = res.unwrap().next().unwrap();
+        assert!(info.4 >= 1);
+        assert!(info.11 > 0);
+
+        api::nats_stream_delete(stream).unwrap();
+    }
+
+    #[pg_test]
+    fn test_pgnats_stream_get_message() {
+        use pgrx::JsonB;
+        use serde_json::json;
+
+        let stream = "TEST_STREAM_GET_MESSAGE";
+        let mut config = json!({
+            "name": stream,
+            "subjects": ["stream_get_message.>"],
+            "storage": "memory"
+        });
+
+        api::nats_stream_create(JsonB(config.clone())).unwrap();
+
+        api::nats_publish_text_stream(
+            "stream_get_message.a",
+            "first".to_string(),
+            Some(JsonB(json!({ "Event": "created" }))),
+        )
+        .unwrap();
+        api::nats_publish_text_stream("stream_get_message.b", "second".to_string(), None).unwrap();
+        api::nats_publish_text_stream("stream_get_message.a", "third".to_string(), None).unwrap();
+
+        for allow_direct in [false, true] {
+            config["allow_direct"] = json!(allow_direct);
+            api::nats_stream_update(JsonB(config.clone())).unwrap();
+
+            let res = api::nats_stream_get_message(stream, 1);
+            assert!(
+                res.is_ok(),
+                "nats_stream_get_message failed: {:?}",
+                res.as_ref().err()
+            );
+            let rows: Vec<_> = res.unwrap().collect();
+            assert_eq!(rows.len(), 1);
+            assert_eq!(rows[0].0, "stream_get_message.a");
+            assert_eq!(rows[0].1, 1);
+            assert_eq!(rows[0].2, b"first");
+            assert_eq!(rows[0].3.as_ref().unwrap().0["Event"], "created");
+
+            let rows: Vec<_> = api::nats_stream_get_last(stream, "stream_get_message.a")
+                .unwrap()
+                .collect();
+            assert_eq!(rows.len(), 1);
+            assert_eq!(rows[0].1, 3);
+            assert_eq!(rows[0].2, b"third");
+            assert!(rows[0].3.is_none());
+
+            assert_eq!(api::nats_stream_get_message(stream, 10).unwrap().count(), 0);
+            assert_eq!(
+                api::nats_stream_get_last(stream, "stream_get_message.c")
+                    .unwrap()
+                    .count(),
+                0
+            );
+        }
+
+        assert!(api::nats_stream_get_message(stream, -1).is_err());
+
+        api::nats_stream_delete(stream).unwrap();
+    }
+
+    #[pg_test]
+    fn test_pgnats_consumer_management() {
+        use pgrx::JsonB;
+        use serde_json::json;
+
+        let stream = "TEST_CONSUMER_MANAGEMENT";
+
+        api::nats_stream_create(JsonB(json!({
+            "name": stream,
+            "subjects": ["consumer_management.>"],
+            "storage": "memory"
+        })))
+        .unwrap();
+
+        for subject in [
+            "consumer_management.a",
+            "consumer_management.a",
+            "consumer_management.b",
+        ] {
+            api::nats_publish_text_stream(subject, "message".to_string(), None).unwrap();
+        }
+
+        let create_res = api::nats_consumer_create(
+            stream,
+            JsonB(json!({
+                "durable_name": "pull",
+                "filter_subject": "consumer_management.a",
+                "ack_policy": "explicit",
+                "max_deliver": 5,
+                "backoff": [1_000_000_000, 5_000_000_000u64]
+            })),
+        );
+        assert!(
+            create_res.is_ok(),
+            "consumer_create failed: {:?}",
+            create_res.as_ref().err()
+        );
+        let info = create_res.unwrap().next().unwrap();
+        assert_eq!(info.0, stream);
+        assert_eq!(info.1, "pull");
+        assert_eq!(info.2 .0["max_deliver"], 5);
+        assert_eq!(info.6, 0);
+        assert_eq!(info.8, 2);
+
+        let push_res = api::nats_consumer_create(
+            stream,
+            JsonB(json!({
+                "durable_name": "push",
+                "deliver_subject": "consumer_management_deliver",
+                "ack_policy": "none"
+            })),
+        );
+        assert!(
+            push_res.is_ok(),
+            "consumer_create failed: {:?}",
+            push_res.as_ref().err()
+        );
+
+        let info = api::nats_consumer_info(stream, "pull")
+            .unwrap()
+            .next()
+            .unwrap();
+        assert_eq!(info.8, 2);
+
+        let mut names: Vec<String> = api::nats_consumer_list(stream)
+            .unwrap()
+            .map(|info| info.1)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["pull".to_string(), "push".to_string()]);
+
+        let res = api::nats_consumer_delete(stream, "pull");
+        assert!(res.is_ok(), "consumer_delete failed: {:?}", res);
+        assert!(api::nats_consumer_info(stream, "pull").is_err());
+
+        api::nats_stream_delete(stream).unwrap();
+    }
+
+    #[pg_test]
+    fn test_pgnats_fetch_and_ack() {
+        use pgrx::{datum::Interval, JsonB, Spi};
+        use serde_json::json;
+
+        let stream = "TEST_FETCH_AND_ACK";
+        let consumer = "worker";
+
+        api::nats_stream_create(JsonB(json!({
+            "name": stream,
+            "subjects": ["fetch_and_ack.>"],
+            "storage": "memory"
+        })))
+        .unwrap();
+        api::nats_consumer_create(
+            stream,
+            JsonB(json!({
+                "durable_name": consumer,
+                "ack_policy": "explicit"
+            })),
+        )
+        .unwrap();
+
+        for i in 0..3 {
+            api::nats_publish_text_stream(
+                "fetch_and_ack.orders",
+                format!("message {i}"),
+                Some(JsonB(json!({ "Index": i.to_string() }))),
+            )
+            .unwrap();
+        }
+
+        let res = api::nats_fetch(stream, consumer, 2, None);
+        assert!(res.is_ok(), "nats_fetch failed: {:?}", res.as_ref().err());
+        let rows: Vec<_> = res.unwrap().collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].0, "fetch_and_ack.orders");
+        assert_eq!(rows[0].1, b"message 0");
+        assert_eq!(rows[0].2.as_ref().unwrap().0["Index"], "0");
+        assert_eq!(rows[0].3, 1);
+        assert_eq!(rows[1].4, 2);
+        assert_eq!(rows[1].5, 1);
+
+        let res = api::nats_in_progress(rows[0].7.as_deref().unwrap());
+        assert!(res.is_ok(), "nats_in_progress failed: {:?}", res);
+        let res = api::nats_ack(rows[0].7.as_deref().unwrap());
+        assert!(res.is_ok(), "nats_ack failed: {:?}", res);
+        let res = api::nats_nak(rows[1].7.as_deref().unwrap(), None);
+        assert!(res.is_ok(), "nats_nak failed: {:?}", res);
+
+        // Acknowledgements wait for the commit, which the test transaction never reaches
+        assert_eq!(crate::ctx::CTX.with_borrow(|ctx| ctx.pending_acks.len()), 3);
+        crate::ctx::send_pending_acks();
+
+        let expires = Spi::get_one::<Interval>("SELECT '1 second'::interval")
+            .unwrap()
+            .unwrap();
+        let rows: Vec<_> = api::nats_fetch(stream, consumer, 10, Some(expires))
+            .unwrap()
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().any(|row| row.3 == 2 && row.5 == 2));
+        assert!(rows.iter().any(|row| row.3 == 3 && row.5 == 1));
+
+        for row in &rows {
+            let res = api::nats_term(row.7.as_deref().unwrap());
+            assert!(res.is_ok(), "nats_term failed: {:?}", res);
+        }
+        crate::ctx::send_pending_acks();
+
+        let info = api::nats_consumer_info(stream, consumer)
+            .unwrap()
+            .next()
+            .unwrap();
+        assert_eq!(info.8, 0);
+        assert_eq!(info.9, 0);
+
+        assert!(api::nats_fetch(stream, consumer, 0, None).is_err());
+
+        api::nats_stream_delete(stream).unwrap();
+    }
+
     #[pg_test]
     fn test_pgnats_publish_with_reply_and_headers() {
         use pgrx::JsonB;
@@ -226,6 +543,32 @@ mod tests {
         assert_eq!(json_value, returned_json.map(|v| v.0).unwrap());
     }
 
+    #[cfg(feature = "kv")]
+    #[pg_test]
+    fn test_pgnats_kv_entry() {
+        let bucket = "test_default".to_string();
+        let key = "entry_key";
+        let text = "Hello, entry!";
+
+        let revision = api::nats_put_text(bucket.clone(), key, text).unwrap();
+
+        let entry_res = api::nats_kv_entry(bucket.clone(), key);
+        assert!(
+            entry_res.is_ok(),
+            "nats_kv_entry occurs error: {:?}",
+            entry_res.as_ref().err()
+        );
+
+        let mut entry = entry_res.unwrap();
+        let (value, entry_revision, _, _, operation) = entry.next().unwrap();
+        assert_eq!(text.as_bytes(), value.as_slice());
+        assert_eq!(revision, entry_revision);
+        assert_eq!("PUT", operation);
+
+        let missing = api::nats_kv_entry(bucket.clone(), "missing_entry_key").unwrap();
+        assert_eq!(0, missing.count());
+    }
+
     #[cfg(feature = "kv")]
     #[pg_test]
     fn test_pgnats_delete_value() {
@@ -250,6 +593,262 @@ mod tests {
         assert_eq!(None, value);
     }
 
+    #[cfg(feature = "kv")]
+    #[pg_test]
+    fn test_pgnats_kv_purge() {
+        let bucket = "test_default".to_string();
+        let key = "purge_key";
+
+        api::nats_put_text(bucket.clone(), key, "to be purged").unwrap();
+
+        let purge_res = api::nats_kv_purge(bucket.clone(), key);
+        assert!(
+            purge_res.is_ok(),
+            "nats_kv_purge occurs error: {:?}",
+            purge_res
+        );
+
+        let entry = api::nats_kv_entry(bucket.clone(), key)
+            .unwrap()
+            .next()
+            .unwrap();
+        assert_eq!("PURGE", entry.4);
+        assert_eq!(None, api::nats_get_text(bucket.clone(), key).unwrap());
+    }
+
+    #[cfg(feature = "kv")]
+    #[pg_test]
+    fn test_pgnats_kv_put_and_get_many() {
+        use serde_json::json;
+
+        let bucket = "test_default".to_string();
+        let values = json!({
+            "many_key_1": {"n": 1},
+            "many_key_2": "two",
+        });
+
+        let put_res = api::nats_kv_put_many(bucket.clone(), pgrx::JsonB(values));
+        assert!(
+            put_res.is_ok(),
+            "nats_kv_put_many occurs error: {:?}",
+            put_res.as_ref().err()
+        );
+        let revisions: Vec<(String, i64)> = put_res.unwrap().collect();
+        assert_eq!(2, revisions.len());
+
+        let get_res = api::nats_kv_get_many(
+            bucket.clone(),
+            vec![
+                "many_key_2".to_string(),
+                "many_missing_key".to_string(),
+                "many_key_1".to_string(),
+            ],
+        );
+        assert!(
+            get_res.is_ok(),
+            "nats_kv_get_many occurs error: {:?}",
+            get_res.as_ref().err()
+        );
+
+        let rows: Vec<_> = get_res.unwrap().collect();
+        assert_eq!(3, rows.len());
+
+        let (key, value, revision) = &rows[0];
+        assert_eq!("many_key_2", key);
+        assert_eq!(Some(br#""two""#.to_vec()), *value);
+        assert!(revisions.contains(&(key.clone(), revision.unwrap())));
+
+        let (key, value, revision) = &rows[1];
+        assert_eq!("many_missing_key", key);
+        assert_eq!((&None, &None), (value, revision));
+
+        let (key, value, _) = &rows[2];
+        assert_eq!("many_key_1", key);
+        assert_eq!(
+            json!({"n": 1}),
+            serde_json::from_slice::<serde_json::Value>(value.as_ref().unwrap()).unwrap()
+        );
+    }
+
+    #[cfg(feature = "kv")]
+    #[pg_test]
+    fn test_pgnats_kv_foreign_table() {
+        use pgrx::{JsonB, Spi};
+        use serde_json::json;
+
+        let bucket = "test_kv_foreign_table".to_string();
+
+        Spi::run(
+            r#"
+            CREATE FOREIGN DATA WRAPPER pgnats_fdw_kv_test HANDLER pgnats_fdw_handler;
+            CREATE SERVER pgnats_fdw_kv_test_server FOREIGN DATA WRAPPER pgnats_fdw_kv_test;
+            CREATE FOREIGN TABLE test_kv_cfg (key text, value jsonb, revision bigint)
+                SERVER pgnats_fdw_kv_test_server OPTIONS (bucket 'test_kv_foreign_table');
+            "#,
+        )
+        .unwrap();
+
+        Spi::run(
+            r#"INSERT INTO test_kv_cfg (key, value) VALUES ('a', '{"n": 1}'), ('b', '{"n": 2}')"#,
+        )
+        .unwrap();
+
+        let value = Spi::get_one::<JsonB>("SELECT value FROM test_kv_cfg WHERE key = 'a'")
+            .unwrap()
+            .unwrap();
+        assert_eq!(json!({"n": 1}), value.0);
+
+        let revision = Spi::get_one::<i64>("SELECT revision FROM test_kv_cfg WHERE key = 'b'")
+            .unwrap()
+            .unwrap();
+        let (_, entry_revision, _, _, _) = api::nats_kv_entry(bucket.clone(), "b")
+            .unwrap()
+            .next()
+            .unwrap();
+        assert_eq!(entry_revision, revision);
+
+        let returned_revision = Spi::connect_mut(|client| {
+            client
+                .update(
+                    r#"UPDATE test_kv_cfg SET value = '{"n": 3}' WHERE key = 'b' RETURNING revision"#,
+                    None,
+                    &[],
+                )?
+                .first()
+                .get_one::<i64>()
+        })
+        .unwrap();
+        let value = api::nats_get_jsonb(bucket.clone(), "b").unwrap().unwrap();
+        assert_eq!(json!({"n": 3}), value.0);
+
+        let (_, entry_revision, _, _, _) = api::nats_kv_entry(bucket.clone(), "b")
+            .unwrap()
+            .next()
+            .unwrap();
+        assert_eq!(Some(entry_revision), returned_revision);
+        assert!(entry_revision > revision);
+
+        Spi::run("DELETE FROM test_kv_cfg WHERE key = 'a'").unwrap();
+        assert_eq!(None, api::nats_get_text(bucket.clone(), "a").unwrap());
+
+        let keys = Spi::get_one::<Vec<String>>("SELECT array_agg(key) FROM test_kv_cfg")
+            .unwrap()
+            .unwrap();
+        assert_eq!(vec!["b".to_string()], keys);
+
+        // Writes reach the bucket right away and are not undone by a rollback.
+        unsafe { pgrx::pg_sys::BeginInternalSubTransaction(std::ptr::null()) };
+        Spi::run(r#"INSERT INTO test_kv_cfg (key, value) VALUES ('c', '{"n": 4}')"#).unwrap();
+        unsafe { pgrx::pg_sys::RollbackAndReleaseCurrentSubTransaction() };
+
+        let value = api::nats_get_jsonb(bucket.clone(), "c").unwrap().unwrap();
+        assert_eq!(json!({"n": 4}), value.0);
+
+        // Reading a table over a missing bucket fails instead of creating it.
+        Spi::run(
+            r#"
+            CREATE FOREIGN TABLE test_kv_missing (key text, value jsonb)
+                SERVER pgnats_fdw_kv_test_server OPTIONS (bucket 'test_kv_missing_bucket');
+            "#,
+        )
+        .unwrap();
+
+        let (context, owner) = unsafe {
+            let context = pgrx::pg_sys::CurrentMemoryContext;
+            let owner = pgrx::pg_sys::CurrentResourceOwner;
+            pgrx::pg_sys::BeginInternalSubTransaction(std::ptr::null());
+            (context, owner)
+        };
+        let scanned = pgrx::PgTryBuilder::new(|| Spi::run("SELECT * FROM test_kv_missing").is_ok())
+            .catch_others(|_| false)
+            .execute();
+        unsafe {
+            pgrx::pg_sys::RollbackAndReleaseCurrentSubTransaction();
+            pgrx::pg_sys::CurrentMemoryContext = context;
+            pgrx::pg_sys::CurrentResourceOwner = owner;
+        }
+        assert!(!scanned);
+    }
+
+    #[pg_test]
+    fn test_pgnats_stream_foreign_table() {
+        use pgrx::{JsonB, Spi};
+        use serde_json::json;
+
+        let stream = "TEST_STREAM_FOREIGN_TABLE";
+
+        api::nats_stream_create(JsonB(json!({
+            "name": stream,
+            "subjects": ["stream_foreign_table.>"],
+            "storage": "memory"
+        })))
+        .unwrap();
+
+        for id in 1..=5 {
+            let kind = if id % 2 == 0 { "even" } else { "odd" };
+            api::nats_publish_jsonb_stream(
+                &format!("stream_foreign_table.{kind}"),
+                JsonB(json!({ "id": id, "kind": kind })),
+                Some(JsonB(json!({ "Source": "test" }))),
+            )
+            .unwrap();
+        }
+
+        Spi::run(
+            r#"
+            CREATE FOREIGN DATA WRAPPER pgnats_fdw_stream_test HANDLER pgnats_fdw_handler;
+            CREATE SERVER pgnats_fdw_stream_test_server FOREIGN DATA WRAPPER pgnats_fdw_stream_test;
+            CREATE FOREIGN TABLE test_stream_events (
+                seq bigint,
+                subject text,
+                payload jsonb,
+                headers jsonb,
+                published timestamptz,
+                id int,
+                kind text
+            ) SERVER pgnats_fdw_stream_test_server OPTIONS (stream 'TEST_STREAM_FOREIGN_TABLE', format 'jsonb');
+            "#,
+        )
+        .unwrap();
+
+        let ids = |condition: &str| {
+            Spi::get_one::<Vec<i32>>(&format!(
+                "SELECT array_agg(id ORDER BY seq) FROM test_stream_events WHERE {condition}"
+            ))
+            .unwrap()
+            .unwrap_or_default()
+        };
+
+        assert_eq!(vec![1, 2, 3, 4, 5], ids("true"));
+        assert_eq!(vec![3, 4, 5], ids("seq > 2"));
+        assert_eq!(vec![2, 3], ids("seq BETWEEN 2 AND 3"));
+        assert_eq!(vec![1, 2], ids("3 > seq"));
+        assert_eq!(vec![2, 4], ids("subject = 'stream_foreign_table.even'"));
+        assert_eq!(vec![5], ids("seq >= 4 AND kind = 'odd'"));
+        assert!(ids("seq > 100").is_empty());
+        assert!(ids("seq > 3 AND seq < 2").is_empty());
+
+        let payload = Spi::get_one::<JsonB>("SELECT payload FROM test_stream_events WHERE seq = 4")
+            .unwrap()
+            .unwrap();
+        assert_eq!(json!({ "id": 4, "kind": "even" }), payload.0);
+
+        let source = Spi::get_one::<String>(
+            "SELECT headers->>'Source' FROM test_stream_events WHERE seq = 1",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!("test", source);
+
+        let has_published =
+            Spi::get_one::<bool>("SELECT bool_and(published IS NOT NULL) FROM test_stream_events")
+                .unwrap()
+                .unwrap();
+        assert!(has_published);
+
+        api::nats_stream_delete(stream).unwrap();
+    }
+
     #[cfg(feature = "object_store")]
     #[pg_test]
     fn test_pgnats_put_and_get_file() {
@@ -257,8 +856,12 @@ mod tests {
         let key = "test_file.txt";
         let content = b"file content for testing".to_vec();
 
-        let put_res = api::nats_put_file(bucket.clone(), key, content.clone());
-        assert!(put_res.is_ok(), "put_file failed: {:?}", put_res);
+        let put_res = api::nats_put_file(bucket.clone(), key, content.clone(), None, None, None);
+        assert!(
+            put_res.is_ok(),
+            "put_file failed: {:?}",
+            put_res.as_ref().err()
+        );
 
         let get_res = api::nats_get_file(bucket.clone(), key);
         assert!(get_res.is_ok(), "get_file failed: {:?}", get_res);
@@ -267,6 +870,162 @@ mod tests {
         assert_eq!(content, returned);
     }
 
+    #[cfg(feature = "object_store")]
+    #[pg_test]
+    fn test_pgnats_put_and_get_file_large_object() {
+        use pgrx::{pg_sys, Spi};
+
+        let bucket = "test_file_lo".to_string();
+        let key = "large_object.bin";
+        let content: Vec<u8> = (0..600_000u32).map(|i| (i % 251) as u8).collect();
+
+        let lo_oid = Spi::get_one_with_args::<pg_sys::Oid>(
+            "SELECT lo_from_bytea(0, $1)",
+            &[content.clone().into()],
+        )
+        .unwrap()
+        .unwrap();
+
+        let put_res = api::nats_put_file_from_lo(bucket.clone(), key, lo_oid);
+        assert!(put_res.is_ok(), "put_file_from_lo failed: {:?}", put_res);
+        assert_eq!(content, api::nats_get_file(bucket.clone(), key).unwrap());
+
+        let get_res = api::nats_get_file_to_lo(bucket.clone(), key);
+        assert!(get_res.is_ok(), "get_file_to_lo failed: {:?}", get_res);
+
+        let returned =
+            Spi::get_one_with_args::<Vec<u8>>("SELECT lo_get($1)", &[get_res.unwrap().into()])
+                .unwrap()
+                .unwrap();
+        assert_eq!(content, returned);
+
+        let missing = pg_sys::Oid::from(u32::MAX);
+        assert!(api::nats_put_file_from_lo(bucket.clone(), "missing.bin", missing).is_err());
+        assert!(api::nats_get_file(bucket.clone(), "missing.bin").is_err());
+    }
+
+    #[cfg(feature = "object_store")]
+    #[pg_test]
+    fn test_pgnats_get_file_range() {
+        let bucket = "test_file_range".to_string();
+        let key = "range.bin";
+        let content: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+
+        api::nats_put_file(bucket.clone(), key, content.clone(), None, None, None).unwrap();
+
+        for (offset, length) in [(0, 16), (131_000, 2_000), (290_000, 20_000), (0, 300_000)] {
+            let res = api::nats_get_file_range(bucket.clone(), key, offset, length);
+            assert!(res.is_ok(), "get_file_range failed: {:?}", res);
+
+            let start = offset as usize;
+            let end = (start + length as usize).min(content.len());
+            assert_eq!(&content[start..end], res.unwrap().as_slice());
+        }
+
+        assert!(api::nats_get_file_range(bucket.clone(), key, 400_000, 10)
+            .unwrap()
+            .is_empty());
+        assert!(api::nats_get_file_range(bucket.clone(), key, -1, 10).is_err());
+
+        let link = "range_link.bin";
+        api::nats_put_link(bucket.clone(), link.to_string(), bucket.clone(), key).unwrap();
+        api::nats_delete_file(bucket.clone(), key).unwrap();
+
+        assert!(api::nats_get_file_range(bucket.clone(), key, 0, 16).is_err());
+        assert!(api::nats_get_file_range(bucket.clone(), link, 0, 16).is_err());
+    }
+
+    #[cfg(feature = "object_store")]
+    #[pg_test]
+    fn test_pgnats_object_store_management() {
+        use pgrx::{datum::Interval, Spi};
+
+        let store = "test_store_management";
+        let ttl = Spi::get_one::<Interval>("SELECT '1 day'::interval")
+            .unwrap()
+            .unwrap();
+
+        let res = api::nats_object_store_create(
+            store.to_string(),
+            Some("Managed store".to_string()),
+            Some(ttl),
+            Some(1024 * 1024),
+            Some(1),
+            true,
+        );
+        assert!(res.is_ok(), "object_store_create failed: {:?}", res);
+
+        let status_res = api::nats_object_store_status(store);
+        assert!(
+            status_res.is_ok(),
+            "object_store_status failed: {:?}",
+            status_res.as_ref().err()
+        );
+        let status = status_res.unwrap().next().unwrap();
+        assert_eq!(status.0, store);
+        assert_eq!(status.1.as_deref(), Some("Managed store"));
+        assert_eq!(status.2, Some(ttl));
+        assert_eq!(status.3, Some(1024 * 1024));
+        assert_eq!(status.4, 1);
+        assert_eq!(status.5, "file");
+        assert!(status.6);
+        assert!(!status.7);
+
+        api::nats_put_file(
+            store.to_string(),
+            "original.txt",
+            b"linked".to_vec(),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let link_res = api::nats_put_link(
+            store.to_string(),
+            "link.txt".to_string(),
+            store.to_string(),
+            "original.txt",
+        );
+        assert!(
+            link_res.is_ok(),
+            "put_link failed: {:?}",
+            link_res.as_ref().err()
+        );
+        assert_eq!(
+            b"linked".to_vec(),
+            api::nats_get_file(store.to_string(), "link.txt").unwrap()
+        );
+
+        let store_link_res = api::nats_put_store_link(
+            store.to_string(),
+            "other".to_string(),
+            "test_store_management_other".to_string(),
+        );
+        assert!(
+            store_link_res.is_ok(),
+            "put_store_link failed: {:?}",
+            store_link_res.as_ref().err()
+        );
+        let options = store_link_res.unwrap().next().unwrap().4.unwrap();
+        assert_eq!(options.0["link"]["bucket"], "test_store_management_other");
+
+        let res = api::nats_object_store_seal(store);
+        assert!(res.is_ok(), "object_store_seal failed: {:?}", res);
+        assert!(
+            api::nats_object_store_status(store)
+                .unwrap()
+                .next()
+                .unwrap()
+                .7
+        );
+        assert!(api::nats_delete_file(store.to_string(), "original.txt").is_err());
+
+        let res = api::nats_object_store_delete(store);
+        assert!(res.is_ok(), "object_store_delete failed: {:?}", res);
+        assert!(api::nats_object_store_status(store).is_err());
+    }
+
     #[cfg(feature = "object_store")]
     #[pg_test]
     fn test_pgnats_file_info() {
@@ -274,7 +1033,7 @@ mod tests {
         let key = "info.txt";
         let content = b"12345".to_vec();
 
-        api::nats_put_file(bucket.clone(), key, content.clone()).unwrap();
+        api::nats_put_file(bucket.clone(), key, content.clone(), None, None, None).unwrap();
 
         let info_res = api::nats_get_file_info(bucket.clone(), key);
         assert!(info_res.is_ok(), "get_file_info failed",);
@@ -285,6 +1044,45 @@ mod tests {
         assert_eq!(info.7 as usize, content.len());
     }
 
+    #[cfg(feature = "object_store")]
+    #[pg_test]
+    fn test_pgnats_put_file_with_metadata() {
+        use pgrx::JsonB;
+        use serde_json::json;
+
+        let bucket = "test_file_info".to_string();
+        let key = "metadata.txt";
+        let content = b"with metadata".to_vec();
+
+        let put_res = api::nats_put_file(
+            bucket.clone(),
+            key,
+            content.clone(),
+            Some("Test description".to_string()),
+            Some(JsonB(json!({"owner": "tests", "version": 2}))),
+            Some(JsonB(json!({"Content-Type": "text/plain"}))),
+        );
+        assert!(
+            put_res.is_ok(),
+            "put_file with metadata failed: {:?}",
+            put_res.as_ref().err()
+        );
+
+        let (nuid, size, chunks, digest) = put_res.unwrap().next().unwrap();
+        assert!(!nuid.is_empty());
+        assert_eq!(content.len() as i64, size);
+        assert_eq!(1, chunks);
+        assert!(digest.is_some());
+
+        let info = api::nats_get_file_info(bucket.clone(), key)
+            .unwrap()
+            .next()
+            .unwrap();
+        assert_eq!(Some("Test description".to_string()), info.1);
+        assert_eq!(json!({"owner": "tests", "version": "2"}), info.2 .0);
+        assert_eq!(nuid, info.6);
+    }
+
     #[cfg(feature = "object_store")]
     #[pg_test]
     fn test_pgnats_file_list() {
@@ -295,10 +1093,10 @@ mod tests {
         ];
 
         for (key, content) in &files {
-            api::nats_put_file(bucket.clone(), key, content.clone()).unwrap();
+            api::nats_put_file(bucket.clone(), key, content.clone(), None, None, None).unwrap();
         }
 
-        let list_res = api::nats_get_file_list(bucket.clone());
+        let list_res = api::nats_get_file_list(bucket.clone(), None, false);
         assert!(list_res.is_ok(), "get_file_list failed");
 
         let list = list_res.unwrap();
@@ -309,6 +1107,41 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "object_store")]
+    #[pg_test]
+    fn test_pgnats_file_list_filters() {
+        let bucket = "test_file_list_filters".to_string();
+
+        for key in ["reports/a.txt", "reports/b.txt", "images/c.png"] {
+            api::nats_put_file(bucket.clone(), key, b"data".to_vec(), None, None, None).unwrap();
+        }
+        api::nats_delete_file(bucket.clone(), "reports/b.txt").unwrap();
+
+        let names = |prefix, include_deleted| {
+            let mut names: Vec<(String, bool)> =
+                api::nats_get_file_list(bucket.clone(), prefix, include_deleted)
+                    .unwrap()
+                    .map(|info| (info.0, info.11))
+                    .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(
+            names(Some("reports/"), false),
+            vec![("reports/a.txt".to_string(), false)]
+        );
+        assert_eq!(
+            names(Some("reports/"), true),
+            vec![
+                ("reports/a.txt".to_string(), false),
+                ("reports/b.txt".to_string(), true)
+            ]
+        );
+        assert_eq!(names(None, false).len(), 2);
+        assert!(names(Some("missing/"), true).is_empty());
+    }
+
     #[cfg(feature = "object_store")]
     #[pg_test]
     fn test_pgnats_delete_file() {
@@ -316,7 +1149,7 @@ mod tests {
         let key = "delete_me.txt";
         let content = b"goodbye".to_vec();
 
-        api::nats_put_file(bucket.clone(), key, content).unwrap();
+        api::nats_put_file(bucket.clone(), key, content, None, None, None).unwrap();
         api::nats_delete_file(bucket.clone(), key).unwrap();
 
         let get_res = api::nats_get_file(bucket.clone(), key);
@@ -2,7 +2,7 @@
 pub fn init_test_shared_memory() {
     use pgrx::{pg_guard, pg_shmem_init, pg_sys, PgSharedMemoryInitialization};
 
-    use crate::pg_tests::bgw_tests::tests_items::*;
+    use crate::{bgw::SUBSCRIPTIONS_STATUS, pg_tests::bgw_tests::tests_items::*};
 
     pg_shmem_init!(LAUNCHER_MESSAGE_BUS1);
     pg_shmem_init!(TEST_RESULT1);
@@ -21,6 +21,41 @@ pub fn init_test_shared_memory() {
 
     pg_shmem_init!(LAUNCHER_MESSAGE_BUS6);
     pg_shmem_init!(TEST_RESULT6);
+
+    pg_shmem_init!(LAUNCHER_MESSAGE_BUS7);
+    pg_shmem_init!(TEST_RESULT7);
+
+    pg_shmem_init!(LAUNCHER_MESSAGE_BUS8);
+    pg_shmem_init!(TEST_RESULT8);
+
+    pg_shmem_init!(LAUNCHER_MESSAGE_BUS9);
+    pg_shmem_init!(TEST_RESULT9);
+
+    pg_shmem_init!(LAUNCHER_MESSAGE_BUS10);
+    pg_shmem_init!(TEST_RESULT10);
+
+    pg_shmem_init!(LAUNCHER_MESSAGE_BUS11);
+    pg_shmem_init!(TEST_RESULT11);
+
+    pg_shmem_init!(LAUNCHER_MESSAGE_BUS12);
+    pg_shmem_init!(TEST_RESULT12);
+
+    pg_shmem_init!(LAUNCHER_MESSAGE_BUS13);
+    pg_shmem_init!(TEST_RESULT13);
+
+    pg_shmem_init!(LAUNCHER_MESSAGE_BUS14);
+    pg_shmem_init!(TEST_RESULT14);
+
+    pg_shmem_init!(LAUNCHER_MESSAGE_BUS15);
+    pg_shmem_init!(TEST_RESULT15);
+
+    pg_shmem_init!(LAUNCHER_MESSAGE_BUS16);
+    pg_shmem_init!(TEST_RESULT16);
+
+    pg_shmem_init!(LAUNCHER_MESSAGE_BUS17);
+    pg_shmem_init!(TEST_RESULT17);
+
+    pg_shmem_init!(SUBSCRIPTIONS_STATUS);
 }
 
 #[cfg(any(test, feature = "pg_test"))]
@@ -36,6 +71,12 @@ mod tests_items {
         CREATE TABLE test_subscription_table_1 (
             subject TEXT NOT NULL,
             callback TEXT NOT NULL,
+            queue_group TEXT,
+            max_retries INT NOT NULL DEFAULT 0,
+            dead_letter_subject TEXT,
+            dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+            batch_size INT NOT NULL DEFAULT 1,
+            batch_linger_ms BIGINT NOT NULL DEFAULT 0,
             UNIQUE(subject, callback)
         );
         CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_1;
@@ -52,6 +93,12 @@ mod tests_items {
         CREATE TABLE test_subscription_table_2 (
             subject TEXT NOT NULL,
             callback TEXT NOT NULL,
+            queue_group TEXT,
+            max_retries INT NOT NULL DEFAULT 0,
+            dead_letter_subject TEXT,
+            dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+            batch_size INT NOT NULL DEFAULT 1,
+            batch_linger_ms BIGINT NOT NULL DEFAULT 0,
             UNIQUE(subject, callback)
         );
 
@@ -73,6 +120,12 @@ mod tests_items {
         CREATE TABLE test_subscription_table_3 (
             subject TEXT NOT NULL,
             callback TEXT NOT NULL,
+            queue_group TEXT,
+            max_retries INT NOT NULL DEFAULT 0,
+            dead_letter_subject TEXT,
+            dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+            batch_size INT NOT NULL DEFAULT 1,
+            batch_linger_ms BIGINT NOT NULL DEFAULT 0,
             UNIQUE(subject, callback)
         );
 
@@ -90,6 +143,12 @@ mod tests_items {
         CREATE TABLE test_subscription_table_4 (
             subject TEXT NOT NULL,
             callback TEXT NOT NULL,
+            queue_group TEXT,
+            max_retries INT NOT NULL DEFAULT 0,
+            dead_letter_subject TEXT,
+            dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+            batch_size INT NOT NULL DEFAULT 1,
+            batch_linger_ms BIGINT NOT NULL DEFAULT 0,
             UNIQUE(subject, callback)
         );
 
@@ -107,6 +166,12 @@ mod tests_items {
         CREATE TABLE test_subscription_table_5 (
             subject TEXT NOT NULL,
             callback TEXT NOT NULL,
+            queue_group TEXT,
+            max_retries INT NOT NULL DEFAULT 0,
+            dead_letter_subject TEXT,
+            dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+            batch_size INT NOT NULL DEFAULT 1,
+            batch_linger_ms BIGINT NOT NULL DEFAULT 0,
             UNIQUE(subject, callback)
         );
 
@@ -124,6 +189,12 @@ mod tests_items {
         CREATE TABLE test_subscription_table_6 (
             subject TEXT NOT NULL,
             callback TEXT NOT NULL,
+            queue_group TEXT,
+            max_retries INT NOT NULL DEFAULT 0,
+            dead_letter_subject TEXT,
+            dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+            batch_size INT NOT NULL DEFAULT 1,
+            batch_linger_ms BIGINT NOT NULL DEFAULT 0,
             UNIQUE(subject, callback)
         );
 
@@ -131,6 +202,344 @@ mod tests_items {
         CREATE SERVER test_background_worker_r2m FOREIGN DATA WRAPPER pgnats_fdw_test_6 OPTIONS (host 'localhost', port '4222');
         "#
     );
+
+    generate_test_background_worker!(
+        7,
+        c"l7",
+        c"r7",
+        "create_test_fdw_7",
+        r#"
+        CREATE TABLE test_subscription_table_7 (
+            subject TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            queue_group TEXT,
+            max_retries INT NOT NULL DEFAULT 0,
+            dead_letter_subject TEXT,
+            dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+            batch_size INT NOT NULL DEFAULT 1,
+            batch_linger_ms BIGINT NOT NULL DEFAULT 0,
+            UNIQUE(subject, callback)
+        );
+
+        CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_7 VALIDATOR pgnats_fdw_validator_test_7;
+        CREATE SERVER test_background_worker_kv_watch FOREIGN DATA WRAPPER pgnats_fdw_test_7 OPTIONS (host 'localhost', port '4222');
+        "#
+    );
+
+    #[pgrx::pg_extern]
+    pub fn test_7_kv_watch_fn(key: &str, value: Vec<u8>, _revision: i64, operation: &str) {
+        use std::hash::{DefaultHasher, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write(key.as_bytes());
+        hasher.write(&value);
+        hasher.write(operation.as_bytes());
+
+        *TEST_RESULT7.exclusive() = hasher.finish();
+    }
+
+    generate_test_background_worker!(
+        8,
+        c"l8",
+        c"r8",
+        "create_test_fdw_8",
+        r#"
+        CREATE TABLE test_subscription_table_8 (
+            subject TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            queue_group TEXT,
+            max_retries INT NOT NULL DEFAULT 0,
+            dead_letter_subject TEXT,
+            dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+            batch_size INT NOT NULL DEFAULT 1,
+            batch_linger_ms BIGINT NOT NULL DEFAULT 0,
+            UNIQUE(subject, callback)
+        );
+
+        CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_8 VALIDATOR pgnats_fdw_validator_test_8;
+        CREATE SERVER test_background_worker_object_watch FOREIGN DATA WRAPPER pgnats_fdw_test_8 OPTIONS (host 'localhost', port '4222');
+        "#
+    );
+
+    #[pgrx::pg_extern]
+    pub fn test_8_object_watch_fn(name: &str, _nuid: &str, size: i64, operation: &str) {
+        use std::hash::{DefaultHasher, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write(name.as_bytes());
+        hasher.write_i64(size);
+        hasher.write(operation.as_bytes());
+
+        *TEST_RESULT8.exclusive() = hasher.finish();
+    }
+
+    generate_test_background_worker!(
+        9,
+        c"l9",
+        c"r9",
+        "create_test_fdw_9",
+        r#"
+        CREATE TABLE test_subscription_table_9 (
+            subject TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            queue_group TEXT,
+            max_retries INT NOT NULL DEFAULT 0,
+            dead_letter_subject TEXT,
+            dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+            batch_size INT NOT NULL DEFAULT 1,
+            batch_linger_ms BIGINT NOT NULL DEFAULT 0,
+            UNIQUE(subject, callback)
+        );
+
+        CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_9 VALIDATOR pgnats_fdw_validator_test_9;
+        CREATE SERVER test_background_worker_stream_subscribe FOREIGN DATA WRAPPER pgnats_fdw_test_9 OPTIONS (host 'localhost', port '4222');
+        "#
+    );
+
+    generate_test_background_worker!(
+        10,
+        c"l10",
+        c"r10",
+        "create_test_fdw_10",
+        r#"
+        CREATE TABLE test_subscription_table_10 (
+            subject TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            queue_group TEXT,
+            max_retries INT NOT NULL DEFAULT 0,
+            dead_letter_subject TEXT,
+            dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+            batch_size INT NOT NULL DEFAULT 1,
+            batch_linger_ms BIGINT NOT NULL DEFAULT 0,
+            UNIQUE(subject, callback)
+        );
+
+        CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_10 VALIDATOR pgnats_fdw_validator_test_10;
+        CREATE SERVER test_background_worker_queue_group FOREIGN DATA WRAPPER pgnats_fdw_test_10 OPTIONS (host 'localhost', port '4222');
+        "#
+    );
+
+    generate_test_background_worker!(
+        11,
+        c"l11",
+        c"r11",
+        "create_test_fdw_11",
+        r#"
+        CREATE TABLE test_subscription_table_11 (
+            subject TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            queue_group TEXT,
+            max_retries INT NOT NULL DEFAULT 0,
+            dead_letter_subject TEXT,
+            dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+            batch_size INT NOT NULL DEFAULT 1,
+            batch_linger_ms BIGINT NOT NULL DEFAULT 0,
+            UNIQUE(subject, callback)
+        );
+
+        CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_11 VALIDATOR pgnats_fdw_validator_test_11;
+        CREATE SERVER test_background_worker_message_info FOREIGN DATA WRAPPER pgnats_fdw_test_11 OPTIONS (host 'localhost', port '4222');
+        "#
+    );
+
+    #[pgrx::pg_extern]
+    pub fn test_11_message_info_fn(
+        payload: pgrx::JsonB,
+        subject: &str,
+        headers: Option<pgrx::JsonB>,
+        reply: Option<&str>,
+    ) {
+        use std::hash::{DefaultHasher, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write(payload.0.to_string().as_bytes());
+        hasher.write(subject.as_bytes());
+        hasher.write(
+            headers
+                .map(|h| h.0.to_string())
+                .unwrap_or_default()
+                .as_bytes(),
+        );
+        hasher.write(reply.unwrap_or_default().as_bytes());
+
+        *TEST_RESULT11.exclusive() = hasher.finish();
+    }
+
+    generate_test_background_worker!(
+        12,
+        c"l12",
+        c"r12",
+        "create_test_fdw_12",
+        r#"
+        CREATE TABLE test_subscription_table_12 (
+            subject TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            queue_group TEXT,
+            max_retries INT NOT NULL DEFAULT 0,
+            dead_letter_subject TEXT,
+            dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+            batch_size INT NOT NULL DEFAULT 1,
+            batch_linger_ms BIGINT NOT NULL DEFAULT 0,
+            UNIQUE(subject, callback)
+        );
+
+        CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_12 VALIDATOR pgnats_fdw_validator_test_12;
+        CREATE SERVER test_background_worker_respond FOREIGN DATA WRAPPER pgnats_fdw_test_12 OPTIONS (host 'localhost', port '4222');
+        "#
+    );
+
+    #[pgrx::pg_extern]
+    pub fn test_12_responder_fn(payload: &str) -> String {
+        payload.to_uppercase()
+    }
+
+    generate_test_background_worker!(
+        13,
+        c"l13",
+        c"r13",
+        "create_test_fdw_13",
+        r#"
+        CREATE TABLE test_subscription_table_13 (
+            subject TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            queue_group TEXT,
+            max_retries INT NOT NULL DEFAULT 0,
+            dead_letter_subject TEXT,
+            dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+            batch_size INT NOT NULL DEFAULT 1,
+            batch_linger_ms BIGINT NOT NULL DEFAULT 0,
+            UNIQUE(subject, callback)
+        );
+
+        CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_13 VALIDATOR pgnats_fdw_validator_test_13;
+        CREATE SERVER test_background_worker_service FOREIGN DATA WRAPPER pgnats_fdw_test_13 OPTIONS (host 'localhost', port '4222');
+        "#
+    );
+
+    #[pgrx::pg_extern]
+    pub fn test_13_endpoint_fn(payload: &str) -> String {
+        payload.chars().rev().collect()
+    }
+
+    generate_test_background_worker!(
+        14,
+        c"l14",
+        c"r14",
+        "create_test_fdw_14",
+        r#"
+        CREATE TABLE test_subscription_table_14 (
+            subject TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            queue_group TEXT,
+            max_retries INT NOT NULL DEFAULT 0,
+            dead_letter_subject TEXT,
+            dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+            batch_size INT NOT NULL DEFAULT 1,
+            batch_linger_ms BIGINT NOT NULL DEFAULT 0,
+            UNIQUE(subject, callback)
+        );
+
+        CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_14 VALIDATOR pgnats_fdw_validator_test_14;
+        CREATE SERVER test_background_worker_dead_letters FOREIGN DATA WRAPPER pgnats_fdw_test_14 OPTIONS (host 'localhost', port '4222');
+        "#
+    );
+
+    #[pgrx::pg_extern]
+    pub fn test_14_failing_fn(_payload: &[u8]) {
+        *TEST_RESULT14.exclusive() += 1;
+        pgrx::error!("test_14_failing_fn always fails");
+    }
+
+    generate_test_background_worker!(
+        15,
+        c"l15",
+        c"r15",
+        "create_test_fdw_15",
+        r#"
+        CREATE TABLE test_subscription_table_15 (
+            subject TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            queue_group TEXT,
+            max_retries INT NOT NULL DEFAULT 0,
+            dead_letter_subject TEXT,
+            dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+            batch_size INT NOT NULL DEFAULT 1,
+            batch_linger_ms BIGINT NOT NULL DEFAULT 0,
+            UNIQUE(subject, callback)
+        );
+
+        CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_15 VALIDATOR pgnats_fdw_validator_test_15;
+        CREATE SERVER test_background_worker_batch FOREIGN DATA WRAPPER pgnats_fdw_test_15 OPTIONS (host 'localhost', port '4222');
+        "#
+    );
+
+    #[pgrx::pg_extern]
+    pub fn test_15_batch_fn(payloads: Vec<String>) {
+        // Counts calls in the hundreds and messages in the units.
+        *TEST_RESULT15.exclusive() += 100 + u64::try_from(payloads.len()).unwrap_or_default();
+    }
+
+    generate_test_background_worker!(
+        16,
+        c"l16",
+        c"r16",
+        "create_test_fdw_16",
+        r#"
+        CREATE TABLE test_subscription_table_16 (
+            subject TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            queue_group TEXT,
+            max_retries INT NOT NULL DEFAULT 0,
+            dead_letter_subject TEXT,
+            dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+            batch_size INT NOT NULL DEFAULT 1,
+            batch_linger_ms BIGINT NOT NULL DEFAULT 0,
+            UNIQUE(subject, callback)
+        );
+
+        CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_16 VALIDATOR pgnats_fdw_validator_test_16;
+        CREATE SERVER test_background_worker_pool FOREIGN DATA WRAPPER pgnats_fdw_test_16 OPTIONS (host 'localhost', port '4222', workers '2');
+        "#
+    );
+
+    #[pgrx::pg_extern]
+    pub fn test_16_counting_fn(_payload: &[u8]) {
+        *TEST_RESULT16.exclusive() += 1;
+    }
+
+    generate_test_background_worker!(
+        17,
+        c"l17",
+        c"r17",
+        "create_test_fdw_17",
+        r#"
+        CREATE TABLE test_subscription_table_17 (
+            subject TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            queue_group TEXT,
+            max_retries INT NOT NULL DEFAULT 0,
+            dead_letter_subject TEXT,
+            dead_letter_table BOOLEAN NOT NULL DEFAULT false,
+            batch_size INT NOT NULL DEFAULT 1,
+            batch_linger_ms BIGINT NOT NULL DEFAULT 0,
+            UNIQUE(subject, callback)
+        );
+
+        CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_17 VALIDATOR pgnats_fdw_validator_test_17;
+        CREATE SERVER test_background_worker_full_queue_retries FOREIGN DATA WRAPPER pgnats_fdw_test_17 OPTIONS (host 'localhost', port '4222', queue_limit '1', queue_overflow 'drop_newest');
+        "#
+    );
+
+    #[pgrx::pg_extern]
+    pub fn test_17_failing_fn(_payload: &[u8]) {
+        *TEST_RESULT17.exclusive() += 1;
+        pgrx::error!("test_17_failing_fn always fails");
+    }
+
+    #[pgrx::pg_extern]
+    pub fn test_17_slow_fn(_payload: &[u8]) {
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
 }
 
 #[cfg(any(test, feature = "pg_test"))]
@@ -146,8 +555,9 @@ pub(super) mod tests {
     use crate::{
         api,
         bgw::{
-            notification::PgInstanceNotification, ring_queue::RingQueue,
-            subscriber::pg_api::PgInstanceStatus,
+            notification::PgInstanceNotification,
+            ring_queue::RingQueue,
+            subscriber::pg_api::{BatchPolicy, PgInstanceStatus, RetryPolicy},
         },
         constants::EXTENSION_NAME,
         pg_tests::bgw_tests::tests_items::*,
@@ -488,6 +898,669 @@ pub(super) mod tests {
         terminate.wait_for_shutdown().unwrap();
     }
 
+    #[cfg(feature = "kv")]
+    #[pg_test]
+    fn test_background_worker_kv_watch() {
+        use pgrx::function_name;
+
+        let bucket = function_name!().split("::").last().unwrap();
+        let key = "config.key";
+        let content1 = "Hello, World!";
+        let content2 = "Привет, Мир!";
+
+        let worker = BackgroundWorkerBuilder::new("PGNats Background Worker Launcher 7")
+            .set_function("background_worker_launcher_entry_point_test_7")
+            .set_library(EXTENSION_NAME)
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pgrx::pg_sys::MyProcPid })
+            .load_dynamic()
+            .unwrap();
+
+        let _ = worker.wait_for_startup().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        // The watch does not create the bucket
+        api::nats_put_text(bucket.to_string(), "other.key", content1).unwrap();
+
+        crate::bgw::launcher::send_message_to_launcher_with_retry(
+            &LAUNCHER_MESSAGE_BUS7,
+            crate::bgw::launcher::message::LauncherMessage::KvWatch {
+                db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+                bucket: bucket.to_string(),
+                key_pattern: "config.>".to_string(),
+                fn_name: "public.test_7_kv_watch_fn".to_string(),
+            },
+            5,
+            std::time::Duration::from_secs(1),
+        )
+        .unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        api::nats_put_text(bucket.to_string(), key, content1).unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write(key.as_bytes());
+        hasher.write(content1.as_bytes());
+        hasher.write(b"PUT");
+        assert_eq!(*TEST_RESULT7.share(), hasher.finish());
+
+        let last_revision = Spi::get_one_with_args::<i64>(
+            "SELECT last_revision FROM pgnats.kv_watches WHERE bucket = $1",
+            &[bucket.into()],
+        )
+        .unwrap();
+        assert!(last_revision.is_some());
+
+        api::nats_put_text(bucket.to_string(), "other.key", content2).unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        assert_eq!(*TEST_RESULT7.share(), hasher.finish());
+
+        api::nats_delete_value(bucket.to_string(), key).unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write(key.as_bytes());
+        hasher.write(b"DEL");
+        assert_eq!(*TEST_RESULT7.share(), hasher.finish());
+
+        let terminate = worker.terminate();
+        terminate.wait_for_shutdown().unwrap();
+    }
+
+    #[cfg(feature = "object_store")]
+    #[pg_test]
+    fn test_background_worker_object_watch() {
+        use pgrx::function_name;
+
+        let store = function_name!().split("::").last().unwrap();
+        let name = "upload.txt";
+        let content = b"Hello, World!".to_vec();
+
+        let worker = BackgroundWorkerBuilder::new("PGNats Background Worker Launcher 8")
+            .set_function("background_worker_launcher_entry_point_test_8")
+            .set_library(EXTENSION_NAME)
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pgrx::pg_sys::MyProcPid })
+            .load_dynamic()
+            .unwrap();
+
+        let _ = worker.wait_for_startup().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        // The watch does not create the store
+        api::nats_object_store_create(store.to_string(), None, None, None, None, false).unwrap();
+
+        crate::bgw::launcher::send_message_to_launcher_with_retry(
+            &LAUNCHER_MESSAGE_BUS8,
+            crate::bgw::launcher::message::LauncherMessage::ObjectWatch {
+                db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+                store: store.to_string(),
+                fn_name: "public.test_8_object_watch_fn".to_string(),
+            },
+            5,
+            std::time::Duration::from_secs(1),
+        )
+        .unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        api::nats_put_file(store.to_string(), name, content.clone(), None, None, None).unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write(name.as_bytes());
+        hasher.write_i64(content.len() as i64);
+        hasher.write(b"PUT");
+        assert_eq!(*TEST_RESULT8.share(), hasher.finish());
+
+        let last_modified = Spi::get_one_with_args::<i64>(
+            "SELECT last_modified_ns FROM pgnats.object_watches WHERE store = $1",
+            &[store.into()],
+        )
+        .unwrap();
+        assert!(last_modified.is_some());
+
+        api::nats_delete_file(store.to_string(), name).unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write(name.as_bytes());
+        hasher.write_i64(0);
+        hasher.write(b"DEL");
+        assert_eq!(*TEST_RESULT8.share(), hasher.finish());
+
+        let terminate = worker.terminate();
+        terminate.wait_for_shutdown().unwrap();
+    }
+
+    #[pg_test]
+    fn test_background_worker_stream_subscribe() {
+        use pgrx::JsonB;
+        use serde_json::json;
+
+        let stream = "TEST_BACKGROUND_WORKER_STREAM_SUBSCRIBE";
+        let consumer = "test_background_worker_stream_subscribe";
+        let subject = "bgw_stream_subscribe.events";
+        let content1 = "Hello, World!";
+        let content2 = "Привет, Мир!";
+
+        api::nats_stream_create(JsonB(json!({
+            "name": stream,
+            "subjects": ["bgw_stream_subscribe.>"],
+            "storage": "memory"
+        })))
+        .unwrap();
+
+        // Published before the subscription exists, so it must still be delivered.
+        api::nats_publish_text_stream(subject, content1.to_string(), None).unwrap();
+
+        let worker = BackgroundWorkerBuilder::new("PGNats Background Worker Launcher 9")
+            .set_function("background_worker_launcher_entry_point_test_9")
+            .set_library(EXTENSION_NAME)
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pgrx::pg_sys::MyProcPid })
+            .load_dynamic()
+            .unwrap();
+
+        let _ = worker.wait_for_startup().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        crate::bgw::launcher::send_message_to_launcher_with_retry(
+            &LAUNCHER_MESSAGE_BUS9,
+            crate::bgw::launcher::message::LauncherMessage::StreamSubscribe {
+                db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+                stream: stream.to_string(),
+                consumer: consumer.to_string(),
+                fn_name: "public.test_9_fn".to_string(),
+                retry: crate::bgw::subscriber::pg_api::RetryPolicy {
+                    max_retries: 5,
+                    ..Default::default()
+                },
+            },
+            5,
+            std::time::Duration::from_secs(1),
+        )
+        .unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write(content1.as_bytes());
+        assert_eq!(*TEST_RESULT9.share(), hasher.finish());
+
+        api::nats_publish_text_stream(subject, content2.to_string(), None).unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write(content2.as_bytes());
+        assert_eq!(*TEST_RESULT9.share(), hasher.finish());
+
+        let info = api::nats_consumer_info(stream, consumer)
+            .unwrap()
+            .next()
+            .unwrap();
+        assert_eq!(info.6, 2, "both messages must be acknowledged");
+        assert_eq!(info.9, 0);
+
+        let terminate = worker.terminate();
+        terminate.wait_for_shutdown().unwrap();
+
+        api::nats_stream_delete(stream).unwrap();
+    }
+
+    #[pg_test]
+    fn test_background_worker_queue_group() {
+        use pgrx::function_name;
+
+        let subject = function_name!().split("::").last().unwrap();
+        let queue_group = "test_queue_group";
+        let content = "Hello, World!";
+
+        let worker = BackgroundWorkerBuilder::new("PGNats Background Worker Launcher 10")
+            .set_function("background_worker_launcher_entry_point_test_10")
+            .set_library(EXTENSION_NAME)
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pgrx::pg_sys::MyProcPid })
+            .load_dynamic()
+            .unwrap();
+
+        let _ = worker.wait_for_startup().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        crate::bgw::launcher::send_message_to_launcher_with_retry(
+            &LAUNCHER_MESSAGE_BUS10,
+            crate::bgw::launcher::message::LauncherMessage::Subscribe {
+                db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+                subject: subject.to_string(),
+                fn_name: "public.test_10_fn".to_string(),
+                queue_group: Some(queue_group.to_string()),
+                retry: Default::default(),
+                batch: Default::default(),
+            },
+            5,
+            std::time::Duration::from_secs(1),
+        )
+        .unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let stored = Spi::get_one::<String>(
+            "SELECT queue_group FROM test_subscription_table_10 WHERE callback = 'public.test_10_fn'",
+        )
+        .unwrap();
+        assert_eq!(stored.as_deref(), Some(queue_group));
+
+        api::nats_publish_text(subject, content.to_string(), None, None).unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write(content.as_bytes());
+        assert_eq!(*TEST_RESULT10.share(), hasher.finish());
+
+        let terminate = worker.terminate();
+        terminate.wait_for_shutdown().unwrap();
+    }
+
+    #[pg_test]
+    fn test_background_worker_message_info() {
+        use pgrx::JsonB;
+        use serde_json::json;
+
+        let subject = "test_background_worker_message_info.orders";
+        let payload = json!({ "id": 42 });
+        let headers = json!({ "X-Source": "test" });
+        let reply = "test_background_worker_message_info.reply";
+
+        let worker = BackgroundWorkerBuilder::new("PGNats Background Worker Launcher 11")
+            .set_function("background_worker_launcher_entry_point_test_11")
+            .set_library(EXTENSION_NAME)
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pgrx::pg_sys::MyProcPid })
+            .load_dynamic()
+            .unwrap();
+
+        let _ = worker.wait_for_startup().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        pgnats_subscribe(
+            "test_background_worker_message_info.*".to_string(),
+            "public.test_11_message_info_fn".to_string(),
+            &LAUNCHER_MESSAGE_BUS11,
+        );
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        api::nats_publish_jsonb(
+            subject,
+            JsonB(payload.clone()),
+            Some(reply),
+            Some(JsonB(headers.clone())),
+        )
+        .unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write(payload.to_string().as_bytes());
+        hasher.write(subject.as_bytes());
+        hasher.write(headers.to_string().as_bytes());
+        hasher.write(reply.as_bytes());
+        assert_eq!(*TEST_RESULT11.share(), hasher.finish());
+
+        let terminate = worker.terminate();
+        terminate.wait_for_shutdown().unwrap();
+    }
+
+    #[pg_test]
+    fn test_background_worker_respond() {
+        let subject = "test_background_worker_respond";
+        let content = "hello responder";
+
+        let worker = BackgroundWorkerBuilder::new("PGNats Background Worker Launcher 12")
+            .set_function("background_worker_launcher_entry_point_test_12")
+            .set_library(EXTENSION_NAME)
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pgrx::pg_sys::MyProcPid })
+            .load_dynamic()
+            .unwrap();
+
+        let _ = worker.wait_for_startup().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        crate::bgw::launcher::send_message_to_launcher_with_retry(
+            &LAUNCHER_MESSAGE_BUS12,
+            crate::bgw::launcher::message::LauncherMessage::Respond {
+                db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+                subject: subject.to_string(),
+                fn_name: "public.test_12_responder_fn".to_string(),
+                queue_group: None,
+            },
+            5,
+            std::time::Duration::from_secs(1),
+        )
+        .unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let response = api::nats_request_text(subject, content.to_string(), Some(5000)).unwrap();
+        assert_eq!(response, content.to_uppercase().into_bytes());
+
+        let terminate = worker.terminate();
+        terminate.wait_for_shutdown().unwrap();
+    }
+
+    #[pg_test]
+    fn test_background_worker_service() {
+        let service = "pgnats_test_service";
+        let subject = "test_background_worker_service.reverse";
+
+        let worker = BackgroundWorkerBuilder::new("PGNats Background Worker Launcher 13")
+            .set_function("background_worker_launcher_entry_point_test_13")
+            .set_library(EXTENSION_NAME)
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pgrx::pg_sys::MyProcPid })
+            .load_dynamic()
+            .unwrap();
+
+        let _ = worker.wait_for_startup().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let db_oid = unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32();
+
+        crate::bgw::launcher::send_message_to_launcher_with_retry(
+            &LAUNCHER_MESSAGE_BUS13,
+            crate::bgw::launcher::message::LauncherMessage::ServiceAdd {
+                db_oid,
+                name: service.to_string(),
+                version: "1.0.0".to_string(),
+                description: None,
+            },
+            5,
+            std::time::Duration::from_secs(1),
+        )
+        .unwrap();
+
+        crate::bgw::launcher::send_message_to_launcher_with_retry(
+            &LAUNCHER_MESSAGE_BUS13,
+            crate::bgw::launcher::message::LauncherMessage::ServiceEndpointAdd {
+                db_oid,
+                service: service.to_string(),
+                endpoint: "reverse".to_string(),
+                subject: subject.to_string(),
+                fn_name: "public.test_13_endpoint_fn".to_string(),
+            },
+            5,
+            std::time::Duration::from_secs(1),
+        )
+        .unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let response = api::nats_request_text(subject, "abc".to_string(), Some(5000)).unwrap();
+        assert_eq!(response, b"cba");
+
+        let ping =
+            api::nats_request_text(&format!("$SRV.PING.{service}"), String::new(), Some(5000))
+                .unwrap();
+        let ping: serde_json::Value = serde_json::from_slice(&ping).unwrap();
+        assert_eq!(ping["name"], service);
+        assert_eq!(ping["version"], "1.0.0");
+
+        let stats =
+            api::nats_request_text(&format!("$SRV.STATS.{service}"), String::new(), Some(5000))
+                .unwrap();
+        let stats: serde_json::Value = serde_json::from_slice(&stats).unwrap();
+        assert_eq!(stats["endpoints"][0]["name"], "reverse");
+        assert_eq!(stats["endpoints"][0]["subject"], subject);
+        assert_eq!(stats["endpoints"][0]["num_requests"], 1);
+        assert_eq!(stats["endpoints"][0]["num_errors"], 0);
+
+        let terminate = worker.terminate();
+        terminate.wait_for_shutdown().unwrap();
+    }
+
+    #[pg_test]
+    fn test_background_worker_dead_letters() {
+        let subject = "test_background_worker_dead_letters";
+        let content = "Hello, World!";
+
+        let worker = BackgroundWorkerBuilder::new("PGNats Background Worker Launcher 14")
+            .set_function("background_worker_launcher_entry_point_test_14")
+            .set_library(EXTENSION_NAME)
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pgrx::pg_sys::MyProcPid })
+            .load_dynamic()
+            .unwrap();
+
+        let _ = worker.wait_for_startup().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        crate::bgw::launcher::send_message_to_launcher_with_retry(
+            &LAUNCHER_MESSAGE_BUS14,
+            crate::bgw::launcher::message::LauncherMessage::Subscribe {
+                db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+                subject: subject.to_string(),
+                fn_name: "public.test_14_failing_fn".to_string(),
+                queue_group: None,
+                retry: RetryPolicy {
+                    max_retries: 2,
+                    dead_letter_subject: None,
+                    dead_letter_table: true,
+                },
+                batch: Default::default(),
+            },
+            5,
+            std::time::Duration::from_secs(1),
+        )
+        .unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        api::nats_publish_text(subject, content.to_string(), None, None).unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(6));
+
+        assert_eq!(*TEST_RESULT14.share(), 3);
+
+        let attempts = Spi::get_one::<i32>(&format!(
+            "SELECT attempts FROM pgnats.dead_letters WHERE subject = '{subject}'"
+        ))
+        .unwrap();
+        assert_eq!(attempts, Some(3));
+
+        let payload = Spi::get_one::<Vec<u8>>(&format!(
+            "SELECT payload FROM pgnats.dead_letters WHERE subject = '{subject}'"
+        ))
+        .unwrap();
+        assert_eq!(payload.as_deref(), Some(content.as_bytes()));
+
+        std::thread::sleep(std::time::Duration::from_secs(2));
+
+        let (_, callback, active, received, processed, errors, last_error, last_message_at, _) =
+            api::pgnats_subscriptions_status()
+                .unwrap()
+                .find(|status| status.0 == subject)
+                .unwrap();
+        assert_eq!(callback, "public.test_14_failing_fn");
+        assert!(active);
+        assert_eq!((received, processed, errors), (1, 0, 3));
+        assert!(last_error
+            .unwrap()
+            .contains("test_14_failing_fn always fails"));
+        assert!(last_message_at.is_some());
+
+        let terminate = worker.terminate();
+        terminate.wait_for_shutdown().unwrap();
+    }
+
+    #[pg_test]
+    fn test_background_worker_batch() {
+        let subject = "test_background_worker_batch";
+
+        let worker = BackgroundWorkerBuilder::new("PGNats Background Worker Launcher 15")
+            .set_function("background_worker_launcher_entry_point_test_15")
+            .set_library(EXTENSION_NAME)
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pgrx::pg_sys::MyProcPid })
+            .load_dynamic()
+            .unwrap();
+
+        let _ = worker.wait_for_startup().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        crate::bgw::launcher::send_message_to_launcher_with_retry(
+            &LAUNCHER_MESSAGE_BUS15,
+            crate::bgw::launcher::message::LauncherMessage::Subscribe {
+                db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+                subject: subject.to_string(),
+                fn_name: "public.test_15_batch_fn".to_string(),
+                queue_group: None,
+                retry: Default::default(),
+                batch: BatchPolicy {
+                    size: 5,
+                    linger_ms: 60_000,
+                },
+            },
+            5,
+            std::time::Duration::from_secs(1),
+        )
+        .unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        for i in 0..5 {
+            api::nats_publish_text(subject, format!("message {i}"), None, None).unwrap();
+        }
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        assert_eq!(*TEST_RESULT15.share(), 105);
+
+        // A payload that is not UTF-8 is dead-lettered without failing its batch.
+        for i in 0..4 {
+            api::nats_publish_text(subject, format!("message {i}"), None, None).unwrap();
+        }
+        api::nats_publish_binary(subject, vec![0xff, 0xfe], None, None).unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        assert_eq!(*TEST_RESULT15.share(), 209);
+
+        let terminate = worker.terminate();
+        terminate.wait_for_shutdown().unwrap();
+    }
+
+    #[pg_test]
+    fn test_background_worker_pool() {
+        let subjects: Vec<String> = (0..4)
+            .map(|i| format!("test_background_worker_pool_{i}"))
+            .collect();
+
+        let worker = BackgroundWorkerBuilder::new("PGNats Background Worker Launcher 16")
+            .set_function("background_worker_launcher_entry_point_test_16")
+            .set_library(EXTENSION_NAME)
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pgrx::pg_sys::MyProcPid })
+            .load_dynamic()
+            .unwrap();
+
+        let _ = worker.wait_for_startup().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(5));
+
+        for subject in &subjects {
+            pgnats_subscribe(
+                subject.clone(),
+                "public.test_16_counting_fn".to_string(),
+                &LAUNCHER_MESSAGE_BUS16,
+            );
+        }
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        for subject in &subjects {
+            api::nats_publish_text(subject, "message".to_string(), None, None).unwrap();
+        }
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        assert_eq!(*TEST_RESULT16.share(), 4);
+
+        let terminate = worker.terminate();
+        terminate.wait_for_shutdown().unwrap();
+    }
+
+    #[pg_test]
+    fn test_background_worker_full_queue_retries() {
+        let subject = "test_background_worker_full_queue_retries";
+        let busy_subject = "test_background_worker_full_queue_retries_busy";
+
+        let worker = BackgroundWorkerBuilder::new("PGNats Background Worker Launcher 17")
+            .set_function("background_worker_launcher_entry_point_test_17")
+            .set_library(EXTENSION_NAME)
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pgrx::pg_sys::MyProcPid })
+            .load_dynamic()
+            .unwrap();
+
+        let _ = worker.wait_for_startup().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        crate::bgw::launcher::send_message_to_launcher_with_retry(
+            &LAUNCHER_MESSAGE_BUS17,
+            crate::bgw::launcher::message::LauncherMessage::Subscribe {
+                db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+                subject: subject.to_string(),
+                fn_name: "public.test_17_failing_fn".to_string(),
+                queue_group: None,
+                retry: RetryPolicy {
+                    max_retries: 2,
+                    dead_letter_subject: None,
+                    dead_letter_table: true,
+                },
+                batch: Default::default(),
+            },
+            5,
+            std::time::Duration::from_secs(1),
+        )
+        .unwrap();
+        pgnats_subscribe(
+            busy_subject.to_string(),
+            "public.test_17_slow_fn".to_string(),
+            &LAUNCHER_MESSAGE_BUS17,
+        );
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        api::nats_publish_text(subject, "failing".to_string(), None, None).unwrap();
+
+        // Keeps the single-message queue full while the failed message waits for its retries.
+        for _ in 0..160 {
+            api::nats_publish_text(busy_subject, "busy".to_string(), None, None).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        std::thread::sleep(std::time::Duration::from_secs(2));
+
+        assert_eq!(*TEST_RESULT17.share(), 3);
+
+        let attempts = Spi::get_one::<i32>(&format!(
+            "SELECT attempts FROM pgnats.dead_letters WHERE subject = '{subject}'"
+        ))
+        .unwrap();
+        assert_eq!(attempts, Some(3));
+
+        let terminate = worker.terminate();
+        terminate.wait_for_shutdown().unwrap();
+    }
+
+    #[pg_test]
+    fn test_background_worker_overloaded_callback() {
+        use crate::bgw::subscriber::pg_api::fetch_callback_signature;
+
+        Spi::run(
+            "CREATE FUNCTION public.test_overloaded_fn(a int, b int) \
+             RETURNS void AS '' LANGUAGE sql",
+        )
+        .unwrap();
+        Spi::run(
+            "CREATE FUNCTION public.test_overloaded_fn(payload text) \
+             RETURNS void AS '' LANGUAGE sql",
+        )
+        .unwrap();
+
+        let signature = fetch_callback_signature("public.test_overloaded_fn").unwrap();
+        assert_eq!(signature.payload, crate::utils::CallbackPayload::Text);
+        assert!(!signature.batch);
+
+        assert!(fetch_callback_signature("public.test_missing_fn").is_err());
+    }
+
     fn pgnats_subscribe<const N: usize>(
         subject: String,
         fn_name: String,
@@ -499,6 +1572,9 @@ pub(super) mod tests {
                 db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
                 subject,
                 fn_name,
+                queue_group: None,
+                retry: Default::default(),
+                batch: Default::default(),
             },
             5,
             std::time::Duration::from_secs(1),
@@ -120,6 +120,42 @@ pub(crate) fn extract_headers(v: serde_json::Value) -> async_nats::HeaderMap {
     map
 }
 
+pub(crate) fn headers_to_json(headers: &async_nats::HeaderMap) -> serde_json::Value {
+    let map = headers
+        .iter()
+        .map(|(k, v)| {
+            let value = match v.as_slice() {
+                [v] => serde_json::Value::String(v.as_str().to_string()),
+                v => v.iter().map(|v| v.as_str().to_string()).collect(),
+            };
+
+            (k.to_string(), value)
+        })
+        .collect();
+
+    serde_json::Value::Object(map)
+}
+
+/// Microseconds between the Unix epoch and the Postgres epoch (2000-01-01 00:00:00 UTC)
+const POSTGRES_EPOCH_OFFSET_MICROS: i64 = 946_684_800_000_000;
+
+pub fn timestamptz_from_unix_nanos(nanos: i128) -> Option<pgrx::datum::TimestampWithTimeZone> {
+    let micros = i64::try_from(nanos / 1_000).ok()?;
+    let pg_micros = micros.checked_sub(POSTGRES_EPOCH_OFFSET_MICROS)?;
+
+    pgrx::datum::TimestampWithTimeZone::try_from(pg_micros).ok()
+}
+
+pub fn kv_operation_name(op: async_nats::jetstream::kv::Operation) -> &'static str {
+    use async_nats::jetstream::kv::Operation;
+
+    match op {
+        Operation::Put => "PUT",
+        Operation::Delete => "DEL",
+        Operation::Purge => "PURGE",
+    }
+}
+
 pub fn pack_oid_dsmh_to_i64(oid: sys::Oid, dsmh: DsmHandle) -> i64 {
     ((oid.to_u32() as u64) << 32 | (*dsmh as u64)) as i64
 }
@@ -158,8 +194,159 @@ pub fn is_extension_installed(name: &str) -> bool {
     })
 }
 
+/// Type of the payload argument of a subscription callback, or of the value returned
+/// by a responder.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CallbackPayload {
+    #[default]
+    Bytea,
+    Text,
+    Json,
+    Jsonb,
+}
+
+impl CallbackPayload {
+    pub fn from_type(type_oid: sys::Oid) -> Option<Self> {
+        if type_oid == sys::BYTEAOID {
+            Some(Self::Bytea)
+        } else if type_oid == sys::TEXTOID {
+            Some(Self::Text)
+        } else if type_oid == sys::JSONOID {
+            Some(Self::Json)
+        } else if type_oid == sys::JSONBOID {
+            Some(Self::Jsonb)
+        } else {
+            None
+        }
+    }
+
+    pub fn from_array_type(type_oid: sys::Oid) -> Option<Self> {
+        if type_oid == sys::BYTEAARRAYOID {
+            Some(Self::Bytea)
+        } else if type_oid == sys::TEXTARRAYOID {
+            Some(Self::Text)
+        } else if type_oid == sys::JSONARRAYOID {
+            Some(Self::Json)
+        } else if type_oid == sys::JSONBARRAYOID {
+            Some(Self::Jsonb)
+        } else {
+            None
+        }
+    }
+}
+
+/// Argument shape of a subscription callback, detected from the function's argument types.
+///
+/// A callback takes the payload as `bytea`, `text`, `json` or `jsonb`, optionally followed
+/// by `(subject text, headers jsonb, reply text)`. A batch callback takes arrays of the same
+/// types and receives a whole batch of messages in one call.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CallbackSignature {
+    pub payload: CallbackPayload,
+    pub with_message_info: bool,
+    pub batch: bool,
+}
+
+impl CallbackSignature {
+    pub fn from_arg_types(arg_types: &[sys::Oid]) -> Option<Self> {
+        let (payload, rest) = arg_types.split_first()?;
+        let (payload, batch) = match CallbackPayload::from_type(*payload) {
+            Some(payload) => (payload, false),
+            None => (CallbackPayload::from_array_type(*payload)?, true),
+        };
+
+        let (text_type, jsonb_type) = if batch {
+            (sys::TEXTARRAYOID, sys::JSONBARRAYOID)
+        } else {
+            (sys::TEXTOID, sys::JSONBOID)
+        };
+
+        let with_message_info = match rest {
+            [] => false,
+            [subject, headers, reply]
+                if *subject == text_type && *headers == jsonb_type && *reply == text_type =>
+            {
+                true
+            }
+            _ => return None,
+        };
+
+        Some(Self {
+            payload,
+            with_message_info,
+            batch,
+        })
+    }
+}
+
+pub fn resolve_callback_name(func_oid: sys::Oid) -> anyhow::Result<Option<String>> {
+    Ok(resolve_callback(func_oid)?.map(|(name, _)| name))
+}
+
+/// Resolves the name of a subscription callback and whether it is a batch callback.
+pub fn resolve_callback(func_oid: sys::Oid) -> anyhow::Result<Option<(String, bool)>> {
+    let Some((name, arg_types)) = resolve_function(func_oid)? else {
+        return Ok(None);
+    };
+
+    let signature = CallbackSignature::from_arg_types(&arg_types).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Argument types must be (payload) or (payload, subject text, headers jsonb, \
+             reply text), where payload is bytea, text, json or jsonb, or the same types as arrays"
+        )
+    })?;
+
+    Ok(Some((name, signature.batch)))
+}
+
+pub fn resolve_responder_name(func_oid: sys::Oid) -> anyhow::Result<Option<String>> {
+    let Some((name, batch)) = resolve_callback(func_oid)? else {
+        return Ok(None);
+    };
+
+    anyhow::ensure!(!batch, "Responders cannot take arrays of messages");
+
+    // SAFETY: `get_func_rettype` reads the function's syscache entry and raises a Postgres
+    // error for an unknown OID; the function was already found above.
+    let return_type = unsafe { sys::get_func_rettype(func_oid) };
+
+    anyhow::ensure!(
+        CallbackPayload::from_type(return_type).is_some(),
+        "Return type must be bytea, text, json or jsonb"
+    );
+
+    Ok(Some(name))
+}
+
+pub fn resolve_function_name(
+    func_oid: sys::Oid,
+    arg_types: &[sys::Oid],
+) -> anyhow::Result<Option<String>> {
+    let Some((name, actual_types)) = resolve_function(func_oid)? else {
+        return Ok(None);
+    };
+
+    anyhow::ensure!(
+        actual_types.len() == arg_types.len(),
+        "Argument count must be {}",
+        arg_types.len()
+    );
+    anyhow::ensure!(
+        actual_types == arg_types,
+        "Argument types must be ({})",
+        arg_types
+            .iter()
+            .map(|oid| format_type_name(*oid))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    Ok(Some(name))
+}
+
+/// Returns the schema-qualified name and the argument types of a function.
 #[allow(trivial_numeric_casts)]
-pub fn resolve_bytea_name(func_oid: sys::Oid) -> anyhow::Result<Option<String>> {
+fn resolve_function(func_oid: sys::Oid) -> anyhow::Result<Option<(String, Vec<sys::Oid>)>> {
     // SAFETY:
     // 1. All Postgres FFI calls follow documented lifetimes.
     // 2. `SearchSysCache` result is wrapped in `SysHeapTuple` to ensure proper release.
@@ -198,9 +385,12 @@ pub fn resolve_bytea_name(func_oid: sys::Oid) -> anyhow::Result<Option<String>>
             &mut p_argmodes,
         );
 
-        anyhow::ensure!(num_args == 1, "Argument count must be 1");
-        anyhow::ensure!(!p_argtypes.is_null(), "Postgres internal error");
-        anyhow::ensure!(*p_argtypes == sys::BYTEAOID, "Argument type must be bytea");
+        let arg_types = if num_args > 0 {
+            anyhow::ensure!(!p_argtypes.is_null(), "Postgres internal error");
+            std::slice::from_raw_parts(p_argtypes, num_args as usize).to_vec()
+        } else {
+            Vec::new()
+        };
 
         let fn_name = CStr::from_ptr(fn_name).to_string_lossy().to_string();
 
@@ -211,9 +401,24 @@ pub fn resolve_bytea_name(func_oid: sys::Oid) -> anyhow::Result<Option<String>>
         };
 
         if let Some(schema_name) = schema_name {
-            Ok(Some(format!("{schema_name}.{fn_name}")))
+            Ok(Some((format!("{schema_name}.{fn_name}"), arg_types)))
         } else {
-            Ok(Some(fn_name))
+            Ok(Some((fn_name, arg_types)))
+        }
+    }
+}
+
+fn format_type_name(type_oid: sys::Oid) -> String {
+    // SAFETY:
+    // 1. `format_type_be` returns a palloc'd null-terminated string for any type OID.
+    // 2. The pointer is checked for null before dereferencing.
+    unsafe {
+        let name = sys::format_type_be(type_oid);
+
+        if name.is_null() {
+            return type_oid.to_u32().to_string();
         }
+
+        CStr::from_ptr(name).to_string_lossy().to_string()
     }
 }